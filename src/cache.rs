@@ -0,0 +1,345 @@
+use prometheus::{register_int_counter_vec, IntCounterVec};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::CacheConfig,
+    models::{BucketInfo, CollectionManifest, UserDomain, UserInfo},
+};
+
+static CACHE_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "couchbase_admin_cache_requests_total",
+        "Management cache lookups by cache and result (hit, miss or bypass)",
+        &["cache", "result"]
+    )
+    .expect("cache metric registers once")
+});
+
+tokio::task_local! {
+    static BYPASS: bool;
+}
+
+/// Run `f` with cache reads disabled, e.g. for a request sent with `Cache-Control: no-cache`.
+/// Fresh results fetched inside `f` still replace what is cached.
+pub async fn bypass<F: Future>(f: F) -> F::Output {
+    BYPASS.scope(true, f).await
+}
+
+fn bypassed() -> bool {
+    BYPASS.try_with(|bypass| *bypass).unwrap_or(false)
+}
+
+struct Entry<T> {
+    value: T,
+    fetched_at: Instant,
+}
+
+/// Short-lived cache of management API reads.
+///
+/// Entries expire after the configured TTL and are dropped by this service's own
+/// mutations. Collection manifests are additionally checked against the manifest uid
+/// reported in every fresh bucket listing: an unchanged uid keeps the manifest,
+/// a changed one drops it.
+pub struct ManagementCache {
+    ttl: Option<Duration>,
+    buckets: RwLock<Option<Entry<Vec<BucketInfo>>>>,
+    manifests: RwLock<HashMap<String, Entry<CollectionManifest>>>,
+    users: RwLock<HashMap<Option<UserDomain>, Entry<Vec<UserInfo>>>>,
+    user_details: RwLock<HashMap<(UserDomain, String), Entry<UserInfo>>>,
+}
+
+impl ManagementCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        let ttl = (config.enabled && config.ttl_seconds > 0).then(|| Duration::from_secs(config.ttl_seconds));
+
+        Self {
+            ttl,
+            buckets: RwLock::new(None),
+            manifests: RwLock::new(HashMap::new()),
+            users: RwLock::new(HashMap::new()),
+            user_details: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn buckets(&self) -> Option<Vec<BucketInfo>> {
+        let ttl = self.lookup_ttl("buckets")?;
+        let entry = self.buckets.read().unwrap();
+        record("buckets", fresh(entry.as_ref(), ttl))
+    }
+
+    /// Store a fresh bucket listing; `manifest_uids` maps bucket name to its current manifest uid
+    pub fn put_buckets(&self, buckets: Vec<BucketInfo>, manifest_uids: &HashMap<String, String>) {
+        if self.ttl.is_none() {
+            return;
+        }
+
+        let now = Instant::now();
+        self.manifests.write().unwrap().retain(|bucket, entry| {
+            match manifest_uids.get(bucket) {
+                Some(uid) if *uid == entry.value.uid => {
+                    entry.fetched_at = now;
+                    true
+                }
+                _ => false,
+            }
+        });
+        *self.buckets.write().unwrap() = Some(Entry { value: buckets, fetched_at: now });
+    }
+
+    pub fn invalidate_buckets(&self) {
+        *self.buckets.write().unwrap() = None;
+    }
+
+    pub fn manifest(&self, bucket: &str) -> Option<CollectionManifest> {
+        let ttl = self.lookup_ttl("manifests")?;
+        let manifests = self.manifests.read().unwrap();
+        record("manifests", fresh(manifests.get(bucket), ttl))
+    }
+
+    pub fn put_manifest(&self, bucket: &str, manifest: CollectionManifest) {
+        put(self.ttl, &self.manifests, bucket.to_string(), manifest);
+    }
+
+    pub fn invalidate_manifest(&self, bucket: &str) {
+        self.manifests.write().unwrap().remove(bucket);
+    }
+
+    pub fn users(&self, domain: Option<UserDomain>) -> Option<Vec<UserInfo>> {
+        let ttl = self.lookup_ttl("users")?;
+        let users = self.users.read().unwrap();
+        record("users", fresh(users.get(&domain), ttl))
+    }
+
+    pub fn put_users(&self, domain: Option<UserDomain>, users: Vec<UserInfo>) {
+        put(self.ttl, &self.users, domain, users);
+    }
+
+    pub fn user(&self, domain: UserDomain, username: &str) -> Option<UserInfo> {
+        let ttl = self.lookup_ttl("users")?;
+        let users = self.user_details.read().unwrap();
+        record("users", fresh(users.get(&(domain, username.to_string())), ttl))
+    }
+
+    pub fn put_user(&self, user: UserInfo) {
+        put(self.ttl, &self.user_details, (user.domain, user.username.clone()), user);
+    }
+
+    /// Drop every cached user; listings cannot be patched for a single user
+    pub fn invalidate_users(&self) {
+        self.users.write().unwrap().clear();
+        self.user_details.write().unwrap().clear();
+    }
+
+    // The TTL to check entries against, or None when the cache is off or bypassed
+    fn lookup_ttl(&self, cache: &str) -> Option<Duration> {
+        let ttl = self.ttl?;
+        if bypassed() {
+            CACHE_REQUESTS.with_label_values(&[cache, "bypass"]).inc();
+            return None;
+        }
+        Some(ttl)
+    }
+}
+
+fn fresh<T: Clone>(entry: Option<&Entry<T>>, ttl: Duration) -> Option<T> {
+    entry
+        .filter(|entry| entry.fetched_at.elapsed() < ttl)
+        .map(|entry| entry.value.clone())
+}
+
+fn put<K: Eq + Hash, T>(ttl: Option<Duration>, map: &RwLock<HashMap<K, Entry<T>>>, key: K, value: T) {
+    if ttl.is_some() {
+        map.write()
+            .unwrap()
+            .insert(key, Entry { value, fetched_at: Instant::now() });
+    }
+}
+
+fn record<T>(cache: &str, value: Option<T>) -> Option<T> {
+    let result = if value.is_some() { "hit" } else { "miss" };
+    CACHE_REQUESTS.with_label_values(&[cache, result]).inc();
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BucketType, ScopeInfo};
+
+    fn cache(ttl: Duration) -> ManagementCache {
+        let mut cache = ManagementCache::new(&CacheConfig {
+            enabled: true,
+            ttl_seconds: 60,
+        });
+        cache.ttl = Some(ttl);
+        cache
+    }
+
+    fn bucket(name: &str) -> BucketInfo {
+        BucketInfo {
+            name: name.to_string(),
+            bucket_type: BucketType::Couchbase,
+            ram_quota_mb: 100,
+            replica_number: 1,
+            eviction_policy: "valueOnly".to_string(),
+            compression_mode: "passive".to_string(),
+            conflict_resolution_type: "seqno".to_string(),
+            storage_backend: None,
+            max_ttl: 0,
+            flush_enabled: false,
+            durability_min_level: None,
+            replica_index: None,
+            history_retention_collection_default: None,
+            history_retention_bytes: None,
+            history_retention_seconds: None,
+            num_vbuckets: None,
+            status: "healthy".to_string(),
+        }
+    }
+
+    fn manifest(uid: &str) -> CollectionManifest {
+        CollectionManifest {
+            uid: uid.to_string(),
+            scopes: vec![ScopeInfo {
+                name: "_default".to_string(),
+                collections: vec![],
+            }],
+        }
+    }
+
+    fn user(username: &str) -> UserInfo {
+        UserInfo {
+            username: username.to_string(),
+            domain: UserDomain::Local,
+            roles: vec![],
+            groups: vec![],
+            display_name: None,
+        }
+    }
+
+    fn uids(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(b, uid)| (b.to_string(), uid.to_string())).collect()
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let cache = cache(Duration::from_millis(50));
+        cache.put_buckets(vec![bucket("travel")], &HashMap::new());
+        cache.put_manifest("travel", manifest("1"));
+        cache.put_user(user("alice"));
+
+        assert_eq!(cache.buckets().unwrap()[0].name, "travel");
+        assert_eq!(cache.manifest("travel").unwrap().uid, "1");
+        assert!(cache.user(UserDomain::Local, "alice").is_some());
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(cache.buckets().is_none());
+        assert!(cache.manifest("travel").is_none());
+        assert!(cache.user(UserDomain::Local, "alice").is_none());
+    }
+
+    #[test]
+    fn disabled_cache_stores_nothing() {
+        let cache = ManagementCache::new(&CacheConfig {
+            enabled: false,
+            ttl_seconds: 60,
+        });
+        cache.put_buckets(vec![bucket("travel")], &HashMap::new());
+        cache.put_manifest("travel", manifest("1"));
+        assert!(cache.buckets().is_none());
+        assert!(cache.manifest("travel").is_none());
+    }
+
+    #[test]
+    fn writes_invalidate_entries() {
+        let cache = cache(Duration::from_secs(60));
+        cache.put_buckets(vec![bucket("travel")], &HashMap::new());
+        cache.put_manifest("travel", manifest("1"));
+        cache.put_manifest("other", manifest("7"));
+        cache.put_users(None, vec![user("alice")]);
+        cache.put_users(Some(UserDomain::Local), vec![user("alice")]);
+        cache.put_user(user("alice"));
+
+        cache.invalidate_buckets();
+        assert!(cache.buckets().is_none());
+
+        cache.invalidate_manifest("travel");
+        assert!(cache.manifest("travel").is_none());
+        assert!(cache.manifest("other").is_some());
+
+        cache.invalidate_users();
+        assert!(cache.users(None).is_none());
+        assert!(cache.users(Some(UserDomain::Local)).is_none());
+        assert!(cache.user(UserDomain::Local, "alice").is_none());
+    }
+
+    #[test]
+    fn bucket_listing_refreshes_or_drops_manifests_by_uid() {
+        let cache = cache(Duration::from_millis(100));
+        cache.put_manifest("same", manifest("1"));
+        cache.put_manifest("changed", manifest("1"));
+        cache.put_manifest("deleted", manifest("1"));
+
+        std::thread::sleep(Duration::from_millis(60));
+        cache.put_buckets(
+            vec![bucket("same"), bucket("changed")],
+            &uids(&[("same", "1"), ("changed", "2")]),
+        );
+        assert!(cache.manifest("changed").is_none());
+        assert!(cache.manifest("deleted").is_none());
+
+        // An unchanged uid keeps the manifest and restarts its TTL
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.manifest("same").unwrap().uid, "1");
+    }
+
+    #[tokio::test]
+    async fn bypass_skips_reads_but_keeps_fresh_results() {
+        let cache = cache(Duration::from_secs(60));
+        cache.put_buckets(vec![bucket("stale")], &HashMap::new());
+
+        bypass(async {
+            assert!(cache.buckets().is_none());
+            cache.put_buckets(vec![bucket("fresh")], &HashMap::new());
+            assert!(cache.buckets().is_none());
+        })
+        .await;
+
+        assert_eq!(cache.buckets().unwrap()[0].name, "fresh");
+    }
+
+    #[tokio::test]
+    async fn no_cache_header_bypasses_the_cache() {
+        use axum::{routing::get, Router};
+
+        async fn report() -> String {
+            bypassed().to_string()
+        }
+
+        let app = Router::new()
+            .route("/", get(report))
+            .layer(axum::middleware::from_fn(crate::middleware::cache_control_middleware));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        for (cache_control, expected) in [
+            (None, "false"),
+            (Some("max-age=0"), "false"),
+            (Some("no-cache"), "true"),
+            (Some("max-age=0, No-Cache"), "true"),
+        ] {
+            let mut request = client.get(&url);
+            if let Some(value) = cache_control {
+                request = request.header("cache-control", value);
+            }
+            let body = request.send().await.unwrap().text().await.unwrap();
+            assert_eq!(body, expected, "{:?}", cache_control);
+        }
+    }
+}
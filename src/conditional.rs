@@ -0,0 +1,141 @@
+use axum::{
+    http::{
+        header::{ETAG, IF_MATCH, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    cache,
+    error::{AppError, Result},
+    services::CouchbaseService,
+};
+
+/// Strong ETag for a bucket's collection manifest uid
+pub fn manifest_etag(uid: &str) -> String {
+    format!("\"{}\"", uid)
+}
+
+/// Whether the client's `If-None-Match` already names `etag`
+pub fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    header_matches(headers, IF_NONE_MATCH.as_str(), etag, true).unwrap_or(false)
+}
+
+pub fn not_modified(etag: &str) -> Response {
+    with_etag(StatusCode::NOT_MODIFIED.into_response(), etag)
+}
+
+pub fn with_etag(mut response: Response, etag: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(ETAG, value);
+    }
+    response
+}
+
+/// Enforce `If-Match` against the bucket's current manifest, read past the cache.
+///
+/// Couchbase has no conditional manifest update, so this narrows rather than closes
+/// the window between the check and the change.
+pub async fn check_manifest_if_match(
+    headers: &HeaderMap,
+    couchbase_service: &CouchbaseService,
+    bucket: &str,
+) -> Result<()> {
+    if !headers.contains_key(IF_MATCH) {
+        return Ok(());
+    }
+
+    let manifest = cache::bypass(couchbase_service.get_collection_manifest(bucket)).await?;
+    let etag = manifest_etag(&manifest.uid);
+
+    match header_matches(headers, IF_MATCH.as_str(), &etag, false) {
+        Some(true) => Ok(()),
+        _ => Err(AppError::PreconditionFailed(format!(
+            "Manifest of bucket '{}' has changed; current ETag is {}",
+            bucket, etag
+        ))),
+    }
+}
+
+// None when the header is absent. `*` matches any ETag; weak tags only match when
+// `weak` comparison is allowed, as for If-None-Match.
+fn header_matches(headers: &HeaderMap, name: &str, etag: &str, weak: bool) -> Option<bool> {
+    let values: Vec<&str> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    if values.is_empty() {
+        return None;
+    }
+
+    Some(
+        values
+            .iter()
+            .any(|value| *value == "*" || *value == etag || (weak && value.strip_prefix("W/") == Some(etag))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: &str, values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(
+                axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn absent_header_matches_nothing() {
+        assert_eq!(header_matches(&HeaderMap::new(), "if-match", "\"1\"", false), None);
+        assert!(!is_not_modified(&HeaderMap::new(), "\"1\""));
+    }
+
+    #[test]
+    fn lists_and_repeated_headers_are_searched() {
+        let etag = manifest_etag("1a");
+        assert_eq!(etag, "\"1a\"");
+
+        for values in [
+            vec!["\"1a\""],
+            vec!["\"0\", \"1a\""],
+            vec!["\"0\",\"1a\" , \"2\""],
+            vec!["\"0\"", "\"1a\""],
+            vec!["*"],
+        ] {
+            assert_eq!(header_matches(&headers("if-match", &values), "if-match", &etag, false), Some(true), "{:?}", values);
+        }
+        for values in [vec!["\"1\""], vec!["1a"], vec!["\"1a"], vec!["\"0\", \"2\""], vec![""]] {
+            assert_eq!(header_matches(&headers("if-match", &values), "if-match", &etag, false), Some(false), "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn weak_tags_only_match_weakly() {
+        let etag = manifest_etag("1a");
+        let weak = headers("if-none-match", &["W/\"1a\""]);
+        assert_eq!(header_matches(&weak, "if-none-match", &etag, true), Some(true));
+        assert_eq!(header_matches(&weak, "if-none-match", &etag, false), Some(false));
+        assert!(is_not_modified(&weak, &etag));
+
+        // If-Match uses strong comparison
+        let weak = headers("if-match", &["W/\"1a\""]);
+        assert_eq!(header_matches(&weak, "if-match", &etag, false), Some(false));
+    }
+
+    #[test]
+    fn empty_tag_only_matches_itself() {
+        let empty = headers("if-match", &["\"\""]);
+        assert_eq!(header_matches(&empty, "if-match", &manifest_etag("1a"), false), Some(false));
+    }
+}
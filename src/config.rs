@@ -1,11 +1,25 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 
+use crate::models::RoleTemplate;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub server: ServerConfig,
     pub couchbase: CouchbaseConfig,
     pub auth: AuthConfig,
+    pub grants: GrantsConfig,
+    pub password_policy: PasswordPolicyConfig,
+    pub rotation: RotationConfig,
+    pub cache: CacheConfig,
+    pub idempotency: IdempotencyConfig,
+    pub rate_limit: RateLimitConfig,
+    pub logging: LoggingConfig,
+    pub telemetry: TelemetryConfig,
+    pub query: QueryConfig,
+    #[serde(default)]
+    pub role_templates: HashMap<String, RoleTemplate>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,9 +31,94 @@ pub struct ServerConfig {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CouchbaseConfig {
     pub host: String,
+    /// Query service URL, used for index management
+    pub query_host: String,
+    /// Search (FTS) service URL
+    pub search_host: String,
+    /// Eventing service URL
+    pub eventing_host: String,
+    /// Analytics service URL
+    pub analytics_host: String,
     pub username: String,
     pub password: String,
     pub timeout_seconds: u64,
+    pub role_catalog_refresh_seconds: u64,
+    /// Maximum number of buckets fetched in parallel when building the topology
+    pub topology_concurrency: usize,
+    /// Maximum number of concurrent management calls to the cluster
+    pub max_concurrent_requests: usize,
+}
+
+/// Short-TTL cache of bucket, scope and user reads in the service layer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub ttl_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IdempotencyConfig {
+    /// How long a key and its response are remembered
+    pub ttl_seconds: u64,
+    /// Optional JSON file to persist completed keys across restarts
+    pub store_path: Option<String>,
+    /// Most keys remembered at once; the oldest completed keys are evicted first
+    pub max_entries: usize,
+    /// Most stored response bytes kept at once; larger responses are not stored
+    pub max_bytes: usize,
+}
+
+/// Token-bucket limits per authenticated user, or per client IP without one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub requests_per_second: f64,
+    pub burst: u32,
+    /// Overrides keyed by method and route, e.g. `POST /users` or `GET /buckets/:bucket/scopes`
+    #[serde(default)]
+    pub routes: HashMap<String, RouteRateLimit>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RouteRateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+/// Log output; levels are controlled with `RUST_LOG`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// OpenTelemetry trace export over OTLP/HTTP
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Collector base URL; spans are posted to `{otlp_endpoint}/v1/traces`
+    pub otlp_endpoint: String,
+    pub service_name: String,
+}
+
+/// Guardrails for `POST /query`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueryConfig {
+    /// Reject DML and DDL for every query; when false callers may opt out per request
+    pub read_only: bool,
+    /// Account queries run as unless the caller forwards their own Couchbase credentials.
+    /// When unset, queries without forwarded credentials are refused with 401.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub timeout_seconds: u64,
+    pub max_timeout_seconds: u64,
+    pub max_rows: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,22 +128,117 @@ pub struct AuthConfig {
     pub password: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrantsConfig {
+    pub store_path: String,
+    pub audit_log_path: String,
+    pub reap_interval_seconds: u64,
+    pub max_duration_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digits: bool,
+    pub require_special_chars: bool,
+    /// Additional passwords to reject, on top of the built-in common passwords
+    #[serde(default)]
+    pub denylist: Vec<String>,
+    /// File with one denylisted password per line
+    pub denylist_file: Option<String>,
+    /// Also enforce the cluster's `/settings/passwordPolicy`, whichever is stricter
+    pub sync_with_cluster: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RotationConfig {
+    pub policy_store_path: String,
+    pub check_interval_seconds: u64,
+    /// Timeout for each request to a webhook sink
+    pub sink_timeout_seconds: u64,
+    /// Named destinations for rotated credentials, referenced by rotation requests
+    #[serde(default)]
+    pub sinks: HashMap<String, SecretSinkConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SecretSinkConfig {
+    /// Write the credentials as JSON to a file; `{username}` is substituted in the path
+    File { path: String },
+    /// Write a Kubernetes Secret manifest to a file; `{username}` is substituted in the path
+    KubernetesManifest {
+        path: String,
+        namespace: String,
+        /// Secret name; `{username}` is substituted. Defaults to `{username}-couchbase`
+        name: Option<String>,
+    },
+    /// POST the credentials as JSON to a URL
+    Webhook {
+        url: String,
+        bearer_token: Option<String>,
+    },
+}
+
 impl Config {
     pub fn load() -> Result<Self, config::ConfigError> {
         // Load .env file if it exists
         dotenv::dotenv().ok();
 
+        // Optional config file (config.yaml, config.json, ...) for structured settings
+        // such as role templates
+        let config_file = env::var("CONFIG_FILE").unwrap_or_else(|_| "config".to_string());
+
         let mut settings = config::Config::builder()
+            .add_source(config::File::with_name(&config_file).required(false))
             .add_source(config::Environment::default())
             .set_default("server.port", 8080)?
             .set_default("server.host", "0.0.0.0")?
             .set_default("couchbase.host", "http://localhost:8091")?
+            .set_default("couchbase.query_host", "http://localhost:8093")?
+            .set_default("couchbase.search_host", "http://localhost:8094")?
+            .set_default("couchbase.eventing_host", "http://localhost:8096")?
+            .set_default("couchbase.analytics_host", "http://localhost:8095")?
             .set_default("couchbase.username", "Administrator")?
             .set_default("couchbase.password", "password")?
             .set_default("couchbase.timeout_seconds", 30)?
+            .set_default("couchbase.role_catalog_refresh_seconds", 300)?
+            .set_default("couchbase.topology_concurrency", 8)?
+            .set_default("couchbase.max_concurrent_requests", 16)?
+            .set_default("cache.enabled", true)?
+            .set_default("cache.ttl_seconds", 10)?
+            .set_default("idempotency.ttl_seconds", 24 * 3600)?
+            .set_default("idempotency.max_entries", 10_000)?
+            .set_default("idempotency.max_bytes", 64 * 1024 * 1024)?
+            .set_default("rate_limit.enabled", true)?
+            .set_default("rate_limit.requests_per_second", 20.0)?
+            .set_default("rate_limit.burst", 40)?
+            .set_default("logging.format", "text")?
+            .set_default("telemetry.enabled", false)?
+            .set_default("telemetry.otlp_endpoint", "http://localhost:4318")?
+            .set_default("telemetry.service_name", "couchbase-admin-service")?
+            .set_default("query.read_only", true)?
+            .set_default("query.timeout_seconds", 30)?
+            .set_default("query.max_timeout_seconds", 300)?
+            .set_default("query.max_rows", 10_000)?
             .set_default("auth.enabled", true)?
             .set_default("auth.username", "admin")?
-            .set_default("auth.password", "admin")?;
+            .set_default("auth.password", "admin")?
+            .set_default("grants.store_path", "data/grants.json")?
+            .set_default("grants.audit_log_path", "data/grants-audit.jsonl")?
+            .set_default("grants.reap_interval_seconds", 30)?
+            .set_default("grants.max_duration_seconds", 7 * 24 * 3600)?
+            .set_default("password_policy.min_length", 8)?
+            .set_default("password_policy.require_uppercase", false)?
+            .set_default("password_policy.require_lowercase", false)?
+            .set_default("password_policy.require_digits", false)?
+            .set_default("password_policy.require_special_chars", false)?
+            .set_default("password_policy.sync_with_cluster", true)?
+            .set_default("rotation.policy_store_path", "data/rotation-policies.json")?
+            .set_default("rotation.check_interval_seconds", 60)?
+            .set_default("rotation.sink_timeout_seconds", 30)?;
 
         // Override with environment variables
         if let Ok(port) = env::var("PORT") {
@@ -57,6 +251,22 @@ impl Config {
             settings = settings.set_override("couchbase.host", host)?;
         }
 
+        if let Ok(query_host) = env::var("COUCHBASE_QUERY_HOST") {
+            settings = settings.set_override("couchbase.query_host", query_host)?;
+        }
+
+        if let Ok(search_host) = env::var("COUCHBASE_SEARCH_HOST") {
+            settings = settings.set_override("couchbase.search_host", search_host)?;
+        }
+
+        if let Ok(eventing_host) = env::var("COUCHBASE_EVENTING_HOST") {
+            settings = settings.set_override("couchbase.eventing_host", eventing_host)?;
+        }
+
+        if let Ok(analytics_host) = env::var("COUCHBASE_ANALYTICS_HOST") {
+            settings = settings.set_override("couchbase.analytics_host", analytics_host)?;
+        }
+
         if let Ok(username) = env::var("COUCHBASE_USERNAME") {
             settings = settings.set_override("couchbase.username", username)?;
         }
@@ -71,6 +281,126 @@ impl Config {
             }
         }
 
+        if let Ok(refresh) = env::var("COUCHBASE_ROLE_CATALOG_REFRESH_SECONDS") {
+            if let Ok(refresh) = refresh.parse::<u64>() {
+                settings = settings.set_override("couchbase.role_catalog_refresh_seconds", refresh)?;
+            }
+        }
+
+        if let Ok(concurrency) = env::var("COUCHBASE_TOPOLOGY_CONCURRENCY") {
+            if let Ok(concurrency) = concurrency.parse::<usize>() {
+                settings = settings.set_override("couchbase.topology_concurrency", concurrency as u64)?;
+            }
+        }
+
+        if let Ok(enabled) = env::var("CACHE_ENABLED") {
+            if let Ok(enabled) = enabled.parse::<bool>() {
+                settings = settings.set_override("cache.enabled", enabled)?;
+            }
+        }
+
+        if let Ok(ttl) = env::var("CACHE_TTL_SECONDS") {
+            if let Ok(ttl) = ttl.parse::<u64>() {
+                settings = settings.set_override("cache.ttl_seconds", ttl)?;
+            }
+        }
+
+        if let Ok(ttl) = env::var("IDEMPOTENCY_TTL_SECONDS") {
+            if let Ok(ttl) = ttl.parse::<u64>() {
+                settings = settings.set_override("idempotency.ttl_seconds", ttl)?;
+            }
+        }
+
+        if let Ok(path) = env::var("IDEMPOTENCY_STORE_PATH") {
+            settings = settings.set_override("idempotency.store_path", path)?;
+        }
+
+        if let Ok(max) = env::var("IDEMPOTENCY_MAX_ENTRIES") {
+            if let Ok(max) = max.parse::<u64>() {
+                settings = settings.set_override("idempotency.max_entries", max)?;
+            }
+        }
+
+        if let Ok(max) = env::var("IDEMPOTENCY_MAX_BYTES") {
+            if let Ok(max) = max.parse::<u64>() {
+                settings = settings.set_override("idempotency.max_bytes", max)?;
+            }
+        }
+
+        if let Ok(max) = env::var("COUCHBASE_MAX_CONCURRENT_REQUESTS") {
+            if let Ok(max) = max.parse::<u64>() {
+                settings = settings.set_override("couchbase.max_concurrent_requests", max)?;
+            }
+        }
+
+        if let Ok(enabled) = env::var("RATE_LIMIT_ENABLED") {
+            if let Ok(enabled) = enabled.parse::<bool>() {
+                settings = settings.set_override("rate_limit.enabled", enabled)?;
+            }
+        }
+
+        if let Ok(rate) = env::var("RATE_LIMIT_REQUESTS_PER_SECOND") {
+            if let Ok(rate) = rate.parse::<f64>() {
+                settings = settings.set_override("rate_limit.requests_per_second", rate)?;
+            }
+        }
+
+        if let Ok(burst) = env::var("RATE_LIMIT_BURST") {
+            if let Ok(burst) = burst.parse::<u64>() {
+                settings = settings.set_override("rate_limit.burst", burst)?;
+            }
+        }
+
+        if let Ok(format) = env::var("LOG_FORMAT") {
+            settings = settings.set_override("logging.format", format.to_lowercase())?;
+        }
+
+        if let Ok(enabled) = env::var("OTEL_ENABLED") {
+            if let Ok(enabled) = enabled.parse::<bool>() {
+                settings = settings.set_override("telemetry.enabled", enabled)?;
+            }
+        }
+
+        if let Ok(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            settings = settings.set_override("telemetry.otlp_endpoint", endpoint)?;
+        }
+
+        if let Ok(service_name) = env::var("OTEL_SERVICE_NAME") {
+            settings = settings.set_override("telemetry.service_name", service_name)?;
+        }
+
+        if let Ok(read_only) = env::var("QUERY_READ_ONLY") {
+            if let Ok(read_only) = read_only.parse::<bool>() {
+                settings = settings.set_override("query.read_only", read_only)?;
+            }
+        }
+
+        if let Ok(username) = env::var("QUERY_USERNAME") {
+            settings = settings.set_override("query.username", username)?;
+        }
+
+        if let Ok(password) = env::var("QUERY_PASSWORD") {
+            settings = settings.set_override("query.password", password)?;
+        }
+
+        if let Ok(timeout) = env::var("QUERY_TIMEOUT_SECONDS") {
+            if let Ok(timeout) = timeout.parse::<u64>() {
+                settings = settings.set_override("query.timeout_seconds", timeout)?;
+            }
+        }
+
+        if let Ok(timeout) = env::var("QUERY_MAX_TIMEOUT_SECONDS") {
+            if let Ok(timeout) = timeout.parse::<u64>() {
+                settings = settings.set_override("query.max_timeout_seconds", timeout)?;
+            }
+        }
+
+        if let Ok(max_rows) = env::var("QUERY_MAX_ROWS") {
+            if let Ok(max_rows) = max_rows.parse::<u64>() {
+                settings = settings.set_override("query.max_rows", max_rows)?;
+            }
+        }
+
         if let Ok(enabled) = env::var("AUTH_ENABLED") {
             if let Ok(enabled) = enabled.parse::<bool>() {
                 settings = settings.set_override("auth.enabled", enabled)?;
@@ -85,6 +415,73 @@ impl Config {
             settings = settings.set_override("auth.password", password)?;
         }
 
-        settings.build()?.try_deserialize()
+        if let Ok(path) = env::var("GRANTS_STORE_PATH") {
+            settings = settings.set_override("grants.store_path", path)?;
+        }
+
+        if let Ok(path) = env::var("GRANTS_AUDIT_LOG_PATH") {
+            settings = settings.set_override("grants.audit_log_path", path)?;
+        }
+
+        if let Ok(interval) = env::var("GRANTS_REAP_INTERVAL_SECONDS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                settings = settings.set_override("grants.reap_interval_seconds", interval)?;
+            }
+        }
+
+        if let Ok(max) = env::var("GRANTS_MAX_DURATION_SECONDS") {
+            if let Ok(max) = max.parse::<u64>() {
+                settings = settings.set_override("grants.max_duration_seconds", max)?;
+            }
+        }
+
+        if let Ok(min_length) = env::var("PASSWORD_MIN_LENGTH") {
+            if let Ok(min_length) = min_length.parse::<u64>() {
+                settings = settings.set_override("password_policy.min_length", min_length)?;
+            }
+        }
+
+        for (var, key) in [
+            ("PASSWORD_REQUIRE_UPPERCASE", "password_policy.require_uppercase"),
+            ("PASSWORD_REQUIRE_LOWERCASE", "password_policy.require_lowercase"),
+            ("PASSWORD_REQUIRE_DIGITS", "password_policy.require_digits"),
+            ("PASSWORD_REQUIRE_SPECIAL_CHARS", "password_policy.require_special_chars"),
+            ("PASSWORD_POLICY_SYNC_WITH_CLUSTER", "password_policy.sync_with_cluster"),
+        ] {
+            if let Ok(value) = env::var(var) {
+                if let Ok(value) = value.parse::<bool>() {
+                    settings = settings.set_override(key, value)?;
+                }
+            }
+        }
+
+        if let Ok(path) = env::var("PASSWORD_DENYLIST_FILE") {
+            settings = settings.set_override("password_policy.denylist_file", path)?;
+        }
+
+        if let Ok(path) = env::var("ROTATION_POLICY_STORE_PATH") {
+            settings = settings.set_override("rotation.policy_store_path", path)?;
+        }
+
+        if let Ok(interval) = env::var("ROTATION_CHECK_INTERVAL_SECONDS") {
+            if let Ok(interval) = interval.parse::<u64>() {
+                settings = settings.set_override("rotation.check_interval_seconds", interval)?;
+            }
+        }
+
+        if let Ok(timeout) = env::var("ROTATION_SINK_TIMEOUT_SECONDS") {
+            if let Ok(timeout) = timeout.parse::<u64>() {
+                settings = settings.set_override("rotation.sink_timeout_seconds", timeout)?;
+            }
+        }
+
+        let mut config: Config = settings.build()?.try_deserialize()?;
+
+        // Built-in templates apply unless a configured template of the same name replaces them
+        for (name, template) in RoleTemplate::builtin() {
+            config.role_templates.entry(name).or_insert(template);
+        }
+
+        Ok(config)
     }
 }
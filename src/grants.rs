@@ -0,0 +1,488 @@
+use chrono::{DateTime, Duration, Utc};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    config::GrantsConfig,
+    error::{AppError, Result},
+    models::{Grant, GrantAction, GrantAuditEvent, Role, UserDomain},
+    services::CouchbaseService,
+    storage,
+};
+
+/// A grant to add, before its roles are checked against the user's current roles
+pub struct NewGrant {
+    pub domain: UserDomain,
+    pub username: String,
+    pub roles: Vec<Role>,
+    pub duration: Duration,
+    pub reason: Option<String>,
+    pub granted_by: Option<String>,
+}
+
+/// File-backed store of temporary role grants.
+///
+/// Active grants are kept in a JSON file so they survive restarts; every grant,
+/// revocation and expiry is appended to a JSON-lines audit log. The lock is held
+/// across the Couchbase role update so concurrent grants for the same user and
+/// the reaper never interleave their read-modify-write of the user's roles.
+pub struct GrantStore {
+    store_path: PathBuf,
+    audit_log_path: PathBuf,
+    max_duration: Duration,
+    grants: Mutex<Vec<Grant>>,
+}
+
+impl GrantStore {
+    pub async fn open(config: &GrantsConfig) -> Result<Self> {
+        let store_path = PathBuf::from(&config.store_path);
+        let grants = storage::read_json_or_default(&store_path).await?;
+
+        Ok(Self {
+            store_path,
+            audit_log_path: PathBuf::from(&config.audit_log_path),
+            max_duration: Duration::seconds(config.max_duration_seconds as i64),
+            grants: Mutex::new(grants),
+        })
+    }
+
+    pub async fn list(&self, username: Option<&str>, domain: Option<UserDomain>) -> Vec<Grant> {
+        self.grants
+            .lock()
+            .await
+            .iter()
+            .filter(|g| username.is_none_or(|u| g.username == u))
+            .filter(|g| domain.is_none_or(|d| g.domain == d))
+            .cloned()
+            .collect()
+    }
+
+    /// Add the grant's roles to the user for its duration. Roles the user already
+    /// holds directly are left out of the grant so that expiry never removes a base role.
+    /// The grant is recorded before the roles are applied, so roles are never added
+    /// without a grant that will take them away again.
+    pub async fn grant(&self, couchbase_service: &CouchbaseService, request: NewGrant) -> Result<Grant> {
+        let NewGrant {
+            domain,
+            username,
+            roles,
+            duration,
+            reason,
+            granted_by,
+        } = request;
+
+        if duration > self.max_duration {
+            return Err(AppError::Validation(format!(
+                "Grant duration exceeds the maximum of {} seconds",
+                self.max_duration.num_seconds()
+            )));
+        }
+
+        let mut grants = self.grants.lock().await;
+
+        let mut current = couchbase_service.get_user_direct_roles(domain, &username).await?;
+        let added = roles_to_add(&current, roles);
+
+        if added.is_empty() {
+            return Err(AppError::Validation(format!(
+                "User '{}' already holds all of the requested roles",
+                username
+            )));
+        }
+
+        let now = Utc::now();
+        let grant = Grant {
+            id: uuid::Uuid::new_v4().to_string(),
+            username,
+            domain,
+            roles: added,
+            reason,
+            granted_by: granted_by.clone(),
+            created_at: now,
+            expires_at: now + duration,
+        };
+
+        grants.push(grant.clone());
+        if let Err(e) = self.persist(&grants).await {
+            grants.pop();
+            return Err(e);
+        }
+
+        current.extend(grant.roles.iter().cloned());
+        if let Err(e) = couchbase_service.set_user_roles(domain, &grant.username, &current).await {
+            grants.pop();
+            // A grant left in the file only makes the reaper remove roles the user lacks
+            if let Err(persist_error) = self.persist(&grants).await {
+                warn!("Failed to drop grant {} after its roles were not applied: {}", grant.id, persist_error);
+            }
+            return Err(e);
+        }
+
+        self.audit(GrantAction::Granted, &grant, granted_by).await;
+
+        Ok(grant)
+    }
+
+    /// Remove a grant before it expires
+    pub async fn revoke(
+        &self,
+        couchbase_service: &CouchbaseService,
+        domain: UserDomain,
+        username: &str,
+        grant_id: &str,
+        actor: Option<String>,
+    ) -> Result<Grant> {
+        let mut grants = self.grants.lock().await;
+
+        let index = grants
+            .iter()
+            .position(|g| g.id == grant_id && g.username == username && g.domain == domain)
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Grant '{}' not found for user '{}'", grant_id, username))
+            })?;
+
+        let grant = grants[index].clone();
+        self.remove_granted_roles(couchbase_service, &grants, &grant).await?;
+
+        grants.remove(index);
+        self.persist(&grants).await?;
+        self.audit(GrantAction::Revoked, &grant, actor).await;
+
+        Ok(grant)
+    }
+
+    /// Remove every grant whose expiry has passed. Failed removals stay in the
+    /// store and are retried on the next pass.
+    pub async fn reap_expired(&self, couchbase_service: &CouchbaseService) {
+        let mut grants = self.grants.lock().await;
+
+        let expired = expired_grants(&grants, Utc::now());
+        if expired.is_empty() {
+            return;
+        }
+
+        for grant in expired {
+            match self.remove_granted_roles(couchbase_service, &grants, &grant).await {
+                Ok(()) | Err(AppError::NotFound(_)) => {
+                    grants.retain(|g| g.id != grant.id);
+                    info!("Grant {} for user '{}' expired", grant.id, grant.username);
+                    self.audit(GrantAction::Expired, &grant, None).await;
+                }
+                Err(e) => {
+                    warn!("Failed to remove expired grant {} for user '{}': {}", grant.id, grant.username, e);
+                }
+            }
+        }
+
+        if let Err(e) = self.persist(&grants).await {
+            warn!("Failed to persist grant store: {}", e);
+        }
+    }
+
+    pub async fn run_reaper(self: Arc<Self>, couchbase_service: CouchbaseService, interval: std::time::Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.reap_expired(&couchbase_service).await;
+        }
+    }
+
+    pub async fn audit_events(
+        &self,
+        username: Option<&str>,
+        domain: Option<UserDomain>,
+    ) -> Result<Vec<GrantAuditEvent>> {
+        let events: Vec<GrantAuditEvent> = storage::read_json_lines(&self.audit_log_path).await?;
+
+        Ok(events
+            .into_iter()
+            .filter(|e| username.is_none_or(|u| e.username == u))
+            .filter(|e| domain.is_none_or(|d| e.domain == d))
+            .collect())
+    }
+
+    // Take the grant's roles off the user, keeping any still covered by another active grant
+    async fn remove_granted_roles(
+        &self,
+        couchbase_service: &CouchbaseService,
+        grants: &[Grant],
+        grant: &Grant,
+    ) -> Result<()> {
+        let current = couchbase_service
+            .get_user_direct_roles(grant.domain, &grant.username)
+            .await?;
+        let remaining = remaining_roles(&current, grants, grant);
+
+        if remaining.len() != current.len() {
+            couchbase_service
+                .set_user_roles(grant.domain, &grant.username, &remaining)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn persist(&self, grants: &[Grant]) -> Result<()> {
+        storage::write_json_atomic(&self.store_path, grants).await
+    }
+
+    async fn audit(&self, action: GrantAction, grant: &Grant, actor: Option<String>) {
+        let event = GrantAuditEvent {
+            timestamp: Utc::now(),
+            action,
+            grant_id: grant.id.clone(),
+            username: grant.username.clone(),
+            domain: grant.domain,
+            roles: grant.roles.clone(),
+            actor,
+        };
+
+        if let Err(e) = storage::append_json_line(&self.audit_log_path, &event).await {
+            warn!("Failed to write grant audit event: {}", e);
+        }
+    }
+}
+
+// Requested roles the user does not already hold, without duplicates
+fn roles_to_add(current: &[Role], requested: Vec<Role>) -> Vec<Role> {
+    requested.into_iter().fold(Vec::new(), |mut added, role| {
+        if !current.contains(&role) && !added.contains(&role) {
+            added.push(role);
+        }
+        added
+    })
+}
+
+fn expired_grants(grants: &[Grant], now: DateTime<Utc>) -> Vec<Grant> {
+    grants.iter().filter(|g| g.expires_at <= now).cloned().collect()
+}
+
+// The user's roles once `grant` is removed; roles another active grant of the
+// same user also added are kept
+fn remaining_roles(current: &[Role], grants: &[Grant], grant: &Grant) -> Vec<Role> {
+    let still_granted: Vec<&Role> = grants
+        .iter()
+        .filter(|g| g.id != grant.id && g.username == grant.username && g.domain == grant.domain)
+        .flat_map(|g| g.roles.iter())
+        .collect();
+
+    current
+        .iter()
+        .filter(|role| !grant.roles.contains(role) || still_granted.contains(role))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::parse_duration;
+    use crate::services::testing::{fake_cluster, puts, Fixture, Method};
+    use std::path::Path;
+
+    fn role(spec: &str) -> Role {
+        Role::parse_spec(spec).unwrap()
+    }
+
+    fn grant(id: &str, username: &str, roles: &[&str], expires_in_seconds: i64) -> Grant {
+        let now = Utc::now();
+        Grant {
+            id: id.to_string(),
+            username: username.to_string(),
+            domain: UserDomain::Local,
+            roles: roles.iter().map(|spec| role(spec)).collect(),
+            reason: None,
+            granted_by: None,
+            created_at: now,
+            expires_at: now + Duration::seconds(expires_in_seconds),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("grants-test-{}-{}", uuid::Uuid::new_v4(), name))
+    }
+
+    #[test]
+    fn duration_units() {
+        for (value, seconds) in [
+            ("30s", 30),
+            ("90m", 5400),
+            ("4h", 14400),
+            ("2d", 172800),
+            ("45", 45),
+            (" 1h ", 3600),
+        ] {
+            assert_eq!(parse_duration(value).unwrap().num_seconds(), seconds, "{}", value);
+        }
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for value in ["", "h", "1w", "abc", "1.5h", "-1h", "h1", "1 h m"] {
+            assert!(parse_duration(value).is_err(), "{}", value);
+        }
+        for value in ["0", "0s", "0d"] {
+            assert_eq!(parse_duration(value).unwrap_err(), "Duration must be positive", "{}", value);
+        }
+    }
+
+    #[test]
+    fn duration_overflow() {
+        assert!(parse_duration(&format!("{}d", i64::MAX)).is_err());
+        assert!(parse_duration(&format!("{}s", u64::MAX)).is_err());
+        // Beyond what a chrono duration can hold
+        assert!(parse_duration(&format!("{}s", i64::MAX)).is_err());
+        assert_eq!(parse_duration("36500d").unwrap().num_days(), 36500);
+    }
+
+    #[test]
+    fn held_and_duplicate_roles_are_not_granted() {
+        let current = [role("data_reader[travel]")];
+        let added = roles_to_add(
+            &current,
+            vec![role("data_reader[travel]"), role("admin"), role("admin"), role("data_writer[travel]")],
+        );
+        assert_eq!(added, [role("admin"), role("data_writer[travel]")]);
+    }
+
+    #[test]
+    fn only_past_grants_expire() {
+        let grants = [grant("old", "alice", &["admin"], -1), grant("new", "alice", &["admin"], 60)];
+        let expired = expired_grants(&grants, Utc::now());
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, "old");
+    }
+
+    #[test]
+    fn expiry_keeps_base_roles_and_roles_of_other_grants() {
+        let current = [role("data_reader[travel]"), role("admin"), role("bucket_admin[travel]")];
+        let expiring = grant("g1", "alice", &["admin", "bucket_admin[travel]"], -1);
+        let grants = [
+            expiring.clone(),
+            grant("g2", "alice", &["bucket_admin[travel]"], 60),
+            // Another user's grant of the same role does not keep it
+            grant("g3", "bob", &["admin"], 60),
+        ];
+
+        assert_eq!(
+            remaining_roles(&current, &grants, &expiring),
+            [role("data_reader[travel]"), role("bucket_admin[travel]")]
+        );
+    }
+
+    #[tokio::test]
+    async fn store_is_reloaded_on_open() {
+        let config = GrantsConfig {
+            store_path: temp_path("grants.json").to_string_lossy().into_owned(),
+            audit_log_path: temp_path("audit.jsonl").to_string_lossy().into_owned(),
+            reap_interval_seconds: 60,
+            max_duration_seconds: 3600,
+        };
+        let grants = vec![grant("g1", "alice", &["admin"], 60), grant("g2", "bob", &["admin"], 60)];
+        storage::write_json_atomic(Path::new(&config.store_path), &grants).await.unwrap();
+
+        let store = GrantStore::open(&config).await.unwrap();
+        assert_eq!(store.list(None, None).await.len(), 2);
+        let alice = store.list(Some("alice"), Some(UserDomain::Local)).await;
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].id, "g1");
+        assert!(store.list(Some("alice"), Some(UserDomain::External)).await.is_empty());
+
+        store.audit(GrantAction::Expired, &grants[0], None).await;
+        let events = store.audit_events(Some("alice"), None).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].grant_id, "g1");
+
+        let _ = std::fs::remove_file(&config.store_path);
+        let _ = std::fs::remove_file(&config.audit_log_path);
+    }
+
+    fn config() -> GrantsConfig {
+        GrantsConfig {
+            store_path: temp_path("grants.json").to_string_lossy().into_owned(),
+            audit_log_path: temp_path("audit.jsonl").to_string_lossy().into_owned(),
+            reap_interval_seconds: 60,
+            max_duration_seconds: 3600,
+        }
+    }
+
+    fn alice() -> Fixture {
+        (
+            Method::GET,
+            "/settings/rbac/users/local/alice",
+            200,
+            serde_json::json!({
+                "id": "alice",
+                "domain": "local",
+                "roles": [{"role": "data_reader", "bucket_name": "travel", "origins": [{"type": "user"}]}]
+            }),
+        )
+    }
+
+    fn new_grant() -> NewGrant {
+        NewGrant {
+            domain: UserDomain::Local,
+            username: "alice".to_string(),
+            roles: vec![role("admin")],
+            duration: Duration::hours(1),
+            reason: None,
+            granted_by: Some("oncall".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn grants_are_recorded_before_roles_are_applied() {
+        let (service, requests) = fake_cluster(vec![alice()]).await;
+        let config = config();
+        let store = GrantStore::open(&config).await.unwrap();
+
+        let grant = store.grant(&service, new_grant()).await.unwrap();
+        assert_eq!(grant.roles, [role("admin")]);
+        assert_eq!(puts(&requests)[0].form()["roles"], "data_reader[travel],admin");
+
+        let stored: Vec<Grant> = storage::read_json_or_default(Path::new(&config.store_path)).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, grant.id);
+
+        let _ = std::fs::remove_file(&config.store_path);
+        let _ = std::fs::remove_file(&config.audit_log_path);
+    }
+
+    #[tokio::test]
+    async fn roles_are_not_applied_when_the_grant_cannot_be_stored() {
+        let (service, requests) = fake_cluster(vec![alice()]).await;
+        let mut config = config();
+        let store = GrantStore::open(&config).await.unwrap();
+        // A directory cannot be replaced by the store file
+        std::fs::create_dir(&config.store_path).unwrap();
+
+        assert!(store.grant(&service, new_grant()).await.is_err());
+        assert!(puts(&requests).is_empty());
+        assert!(store.list(None, None).await.is_empty());
+
+        let _ = std::fs::remove_dir(&config.store_path);
+        config.store_path.push_str(".tmp");
+        let _ = std::fs::remove_file(&config.store_path);
+    }
+
+    #[tokio::test]
+    async fn failed_role_updates_drop_the_grant() {
+        let (service, _) = fake_cluster(vec![
+            alice(),
+            (Method::PUT, "/settings/rbac/users/local/alice", 500, serde_json::json!({})),
+        ])
+        .await;
+        let config = config();
+        let store = GrantStore::open(&config).await.unwrap();
+
+        assert!(store.grant(&service, new_grant()).await.is_err());
+        assert!(store.list(None, None).await.is_empty());
+        let stored: Vec<Grant> = storage::read_json_or_default(Path::new(&config.store_path)).await.unwrap();
+        assert!(stored.is_empty());
+        assert!(store.audit_events(None, None).await.unwrap().is_empty());
+
+        let _ = std::fs::remove_file(&config.store_path);
+    }
+}
@@ -0,0 +1,331 @@
+use axum::{
+    body::Body,
+    http::{HeaderName, HeaderValue, StatusCode},
+    response::Response,
+};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::{
+    config::IdempotencyConfig,
+    error::{AppError, Result},
+    storage,
+};
+
+/// A response captured for replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Base64-encoded body
+    pub body: String,
+}
+
+impl StoredResponse {
+    pub fn new(status: StatusCode, headers: &axum::http::HeaderMap, body: &[u8]) -> Self {
+        Self {
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect(),
+            body: general_purpose::STANDARD.encode(body),
+        }
+    }
+
+    // Bytes held in memory for this response
+    fn size(&self) -> usize {
+        self.body.len()
+            + self
+                .headers
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>()
+    }
+
+    pub fn to_response(&self) -> Response {
+        let body = general_purpose::STANDARD.decode(&self.body).unwrap_or_default();
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+
+        let headers = response.headers_mut();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        headers.insert("idempotent-replayed", HeaderValue::from_static("true"));
+
+        response
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdempotencyRecord {
+    request_hash: String,
+    created_at: DateTime<Utc>,
+    /// None while the original request is still being processed
+    response: Option<StoredResponse>,
+}
+
+pub enum IdempotencyOutcome<'a> {
+    /// First use of the key; run the request and `complete` the claim
+    Proceed(IdempotencyClaim<'a>),
+    /// The key was already used for this request
+    Replay(StoredResponse),
+}
+
+/// A key claimed for an in-flight request. Dropping the claim without completing it,
+/// e.g. when the client disconnects, frees the key for a retry.
+pub struct IdempotencyClaim<'a> {
+    store: &'a IdempotencyStore,
+    key: String,
+    completed: bool,
+}
+
+impl IdempotencyClaim<'_> {
+    /// Store the response for the claimed key
+    pub async fn complete(mut self, response: StoredResponse) {
+        self.completed = true;
+        {
+            let mut records = self.store.records.lock().unwrap();
+            if response.size() > self.store.max_bytes {
+                records.remove(&self.key);
+            } else if let Some(record) = records.get_mut(&self.key) {
+                record.response = Some(response);
+            }
+            self.store.evict(&mut records, 0);
+        }
+        self.store.persist().await;
+    }
+}
+
+impl Drop for IdempotencyClaim<'_> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.store.records.lock().unwrap().remove(&self.key);
+        }
+    }
+}
+
+/// Remembers responses to mutating requests sent with an `Idempotency-Key` header
+/// so retries get the original response instead of repeating side effects.
+///
+/// Records live in memory for the configured window and, when a store path is set,
+/// completed records are also written to a local JSON file so they survive restarts.
+/// The number of keys and the stored response bytes are capped; past either cap the
+/// oldest completed records are evicted.
+pub struct IdempotencyStore {
+    ttl: Duration,
+    max_entries: usize,
+    max_bytes: usize,
+    store_path: Option<PathBuf>,
+    records: Mutex<HashMap<String, IdempotencyRecord>>,
+    // Serializes writes of the store file
+    persist_lock: tokio::sync::Mutex<()>,
+}
+
+impl IdempotencyStore {
+    pub async fn open(config: &IdempotencyConfig) -> Result<Self> {
+        let store_path = config.store_path.as_ref().map(PathBuf::from);
+        let records: HashMap<String, IdempotencyRecord> = match &store_path {
+            Some(path) => storage::read_json_or_default(path).await?,
+            None => HashMap::new(),
+        };
+
+        let store = Self {
+            ttl: Duration::seconds(config.ttl_seconds as i64),
+            max_entries: config.max_entries,
+            max_bytes: config.max_bytes,
+            store_path,
+            records: Mutex::new(records),
+            persist_lock: tokio::sync::Mutex::new(()),
+        };
+        store.evict(&mut store.records.lock().unwrap(), 0);
+        Ok(store)
+    }
+
+    /// Largest response body worth buffering for the store
+    pub fn max_response_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Claim `key` for a request with `request_hash`
+    pub fn begin(&self, key: &str, request_hash: &str) -> Result<IdempotencyOutcome<'_>> {
+        let mut records = self.records.lock().unwrap();
+        let now = Utc::now();
+        records.retain(|_, record| record.created_at + self.ttl > now);
+        if !records.contains_key(key) {
+            self.evict(&mut records, 1);
+        }
+
+        match records.get(key) {
+            Some(record) if record.request_hash != request_hash => Err(AppError::UnprocessableEntity(
+                "Idempotency-Key was already used with a different request".to_string(),
+            )),
+            Some(IdempotencyRecord { response: Some(response), .. }) => {
+                Ok(IdempotencyOutcome::Replay(response.clone()))
+            }
+            Some(_) => Err(AppError::Conflict(
+                "A request with this Idempotency-Key is still in progress".to_string(),
+            )),
+            None => {
+                records.insert(
+                    key.to_string(),
+                    IdempotencyRecord {
+                        request_hash: request_hash.to_string(),
+                        created_at: now,
+                        response: None,
+                    },
+                );
+                Ok(IdempotencyOutcome::Proceed(IdempotencyClaim {
+                    store: self,
+                    key: key.to_string(),
+                    completed: false,
+                }))
+            }
+        }
+    }
+
+    /// Drop the oldest completed records until `reserve` more keys fit under the caps.
+    /// In-flight records are never evicted.
+    fn evict(&self, records: &mut HashMap<String, IdempotencyRecord>, reserve: usize) {
+        let mut bytes: usize = records
+            .values()
+            .filter_map(|record| record.response.as_ref())
+            .map(StoredResponse::size)
+            .sum();
+        if records.len() + reserve <= self.max_entries && bytes <= self.max_bytes {
+            return;
+        }
+
+        let mut completed: Vec<(DateTime<Utc>, String, usize)> = records
+            .iter()
+            .filter_map(|(key, record)| {
+                let size = record.response.as_ref()?.size();
+                Some((record.created_at, key.clone(), size))
+            })
+            .collect();
+        completed.sort();
+
+        for (_, key, size) in completed {
+            if records.len() + reserve <= self.max_entries && bytes <= self.max_bytes {
+                break;
+            }
+            records.remove(&key);
+            bytes -= size;
+        }
+    }
+
+    async fn persist(&self) {
+        let Some(path) = &self.store_path else {
+            return;
+        };
+
+        let _guard = self.persist_lock.lock().await;
+        let completed: HashMap<String, IdempotencyRecord> = self
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, record)| record.response.is_some())
+            .map(|(key, record)| (key.clone(), record.clone()))
+            .collect();
+
+        if let Err(e) = storage::write_json_atomic(path, &completed).await {
+            warn!("Failed to persist idempotency records: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    fn store(max_entries: usize, max_bytes: usize) -> IdempotencyStore {
+        IdempotencyStore {
+            ttl: Duration::hours(1),
+            max_entries,
+            max_bytes,
+            store_path: None,
+            records: Mutex::new(HashMap::new()),
+            persist_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    async fn complete(store: &IdempotencyStore, key: &str, body: &[u8]) {
+        match store.begin(key, "hash").unwrap() {
+            IdempotencyOutcome::Proceed(claim) => {
+                claim
+                    .complete(StoredResponse::new(StatusCode::OK, &HeaderMap::new(), body))
+                    .await
+            }
+            IdempotencyOutcome::Replay(_) => panic!("{} was already stored", key),
+        }
+    }
+
+    fn is_stored(store: &IdempotencyStore, key: &str) -> bool {
+        store.records.lock().unwrap().contains_key(key)
+    }
+
+    #[tokio::test]
+    async fn completed_keys_are_replayed() {
+        let store = store(10, 1024);
+        complete(&store, "a", b"done").await;
+
+        assert!(matches!(store.begin("a", "hash"), Ok(IdempotencyOutcome::Replay(_))));
+        assert!(matches!(store.begin("a", "other"), Err(AppError::UnprocessableEntity(_))));
+    }
+
+    #[tokio::test]
+    async fn oldest_keys_are_evicted_past_the_entry_cap() {
+        let store = store(2, 1024);
+        complete(&store, "a", b"1").await;
+        complete(&store, "b", b"2").await;
+        complete(&store, "c", b"3").await;
+
+        assert!(!is_stored(&store, "a"));
+        assert!(is_stored(&store, "b"));
+        assert!(is_stored(&store, "c"));
+    }
+
+    #[tokio::test]
+    async fn in_flight_keys_are_not_evicted() {
+        let store = store(1, 1024);
+        let IdempotencyOutcome::Proceed(claim) = store.begin("a", "hash").unwrap() else {
+            panic!("a was already stored");
+        };
+        complete(&store, "b", b"2").await;
+
+        assert!(matches!(store.begin("a", "hash"), Err(AppError::Conflict(_))));
+        drop(claim);
+        assert!(!is_stored(&store, "a"));
+    }
+
+    #[tokio::test]
+    async fn oldest_responses_are_evicted_past_the_byte_cap() {
+        // Bodies are stored base64-encoded: 6 bytes become 8
+        let store = store(10, 20);
+        complete(&store, "a", b"aaaaaa").await;
+        complete(&store, "b", b"bbbbbb").await;
+        complete(&store, "c", b"cccccc").await;
+
+        assert!(!is_stored(&store, "a"));
+        assert!(is_stored(&store, "b"));
+        assert!(is_stored(&store, "c"));
+
+        // A response larger than the whole store frees its key instead
+        complete(&store, "d", &[0; 30]).await;
+        assert!(!is_stored(&store, "d"));
+        assert!(is_stored(&store, "c"));
+    }
+}
@@ -1,37 +1,73 @@
 use axum::{
     response::Json,
-    routing::{get, post, put, delete},
+    routing::{get, post, put, patch, delete},
     Router,
 };
 use std::net::SocketAddr;
+use std::time::Duration;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
-use tracing::{info, error, Level};
-use tracing_subscriber;
+use tracing::{info, error};
+use tracing_subscriber::{
+    filter::LevelFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
 use prometheus::{TextEncoder, Encoder};
 
+mod cache;
+mod conditional;
 mod config;
 mod error;
+mod grants;
+mod idempotency;
 mod middleware;
 mod models;
+mod passwords;
+mod query;
+mod ratelimit;
+mod rotation;
 mod routes;
 mod services;
+mod state;
+mod storage;
+mod telemetry;
+mod validation;
 
-use config::Config;
+use config::{Config, LogFormat};
 use error::AppError;
+use models::RoleTemplates;
+use state::AppState;
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
-        .with_target(false)
-        .init();
-
     // Load configuration
     let config = Config::load()?;
+
+    // Initialize tracing; RUST_LOG selects log levels, defaulting to info.
+    // Exported spans are not affected by RUST_LOG.
+    let telemetry = telemetry::Telemetry::init(&config.telemetry)?;
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let log_layer = match config.logging.format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+        LogFormat::Text => fmt::layer().with_target(false).boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(log_layer.with_filter(filter))
+        .with(
+            telemetry
+                .as_ref()
+                .map(|telemetry| telemetry.layer().with_filter(LevelFilter::INFO)),
+        )
+        .init();
     info!("Configuration loaded successfully");
+    if telemetry.is_some() {
+        info!("Exporting traces to {}", config.telemetry.otlp_endpoint);
+    }
 
     // Initialize Couchbase service
     let couchbase_service = match services::CouchbaseService::new(&config) {
@@ -47,14 +83,45 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    // Keep the RBAC role catalog in sync with the cluster
+    tokio::spawn(couchbase_service.clone().run_role_catalog_refresh());
+
+    // Load temporary grants and start removing them as they expire
+    let grant_store = Arc::new(grants::GrantStore::open(&config.grants).await?);
+    tokio::spawn(grant_store.clone().run_reaper(
+        couchbase_service.clone(),
+        Duration::from_secs(config.grants.reap_interval_seconds.max(1)),
+    ));
+
+    // Resolve the password policy enforced on new and changed passwords
+    let password_policy = Arc::new(passwords::PasswordPolicyEnforcer::from_config(
+        &config.password_policy,
+    )?);
+
+    // Load rotation policies and rotate credentials as they come due
+    let rotator = Arc::new(rotation::CredentialRotator::open(&config.rotation).await?);
+    tokio::spawn(rotator.clone().run_scheduler(
+        couchbase_service.clone(),
+        password_policy.clone(),
+        Duration::from_secs(config.rotation.check_interval_seconds.max(1)),
+    ));
+
+    // Remember responses to retried mutating requests
+    let idempotency_store = Arc::new(idempotency::IdempotencyStore::open(&config.idempotency).await?);
+
+    // Per-client request limits
+    let rate_limiter = Arc::new(ratelimit::RateLimiter::new(&config.rate_limit));
+
     // Build application routes
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/metrics", get(metrics_handler))
         .route("/buckets", post(routes::buckets::create_bucket))
         .route("/buckets", get(routes::buckets::list_buckets))
+        .route("/buckets/:bucket", patch(routes::buckets::update_bucket))
         .route("/buckets/:bucket/scopes", post(routes::scopes::create_scope))
         .route("/buckets/:bucket/scopes", get(routes::scopes::list_scopes))
+        .route("/buckets/:bucket/scopes/:scope", delete(routes::scopes::delete_scope))
         .route(
             "/buckets/:bucket/scopes/:scope/collections",
             post(routes::collections::create_collection),
@@ -63,30 +130,204 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "/buckets/:bucket/scopes/:scope/collections",
             get(routes::collections::list_collections),
         )
-        .route("/users", post(routes::users::create_user))
+        .route(
+            "/buckets/:bucket/scopes/:scope/collections/:collection",
+            delete(routes::collections::delete_collection),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/collections/:collection/indexes",
+            post(routes::indexes::create_index),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/collections/:collection/indexes",
+            get(routes::indexes::list_indexes),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/collections/:collection/indexes/build",
+            post(routes::indexes::build_indexes),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/collections/:collection/indexes/:index",
+            delete(routes::indexes::drop_index),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/search-indexes",
+            get(routes::search_indexes::list_search_indexes),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/search-indexes/:index",
+            put(routes::search_indexes::upsert_search_index),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/search-indexes/:index",
+            get(routes::search_indexes::get_search_index),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/search-indexes/:index",
+            delete(routes::search_indexes::delete_search_index),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/search-indexes/:index/definition",
+            get(routes::search_indexes::get_search_index_definition),
+        )
+        .route(
+            "/buckets/:bucket/scopes/:scope/search-indexes/:index/search",
+            post(routes::search_indexes::search),
+        )
+        .route("/xdcr/remote-clusters", post(routes::xdcr::create_remote_cluster))
+        .route("/xdcr/remote-clusters", get(routes::xdcr::list_remote_clusters))
+        .route("/xdcr/remote-clusters/:name", delete(routes::xdcr::delete_remote_cluster))
+        .route("/xdcr/replications", post(routes::xdcr::create_replication))
+        .route("/xdcr/replications", get(routes::xdcr::list_replications))
+        .route("/xdcr/replications/:id", get(routes::xdcr::get_replication))
+        .route("/xdcr/replications/:id", delete(routes::xdcr::delete_replication))
+        .route("/xdcr/replications/:id/pause", post(routes::xdcr::pause_replication))
+        .route("/xdcr/replications/:id/resume", post(routes::xdcr::resume_replication))
+        .route("/eventing/functions", get(routes::eventing::list_eventing_functions))
+        .route("/eventing/functions/:name", put(routes::eventing::import_eventing_function))
+        .route("/eventing/functions/:name", get(routes::eventing::get_eventing_function))
+        .route("/eventing/functions/:name", delete(routes::eventing::delete_eventing_function))
+        .route("/eventing/functions/:name/deploy", post(routes::eventing::deploy_eventing_function))
+        .route("/eventing/functions/:name/undeploy", post(routes::eventing::undeploy_eventing_function))
+        .route("/eventing/functions/:name/pause", post(routes::eventing::pause_eventing_function))
+        .route("/eventing/functions/:name/resume", post(routes::eventing::resume_eventing_function))
+        .route("/analytics/scopes", post(routes::analytics::create_analytics_scope))
+        .route("/analytics/scopes", get(routes::analytics::list_analytics_scopes))
+        .route("/analytics/scopes/:scope", delete(routes::analytics::drop_analytics_scope))
+        .route(
+            "/analytics/scopes/:scope/collections",
+            post(routes::analytics::create_analytics_collection),
+        )
+        .route(
+            "/analytics/scopes/:scope/collections",
+            get(routes::analytics::list_analytics_collections),
+        )
+        .route(
+            "/analytics/scopes/:scope/collections/:collection",
+            delete(routes::analytics::drop_analytics_collection),
+        )
+        .route("/analytics/scopes/:scope/links", post(routes::analytics::create_analytics_link))
+        .route("/analytics/scopes/:scope/links", get(routes::analytics::list_analytics_links))
+        .route("/analytics/scopes/:scope/links/:link", get(routes::analytics::get_analytics_link))
+        .route(
+            "/analytics/scopes/:scope/links/:link",
+            delete(routes::analytics::delete_analytics_link),
+        )
+        .route(
+            "/analytics/scopes/:scope/links/:link/connect",
+            post(routes::analytics::connect_analytics_link),
+        )
+        .route(
+            "/analytics/scopes/:scope/links/:link/disconnect",
+            post(routes::analytics::disconnect_analytics_link),
+        )
+        .route("/analytics/ingestion", get(routes::analytics::analytics_ingestion))
+        .route("/topology", get(routes::topology::get_topology))
+        .route("/capacity", get(routes::capacity::get_capacity))
+        .route("/query", post(routes::query::run_query))
+        // Responses carrying generated passwords are marked `no-store`, so they are
+        // never kept for idempotent replay
+        .route(
+            "/users",
+            post(routes::users::create_user).layer(axum::middleware::map_response(middleware::no_store)),
+        )
         .route("/users", get(routes::users::list_users))
+        .route("/users/import", post(routes::users::import_users))
+        .route("/users/export", get(routes::users::export_users))
         .route("/users/:username", get(routes::users::get_user))
         .route("/users/:username", delete(routes::users::delete_user))
         .route("/users/:username/roles", put(routes::users::update_user_roles))
+        .route(
+            "/users/:username/password",
+            put(routes::users::change_password).layer(axum::middleware::map_response(middleware::no_store)),
+        )
+        .route(
+            "/users/:username/rotate",
+            post(routes::rotation::rotate_credentials).layer(axum::middleware::map_response(middleware::no_store)),
+        )
+        .route(
+            "/users/:username/rotation-policy",
+            put(routes::rotation::set_rotation_policy).delete(routes::rotation::delete_rotation_policy),
+        )
+        .route("/rotation-policies", get(routes::rotation::list_rotation_policies))
         .route("/users/:username/permissions", get(routes::users::get_user_permissions))
+        .route("/users/:username/check", post(routes::users::check_user_permissions))
+        .route("/users/:username/grants", post(routes::grants::create_grant))
+        .route("/users/:username/grants/:grant_id", delete(routes::grants::revoke_grant))
+        .route("/grants", get(routes::grants::list_grants))
+        .route("/grants/audit", get(routes::grants::list_grant_audit))
         .route("/roles", get(routes::users::get_available_roles))
+        .route("/password-policy", get(routes::users::get_password_policy))
+        .route("/role-templates", get(routes::users::list_role_templates))
         .layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
+                .layer(axum::middleware::from_fn(middleware::request_id_middleware))
+                .layer(TraceLayer::new_for_http().make_span_with(middleware::make_request_span))
                 .layer(CorsLayer::permissive())
-                .layer(axum::middleware::from_fn(middleware::auth_middleware)),
+                .layer(axum::middleware::from_fn(middleware::auth_middleware))
+                .layer(axum::middleware::from_fn_with_state(
+                    rate_limiter,
+                    middleware::rate_limit_middleware,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    idempotency_store,
+                    middleware::idempotency_middleware,
+                ))
+                .layer(axum::middleware::from_fn(middleware::cache_control_middleware)),
         )
-        .with_state(couchbase_service);
+        .with_state(AppState {
+            couchbase_service,
+            role_templates: Arc::new(RoleTemplates(config.role_templates.clone())),
+            grant_store,
+            password_policy,
+            rotator,
+            query_config: Arc::new(config.query.clone()),
+        });
 
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
     info!("Starting server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    if let Some(telemetry) = telemetry {
+        // Flushing blocks until the exporter is done
+        tokio::task::spawn_blocking(move || telemetry.flush()).await?;
+    }
 
     Ok(())
 }
 
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutting down");
+}
+
 async fn health_check() -> Json<serde_json::Value> {
     Json(serde_json::json!({
         "status": "healthy",
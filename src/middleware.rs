@@ -1,13 +1,39 @@
 use axum::{
-    extract::Request,
-    http::{header::AUTHORIZATION, StatusCode},
+    body::{to_bytes, Body, HttpBody},
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, RETRY_AFTER},
+        HeaderName, HeaderValue, Method, StatusCode,
+    },
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Json, Response},
 };
 use base64::{engine::general_purpose, Engine as _};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
 use std::str;
+use std::sync::Arc;
 
-use crate::config::Config;
+use crate::{
+    config::Config,
+    error::{AppError, Result as AppResult},
+    idempotency::{IdempotencyOutcome, IdempotencyStore, StoredResponse},
+    ratelimit::RateLimiter,
+};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The ID of the request being handled, if any, for forwarding to Couchbase
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+// Largest request or response body buffered for idempotent replay
+const MAX_IDEMPOTENT_BODY_BYTES: usize = 16 * 1024 * 1024;
 
 pub async fn auth_middleware(
     mut request: Request,
@@ -25,15 +51,77 @@ pub async fn auth_middleware(
             },
             couchbase: crate::config::CouchbaseConfig {
                 host: "http://localhost:8091".to_string(),
+                query_host: "http://localhost:8093".to_string(),
+                search_host: "http://localhost:8094".to_string(),
+                eventing_host: "http://localhost:8096".to_string(),
+                analytics_host: "http://localhost:8095".to_string(),
                 username: "Administrator".to_string(),
                 password: "password".to_string(),
                 timeout_seconds: 30,
+                role_catalog_refresh_seconds: 300,
+                topology_concurrency: 8,
+                max_concurrent_requests: 16,
             },
             auth: crate::config::AuthConfig {
                 enabled: true,
                 username: "admin".to_string(),
                 password: "admin".to_string(),
             },
+            grants: crate::config::GrantsConfig {
+                store_path: "data/grants.json".to_string(),
+                audit_log_path: "data/grants-audit.jsonl".to_string(),
+                reap_interval_seconds: 30,
+                max_duration_seconds: 7 * 24 * 3600,
+            },
+            password_policy: crate::config::PasswordPolicyConfig {
+                min_length: 8,
+                require_uppercase: false,
+                require_lowercase: false,
+                require_digits: false,
+                require_special_chars: false,
+                denylist: vec![],
+                denylist_file: None,
+                sync_with_cluster: true,
+            },
+            rotation: crate::config::RotationConfig {
+                policy_store_path: "data/rotation-policies.json".to_string(),
+                check_interval_seconds: 60,
+                sink_timeout_seconds: 30,
+                sinks: Default::default(),
+            },
+            cache: crate::config::CacheConfig {
+                enabled: true,
+                ttl_seconds: 10,
+            },
+            idempotency: crate::config::IdempotencyConfig {
+                ttl_seconds: 24 * 3600,
+                store_path: None,
+                max_entries: 10_000,
+                max_bytes: 64 * 1024 * 1024,
+            },
+            rate_limit: crate::config::RateLimitConfig {
+                enabled: true,
+                requests_per_second: 20.0,
+                burst: 40,
+                routes: Default::default(),
+            },
+            logging: crate::config::LoggingConfig {
+                format: crate::config::LogFormat::Text,
+            },
+            telemetry: crate::config::TelemetryConfig {
+                enabled: false,
+                otlp_endpoint: "http://localhost:4318".to_string(),
+                service_name: "couchbase-admin-service".to_string(),
+            },
+            query: crate::config::QueryConfig {
+                read_only: true,
+                username: None,
+                password: None,
+                timeout_seconds: 30,
+                max_timeout_seconds: 300,
+                max_rows: 10_000,
+            },
+            role_templates: Default::default(),
         });
 
     // Skip auth for health check and metrics endpoints
@@ -88,8 +176,347 @@ pub async fn auth_middleware(
     Ok(next.run(request).await)
 }
 
+/// Accept the caller's `X-Request-Id` or generate one and echo it in the response.
+/// The ID is set on the request so the trace layer records it on the request span.
+pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    // Only printable ASCII reaches this point, so the header value is always valid
+    let value = HeaderValue::from_str(&request_id).expect("request id is a valid header value");
+    let header = HeaderName::from_static(REQUEST_ID_HEADER);
+    request.headers_mut().insert(header.clone(), value.clone());
+
+    let mut response = REQUEST_ID.scope(request_id, next.run(request)).await;
+    response.headers_mut().insert(header, value);
+    response
+}
+
+/// Span for each HTTP request, tagged with its request ID and route and continuing
+/// the caller's trace when it sends a `traceparent` header
+pub fn make_request_span<B>(request: &axum::http::Request<B>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str())
+        .unwrap_or_else(|| request.uri().path());
+
+    let span = tracing::info_span!(
+        "request",
+        otel.name = %format!("{} {}", request.method(), route),
+        otel.kind = "server",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        http.route = %route,
+    );
+    crate::telemetry::set_parent_from_headers(&span, request.headers());
+    span
+}
+
+/// Serve `Cache-Control: no-cache` requests straight from Couchbase instead of the
+/// management cache
+pub async fn cache_control_middleware(request: Request, next: Next) -> Response {
+    let no_cache = request
+        .headers()
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"));
+
+    if no_cache {
+        crate::cache::bypass(next.run(request)).await
+    } else {
+        next.run(request).await
+    }
+}
+
+// Rate limit key for anonymous requests whose peer address is unknown
+const UNKNOWN_CLIENT: &str = "ip:unknown";
+
+/// Reject clients over their token-bucket limit with 429 and `Retry-After`.
+/// Clients are the authenticated user, or the peer IP when there is none.
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str())
+        .unwrap_or("unmatched");
+    if path == "/health" || path == "/metrics" {
+        return next.run(request).await;
+    }
+    let route = format!("{} {}", request.method(), path);
+
+    let client = match request.extensions().get::<UserInfo>() {
+        Some(user) => format!("user:{}", user.username),
+        None => request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| format!("ip:{}", addr.ip()))
+            // Without a peer address every such request shares one bucket
+            .unwrap_or_else(|| UNKNOWN_CLIENT.to_string()),
+    };
+
+    match limiter.check(&client, &route) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, seconds.to_string())],
+                Json(serde_json::json!({
+                    "error": "Rate limit exceeded",
+                    "status": StatusCode::TOO_MANY_REQUESTS.as_u16()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Mark a response that carries credentials as `Cache-Control: no-store`, which also
+/// keeps it out of the idempotency store
+pub async fn no_store(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+/// Replay the stored response for a repeated `Idempotency-Key` on mutating requests.
+/// Keys are scoped to the authenticated user.
+pub async fn idempotency_middleware(
+    State(store): State<Arc<IdempotencyStore>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    idempotent(&store, request, next)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+async fn idempotent(store: &IdempotencyStore, request: Request, next: Next) -> AppResult<Response> {
+    let mutating = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    let key = request
+        .headers()
+        .get("idempotency-key")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let Some(key) = key.filter(|_| mutating) else {
+        return Ok(next.run(request).await);
+    };
+
+    let principal = request
+        .extensions()
+        .get::<UserInfo>()
+        .map(|user| user.username.clone())
+        .unwrap_or_default();
+    let key = format!("{}:{}", principal, key);
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_IDEMPOTENT_BODY_BYTES)
+        .await
+        .map_err(|e| AppError::Validation(format!("Failed to read request body: {}", e)))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update(parts.uri.to_string());
+    if let Some(content_type) = parts.headers.get(CONTENT_TYPE) {
+        hasher.update(content_type.as_bytes());
+    }
+    hasher.update(&body);
+    let request_hash = format!("{:x}", hasher.finalize());
+
+    let claim = match store.begin(&key, &request_hash)? {
+        IdempotencyOutcome::Replay(response) => return Ok(response.to_response()),
+        IdempotencyOutcome::Proceed(claim) => claim,
+    };
+
+    // Server errors are not stored; dropping the claim frees the key for a retry
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if response.status().is_server_error() {
+        return Ok(response);
+    }
+
+    // Responses with credentials must not be written to the store or handed to
+    // whoever replays the key
+    let no_store = response
+        .headers()
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"));
+    if no_store {
+        return Ok(response);
+    }
+
+    // Streamed responses (NDJSON query results) and bodies too large to keep are
+    // passed through untouched and not stored, so the key is freed as for server errors
+    let limit = MAX_IDEMPOTENT_BODY_BYTES.min(store.max_response_bytes());
+    match response.body().size_hint().exact() {
+        Some(len) if len as usize <= limit => {}
+        _ => return Ok(response),
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, limit)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to buffer response: {}", e)))?;
+
+    claim
+        .complete(StoredResponse::new(parts.status, &parts.headers, &body))
+        .await;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
 #[derive(Debug, Clone)]
 pub struct UserInfo {
-    #[allow(dead_code)] // Reserved for future use
     pub username: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IdempotencyConfig;
+    use crate::services::{testing::fake_cluster, CouchbaseService};
+    use axum::{
+        routing::{get, post},
+        Router,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, path: &str, key: &str) -> (Response, String) {
+        let request = Request::post(path)
+            .header("idempotency-key", key)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, usize::MAX).await.unwrap();
+        (
+            Response::from_parts(parts, Body::empty()),
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn generated_passwords_are_not_replayed() {
+        let store_path = std::env::temp_dir().join(format!("idempotency-{}.json", uuid::Uuid::new_v4()));
+        let store = Arc::new(
+            IdempotencyStore::open(&IdempotencyConfig {
+                ttl_seconds: 3600,
+                store_path: Some(store_path.to_string_lossy().into_owned()),
+                max_entries: 100,
+                max_bytes: 1024 * 1024,
+            })
+            .await
+            .unwrap(),
+        );
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let generate = move || {
+            let calls = calls.clone();
+            async move {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                Json(serde_json::json!({ "generated_password": format!("secret-{}", n) }))
+            }
+        };
+        let app = Router::new()
+            .route(
+                "/users",
+                post(generate.clone()).layer(axum::middleware::map_response(no_store)),
+            )
+            .route("/buckets", post(generate))
+            .layer(axum::middleware::from_fn_with_state(store.clone(), idempotency_middleware));
+
+        let (first, body) = send(&app, "/users", "create-alice").await;
+        assert_eq!(body, r#"{"generated_password":"secret-0"}"#);
+        assert_eq!(first.headers()[CACHE_CONTROL], "no-store");
+
+        // The retry runs again rather than handing out the first password
+        let (retry, body) = send(&app, "/users", "create-alice").await;
+        assert_eq!(body, r#"{"generated_password":"secret-1"}"#);
+        assert!(retry.headers().get("idempotent-replayed").is_none());
+
+        // Other responses are still stored and replayed
+        let (_, body) = send(&app, "/buckets", "create-bucket").await;
+        assert_eq!(body, r#"{"generated_password":"secret-2"}"#);
+        let (replay, body) = send(&app, "/buckets", "create-bucket").await;
+        assert_eq!(body, r#"{"generated_password":"secret-2"}"#);
+        assert_eq!(replay.headers()["idempotent-replayed"], "true");
+
+        let persisted = tokio::fs::read_to_string(&store_path).await.unwrap();
+        let _ = tokio::fs::remove_file(&store_path).await;
+        assert!(!persisted.contains(&general_purpose::STANDARD.encode("secret-0")[..8]));
+        assert!(!persisted.contains("create-alice"));
+        assert!(persisted.contains("create-bucket"));
+    }
+
+    async fn request_ids(app: &Router, request_id: Option<&str>) -> (String, String) {
+        let mut request = Request::get("/buckets");
+        if let Some(request_id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, request_id);
+        }
+        let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let echoed = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (echoed, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    // Calls Couchbase and answers with the ID the handler saw on its request
+    async fn list_buckets(State(service): State<CouchbaseService>, request: Request) -> String {
+        let _ = service.list_buckets().await;
+        request.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn request_ids_are_echoed_and_forwarded_to_couchbase() {
+        let (service, requests) = fake_cluster(vec![]).await;
+        let app = Router::new()
+            .route("/buckets", get(list_buckets))
+            .with_state(service)
+            .layer(axum::middleware::from_fn(request_id_middleware));
+
+        let (echoed, seen) = request_ids(&app, Some("deploy-42/step:1")).await;
+        assert_eq!((echoed.as_str(), seen.as_str()), ("deploy-42/step:1", "deploy-42/step:1"));
+        let outbound = requests.lock().unwrap().pop().unwrap();
+        assert_eq!(outbound.path, "/pools/default/buckets");
+        assert_eq!(outbound.headers[REQUEST_ID_HEADER], "deploy-42/step:1");
+
+        // Missing, empty, oversized and unprintable IDs are replaced with a fresh one
+        let oversized = "a".repeat(129);
+        for request_id in [None, Some(""), Some(oversized.as_str()), Some("two words"), Some("tab\tid")] {
+            let (echoed, seen) = request_ids(&app, request_id).await;
+            assert!(uuid::Uuid::parse_str(&echoed).is_ok(), "{:?}: {}", request_id, echoed);
+            assert_eq!(seen, echoed);
+            let outbound = requests.lock().unwrap().pop().unwrap();
+            assert_eq!(outbound.headers[REQUEST_ID_HEADER], echoed.as_str());
+        }
+
+        // The longest accepted ID is kept as it is
+        let longest = "a".repeat(128);
+        assert_eq!(request_ids(&app, Some(&longest)).await.0, longest);
+    }
+}
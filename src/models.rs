@@ -1,238 +1,1752 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::validation::{self, FieldError};
 
 // Bucket Management Models
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct CreateBucketRequest {
     pub bucket_name: String,
+    #[serde(default)]
+    pub bucket_type: BucketType,
     pub ram_quota_mb: Option<u32>,
     pub replica_number: Option<u32>,
     pub eviction_policy: Option<String>,
     pub compression_mode: Option<String>,
     pub conflict_resolution_type: Option<String>,
+    pub storage_backend: Option<StorageBackend>,
+    /// Maximum document expiry in seconds, 0 for none
+    pub max_ttl: Option<u32>,
+    pub flush_enabled: Option<bool>,
+    pub durability_min_level: Option<String>,
+    pub replica_index: Option<bool>,
+    pub history_retention_collection_default: Option<bool>,
+    pub history_retention_bytes: Option<u64>,
+    pub history_retention_seconds: Option<u64>,
+    pub num_vbuckets: Option<u32>,
+}
+
+/// Settings that can be changed on an existing bucket; omitted fields are left as they are
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct UpdateBucketRequest {
+    pub ram_quota_mb: Option<u32>,
+    pub replica_number: Option<u32>,
+    pub eviction_policy: Option<String>,
+    pub compression_mode: Option<String>,
+    pub max_ttl: Option<u32>,
+    pub flush_enabled: Option<bool>,
+    pub durability_min_level: Option<String>,
+}
+
+impl UpdateBucketRequest {
+    pub fn is_empty(&self) -> bool {
+        self.ram_quota_mb.is_none()
+            && self.replica_number.is_none()
+            && self.eviction_policy.is_none()
+            && self.compression_mode.is_none()
+            && self.max_ttl.is_none()
+            && self.flush_enabled.is_none()
+            && self.durability_min_level.is_none()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketType {
+    #[default]
+    // Couchbase reports these as "membase"
+    #[serde(alias = "membase")]
+    Couchbase,
+    Ephemeral,
+    Memcached,
+}
+
+impl BucketType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BucketType::Couchbase => "couchbase",
+            BucketType::Ephemeral => "ephemeral",
+            BucketType::Memcached => "memcached",
+        }
+    }
+
+    /// Parse the `bucketType` reported by the cluster manager
+    pub fn from_couchbase(value: &str) -> Option<Self> {
+        match value {
+            "membase" | "couchbase" => Some(BucketType::Couchbase),
+            "ephemeral" => Some(BucketType::Ephemeral),
+            "memcached" => Some(BucketType::Memcached),
+            _ => None,
+        }
+    }
+
+    pub fn eviction_policies(self) -> &'static [&'static str] {
+        match self {
+            BucketType::Couchbase => &["valueOnly", "fullEviction"],
+            BucketType::Ephemeral => &["noEviction", "nruEviction"],
+            BucketType::Memcached => &[],
+        }
+    }
+
+    pub fn default_eviction_policy(self) -> Option<&'static str> {
+        self.eviction_policies().first().copied()
+    }
+
+    pub fn durability_levels(self) -> &'static [&'static str] {
+        match self {
+            BucketType::Couchbase => &["none", "majority", "majorityAndPersistActive", "persistToMajority"],
+            BucketType::Ephemeral => &["none", "majority"],
+            BucketType::Memcached => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for BucketType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Couchstore,
+    Magma,
+}
+
+impl StorageBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StorageBackend::Couchstore => "couchstore",
+            StorageBackend::Magma => "magma",
+        }
+    }
+
+    pub fn from_couchbase(value: &str) -> Option<Self> {
+        match value {
+            "couchstore" => Some(StorageBackend::Couchstore),
+            "magma" => Some(StorageBackend::Magma),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BucketInfo {
+    pub name: String,
+    pub bucket_type: BucketType,
+    pub ram_quota_mb: u32,
+    pub replica_number: u32,
+    pub eviction_policy: String,
+    pub compression_mode: String,
+    pub conflict_resolution_type: String,
+    pub storage_backend: Option<StorageBackend>,
+    pub max_ttl: u32,
+    pub flush_enabled: bool,
+    pub durability_min_level: Option<String>,
+    pub replica_index: Option<bool>,
+    pub history_retention_collection_default: Option<bool>,
+    pub history_retention_bytes: Option<u64>,
+    pub history_retention_seconds: Option<u64>,
+    pub num_vbuckets: Option<u32>,
+    pub status: String,
+}
+
+// Scope Management Models
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateScopeRequest {
+    pub scope_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScopeInfo {
+    pub name: String,
+    pub collections: Vec<CollectionInfo>,
+}
+
+/// A bucket's scopes and collections as of manifest `uid`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CollectionManifest {
+    pub uid: String,
+    pub scopes: Vec<ScopeInfo>,
+}
+
+// Collection Management Models
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCollectionRequest {
+    pub collection_name: String,
+    pub max_ttl: Option<u32>,
+    pub history: Option<bool>,
+    /// Indexes to create once the collection exists
+    #[serde(default)]
+    pub indexes: Vec<CreateIndexRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CollectionInfo {
+    pub name: String,
+    pub max_ttl: Option<u32>,
+    pub history: Option<bool>,
+    pub scope: String,
+}
+
+// Index Management Models
+/// A GSI index on a collection. `fields`, `partition_by` and `condition` are N1QL
+/// expressions that go into the statement as written, so only trusted callers should
+/// be allowed to create indexes; validation just stops them from adding statements.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CreateIndexRequest {
+    /// Required for secondary indexes; primary indexes default to `#primary`
+    pub index_name: Option<String>,
+    #[serde(default)]
+    pub primary: bool,
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// `WHERE` clause for a partial index
+    pub condition: Option<String>,
+    /// Create without building; build later with `POST .../indexes/build`
+    #[serde(default)]
+    pub deferred: bool,
+    pub num_replica: Option<u32>,
+    /// Hash-partition the index by these expressions
+    #[serde(default)]
+    pub partition_by: Vec<String>,
+    pub num_partition: Option<u32>,
+}
+
+impl CreateIndexRequest {
+    pub fn name(&self) -> &str {
+        self.index_name.as_deref().unwrap_or(PRIMARY_INDEX_NAME)
+    }
+}
+
+/// Name Couchbase gives an unnamed primary index
+pub const PRIMARY_INDEX_NAME: &str = "#primary";
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct BuildIndexesRequest {
+    /// Indexes to build; all deferred indexes on the collection when omitted
+    pub index_names: Option<Vec<String>>,
+}
+
+/// An index with its state from `/indexStatus`, combined across replicas
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexInfo {
+    pub name: String,
+    pub bucket: String,
+    pub scope: String,
+    pub collection: String,
+    pub primary: bool,
+    pub definition: String,
+    /// `Ready` once every replica is; otherwise the state of a replica that is not
+    pub status: String,
+    /// Build progress in percent, of the slowest replica
+    pub progress: u32,
+    pub num_replica: u32,
+    pub num_partition: Option<u32>,
+    pub hosts: Vec<String>,
+}
+
+// Search Index Models
+pub const SEARCH_INDEX_TYPE: &str = "fulltext-index";
+
+fn default_search_index_type() -> String {
+    SEARCH_INDEX_TYPE.to_string()
+}
+
+/// A search index definition in the search service's own format, as shown by
+/// "Index Definition JSON" in the UI. Cluster-specific fields (`uuid`, `sourceName`,
+/// `sourceUUID`) are left out and filled in when the definition is applied, so a
+/// file holding it can be applied to any bucket and scope.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchIndexDefinition {
+    /// Taken from the path when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type", default = "default_search_index_type")]
+    pub index_type: String,
+    /// Mapping, analysis and store settings
+    #[serde(default)]
+    pub params: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan_params: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_params: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchIndexInfo {
+    pub name: String,
+    pub bucket: String,
+    pub scope: String,
+    /// `Ready` once every mutation seen so far is indexed, `Building` before that,
+    /// `Unknown` when the search service reports no stats for the index
+    pub status: String,
+    pub doc_count: Option<u64>,
+    pub definition: SearchIndexDefinition,
+}
+
+// Query Models
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct QueryRequest {
+    pub statement: String,
+    /// Positional parameters, `$1`, `$2`, ...
+    #[serde(default)]
+    pub args: Vec<serde_json::Value>,
+    /// Named parameters, referenced as `$name`
+    #[serde(default)]
+    pub named_args: HashMap<String, serde_json::Value>,
+    /// With `scope`, sets the query context so statements can use bare collection names
+    pub bucket: Option<String>,
+    pub scope: Option<String>,
+    pub timeout_seconds: Option<u64>,
+    pub max_rows: Option<usize>,
+    /// Only honoured when the service allows writes; read-only otherwise
+    pub read_only: Option<bool>,
+}
+
+/// Outcome of a query, reported after its rows
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct QuerySummary {
+    /// Query service status, or `truncated` when the row limit stopped the query
+    pub status: String,
+    pub row_count: usize,
+    pub truncated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<serde_json::Value>,
+    pub metrics: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueryResult {
+    pub rows: Vec<serde_json::Value>,
+    #[serde(flatten)]
+    pub summary: QuerySummary,
+}
+
+// XDCR Models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum XdcrEncryption {
+    #[default]
+    None,
+    /// Only the password is encrypted
+    Half,
+    /// All traffic to the remote cluster is encrypted
+    Full,
+}
+
+impl XdcrEncryption {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            XdcrEncryption::None => "none",
+            XdcrEncryption::Half => "half",
+            XdcrEncryption::Full => "full",
+        }
+    }
+
+    /// Parse a remote cluster reference from `/pools/default/remoteClusters`
+    pub fn from_couchbase(reference: &serde_json::Value) -> Self {
+        if reference["demandEncryption"].as_bool() != Some(true) {
+            return XdcrEncryption::None;
+        }
+        match reference["encryptionType"].as_str() {
+            Some("half") => XdcrEncryption::Half,
+            _ => XdcrEncryption::Full,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRemoteClusterRequest {
+    pub name: String,
+    /// Any node of the remote cluster, as `host` or `host:port`
+    pub hostname: String,
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub encryption: XdcrEncryption,
+    /// PEM root certificate of the remote cluster, for encrypted references
+    pub certificate: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteClusterInfo {
+    pub name: String,
+    pub uuid: String,
+    pub hostname: String,
+    pub username: String,
+    pub encryption: XdcrEncryption,
+    /// As last seen by the cluster manager, e.g. `RC_OK` or `RC_ERROR`
+    pub connectivity_status: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplicationPriority {
+    High,
+    Medium,
+    Low,
+}
+
+impl ReplicationPriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReplicationPriority::High => "High",
+            ReplicationPriority::Medium => "Medium",
+            ReplicationPriority::Low => "Low",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CreateReplicationRequest {
+    pub source_bucket: String,
+    /// Name of a remote cluster reference
+    pub remote_cluster: String,
+    pub target_bucket: String,
+    /// Source `scope` or `scope.collection` to target namespace of the same form.
+    /// A `null` target leaves the source out. Without mappings, every collection
+    /// replicates to the collection of the same name on the target.
+    #[serde(default)]
+    pub collection_mappings: BTreeMap<String, Option<String>>,
+    /// Only documents matching this expression are replicated
+    pub filter_expression: Option<String>,
+    /// Leave out expirations
+    pub filter_expirations: Option<bool>,
+    /// Leave out deletions
+    pub filter_deletions: Option<bool>,
+    pub priority: Option<ReplicationPriority>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReplicationStats {
+    /// Mutations not yet replicated
+    pub changes_left: Option<u64>,
+    pub docs_checked: Option<u64>,
+    /// Weighted average time for a mutation to reach the target
+    pub latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicationInfo {
+    /// `{remote cluster uuid}/{source bucket}/{target bucket}`; in paths the `/`s
+    /// are sent as `%2F`
+    pub id: String,
+    pub source_bucket: String,
+    /// `None` once the remote cluster reference is gone
+    pub remote_cluster: Option<String>,
+    pub remote_cluster_uuid: String,
+    pub target_bucket: String,
+    /// `running`, `paused` or `notRunning`
+    pub status: String,
+    pub filter_expression: Option<String>,
+    pub collection_mappings: BTreeMap<String, Option<String>>,
+    pub stats: ReplicationStats,
+    pub errors: Vec<String>,
+}
+
+// Eventing Models
+fn default_keyspace_name() -> String {
+    validation::DEFAULT_KEYSPACE_NAME.to_string()
+}
+
+/// An eventing function in the eventing service's export format, so definitions
+/// exported from the UI can be kept in a repository and imported as they are
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventingFunctionDefinition {
+    /// Taken from the path when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appname: Option<String>,
+    /// JavaScript source of the handlers
+    pub appcode: String,
+    pub depcfg: EventingDeploymentConfig,
+    /// Worker count, log level, `dcp_stream_boundary`, ...; deployment state is not
+    /// part of a definition and is dropped
+    #[serde(default)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_scope: Option<EventingFunctionScope>,
+}
+
+/// Source and metadata keyspaces and bindings of an eventing function
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventingDeploymentConfig {
+    pub source_bucket: String,
+    #[serde(default = "default_keyspace_name")]
+    pub source_scope: String,
+    #[serde(default = "default_keyspace_name")]
+    pub source_collection: String,
+    pub metadata_bucket: String,
+    #[serde(default = "default_keyspace_name")]
+    pub metadata_scope: String,
+    #[serde(default = "default_keyspace_name")]
+    pub metadata_collection: String,
+    #[serde(default)]
+    pub buckets: Vec<EventingBucketBinding>,
+    /// URL bindings, passed through as they are
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub curl: Vec<serde_json::Value>,
+    /// Constant bindings, passed through as they are
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constants: Vec<serde_json::Value>,
+}
+
+/// A keyspace made available to the handlers under `alias`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventingBucketBinding {
+    pub alias: String,
+    pub bucket_name: String,
+    #[serde(default = "default_keyspace_name")]
+    pub scope_name: String,
+    #[serde(default = "default_keyspace_name")]
+    pub collection_name: String,
+    /// `r` or `rw`
+    pub access: String,
+}
+
+/// Bucket and scope the function belongs to, for RBAC
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventingFunctionScope {
+    pub bucket: String,
+    pub scope: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventingLifecycleAction {
+    Deploy,
+    Undeploy,
+    Pause,
+    Resume,
+}
+
+impl EventingLifecycleAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventingLifecycleAction::Deploy => "deploy",
+            EventingLifecycleAction::Undeploy => "undeploy",
+            EventingLifecycleAction::Pause => "pause",
+            EventingLifecycleAction::Resume => "resume",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventingFunctionInfo {
+    pub name: String,
+    /// `undeployed`, `deploying`, `deployed`, `pausing`, `paused` or `undeploying`
+    pub status: String,
+    /// Eventing nodes the function is deployed on
+    pub deployed_nodes: u32,
+    pub definition: EventingFunctionDefinition,
+}
+
+// Analytics Models
+/// Link to the cluster's own data service, present in every analytics scope
+pub const LOCAL_ANALYTICS_LINK: &str = "Local";
+/// Stands in for secrets when links are read back
+pub const REDACTED: &str = "<redacted>";
+
+fn default_analytics_link() -> String {
+    LOCAL_ANALYTICS_LINK.to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAnalyticsScopeRequest {
+    pub scope_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyticsScopeInfo {
+    pub name: String,
+}
+
+/// KV collection an analytics collection is fed from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyticsSource {
+    pub bucket: String,
+    #[serde(default = "default_keyspace_name")]
+    pub scope: String,
+    #[serde(default = "default_keyspace_name")]
+    pub collection: String,
+}
+
+/// An analytics collection (dataset) shadowing a KV collection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAnalyticsCollectionRequest {
+    pub collection_name: String,
+    pub source: AnalyticsSource,
+    /// `Local`, or a remote Couchbase link in the same analytics scope
+    #[serde(default = "default_analytics_link")]
+    pub link_name: String,
+    /// Only documents matching this expression are ingested. It goes into the
+    /// `CREATE ANALYTICS COLLECTION` statement as written.
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyticsIngestionStatus {
+    pub scope: String,
+    pub collection: String,
+    pub link: String,
+    /// Health of the link feeding the collection, e.g. `healthy` or `stopped`
+    pub status: String,
+    /// Fraction of the source already ingested, from 0 to 1
+    pub progress: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyticsCollectionInfo {
+    pub name: String,
+    pub scope: String,
+    pub link: String,
+    pub bucket: String,
+    /// The KV collection the data comes from. TTL and history settings are only
+    /// known for collections of this cluster.
+    pub source: CollectionInfo,
+    pub filter: Option<String>,
+    pub ingestion: Option<AnalyticsIngestionStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AnalyticsLinkSettings {
+    /// The built-in `Local` link; it cannot be created or deleted
+    Local,
+    /// Another Couchbase cluster
+    Couchbase {
+        /// Any node of the remote cluster, as `host` or `host:port`
+        hostname: String,
+        username: String,
+        password: String,
+        #[serde(default)]
+        encryption: XdcrEncryption,
+        /// PEM root certificate of the remote cluster, required for full encryption
+        certificate: Option<String>,
+    },
+    /// An S3 bucket, for external analytics collections
+    S3 {
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+        region: String,
+        /// For S3 compatible services
+        service_endpoint: Option<String>,
+    },
+}
+
+impl AnalyticsLinkSettings {
+    /// The settings with passwords and secret keys replaced by `REDACTED`
+    pub fn redacted(self) -> Self {
+        match self {
+            AnalyticsLinkSettings::Couchbase {
+                hostname,
+                username,
+                encryption,
+                certificate,
+                ..
+            } => AnalyticsLinkSettings::Couchbase {
+                hostname,
+                username,
+                password: REDACTED.to_string(),
+                encryption,
+                certificate,
+            },
+            AnalyticsLinkSettings::S3 {
+                access_key_id,
+                session_token,
+                region,
+                service_endpoint,
+                ..
+            } => AnalyticsLinkSettings::S3 {
+                access_key_id,
+                secret_access_key: REDACTED.to_string(),
+                session_token: session_token.map(|_| REDACTED.to_string()),
+                region,
+                service_endpoint,
+            },
+            local => local,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAnalyticsLinkRequest {
+    pub link_name: String,
+    #[serde(flatten)]
+    pub settings: AnalyticsLinkSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyticsLinkInfo {
+    pub name: String,
+    pub scope: String,
+    /// Secrets are always redacted
+    #[serde(flatten)]
+    pub settings: AnalyticsLinkSettings,
+}
+
+// Topology Models
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BucketTopology {
+    #[serde(flatten)]
+    pub bucket: BucketInfo,
+    pub manifest_uid: String,
+    pub scopes: Vec<ScopeInfo>,
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// Every bucket with its settings, scopes and collections
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClusterTopology {
+    pub generated_at: DateTime<Utc>,
+    pub buckets: Vec<BucketTopology>,
+    /// Set when the index service could not be read; every bucket's index list is then empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexes_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TopologyFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct TopologyQuery {
+    #[serde(default)]
+    pub format: TopologyFormat,
+}
+
+// Capacity Models
+/// RAM and disk headroom across the cluster
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClusterCapacity {
+    pub ram: RamCapacity,
+    pub disk: DiskCapacity,
+    pub services: Vec<ServiceCapacity>,
+}
+
+/// Per-node RAM: service quotas are reserved on every node running the service
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RamCapacity {
+    pub node_count: usize,
+    /// Physical memory of the smallest node
+    pub node_memory_mb: u64,
+    /// Sum of all service quotas
+    pub allocated_quota_mb: u64,
+    pub unallocated_mb: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiskCapacity {
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub used_by_data_bytes: u64,
+    pub free_bytes: u64,
+}
+
+/// A service's per-node memory quota. Usage is only known for the data service,
+/// where it is the sum of the bucket quotas.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceCapacity {
+    pub service: String,
+    pub quota_mb: u64,
+    pub used_mb: Option<u64>,
+    pub free_mb: Option<u64>,
+}
+
+// User Management Models
+/// Where a user is authenticated: `local` users live in Couchbase with a password,
+/// `external` users come from LDAP/SAML and only have roles assigned here
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UserDomain {
+    #[default]
+    Local,
+    External,
+}
+
+impl UserDomain {
+    pub fn from_couchbase(domain: Option<&str>) -> Self {
+        match domain {
+            Some("external") => UserDomain::External,
+            _ => UserDomain::Local,
+        }
+    }
+}
+
+impl std::fmt::Display for UserDomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserDomain::Local => write!(f, "local"),
+            UserDomain::External => write!(f, "external"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct DomainQuery {
+    #[serde(default)]
+    pub domain: UserDomain,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ListUsersQuery {
+    pub domain: Option<UserDomain>,
+    pub page_size: Option<usize>,
+    /// Opaque cursor from a previous page's `pagination.next_cursor`
+    pub cursor: Option<String>,
+    pub sort_by: Option<UserSortField>,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Substring match on the username
+    pub search: Option<String>,
+    pub role: Option<String>,
+    pub bucket: Option<String>,
+    pub group: Option<String>,
+}
+
+impl ListUsersQuery {
+    /// Filters Couchbase cannot apply server-side
+    pub fn has_local_filters(&self) -> bool {
+        self.domain.is_some() || self.role.is_some() || self.bucket.is_some() || self.group.is_some()
+    }
+
+    pub fn matches(&self, user: &UserInfo) -> bool {
+        self.domain.is_none_or(|d| user.domain == d)
+            && self.search.as_deref().is_none_or(|s| user.username.contains(s))
+            && self.role.as_deref().is_none_or(|r| user.roles.iter().any(|role| role.role == r))
+            && self
+                .bucket
+                .as_deref()
+                .is_none_or(|b| user.roles.iter().any(|role| role.bucket.as_deref() == Some(b)))
+            && self.group.as_deref().is_none_or(|g| user.groups.iter().any(|group| group == g))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    Username,
+    Domain,
+}
+
+impl UserSortField {
+    /// The `sortBy` value understood by `/settings/rbac/users`
+    pub fn couchbase_name(&self) -> &'static str {
+        match self {
+            UserSortField::Username => "id",
+            UserSortField::Domain => "domain",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    #[serde(default)]
+    pub domain: UserDomain,
+    /// Omit to have the service generate a strong password
+    pub password: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    pub groups: Option<Vec<String>>,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    /// Name of a role template to expand into `roles`
+    pub template: Option<String>,
+    /// Placeholder values for the role template
+    pub params: Option<HashMap<String, String>>,
+}
+
+impl CreateUserRequest {
+    #[allow(dead_code)] // Available for future use
+    pub fn has_console_access(&self) -> bool {
+        self.roles.iter().any(|role| roles::has_console_access(&role.role))
+    }
+    
+    #[allow(dead_code)] // Available for future use
+    pub fn get_bucket_permissions(&self) -> Vec<String> {
+        self.roles
+            .iter()
+            .filter_map(|role| role.bucket.clone())
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect()
+    }
+    
+    /// Expand the requested role template, if any, and append its roles to `roles`
+    pub fn apply_template(&mut self, templates: &RoleTemplates) -> Result<(), String> {
+        let Some(name) = self.template.take() else {
+            return Ok(());
+        };
+
+        let template = templates.get(&name).ok_or_else(|| {
+            format!("Unknown role template '{}'. See GET /role-templates", name)
+        })?;
+
+        let params = self.params.take().unwrap_or_default();
+        self.roles.extend(template.expand(&params)?);
+        Ok(())
+    }
+
+    pub fn validate(&self, catalog: &RoleCatalog, policy: &PasswordPolicy) -> Result<(), String> {
+        // Validate username
+        if self.username.is_empty() {
+            return Err("Username cannot be empty".to_string());
+        }
+        
+        if self.username.len() < 3 {
+            return Err("Username must be at least 3 characters long".to_string());
+        }
+        
+        match self.domain {
+            UserDomain::Local => {
+                if !self.username.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                    return Err("Username can only contain alphanumeric characters, underscores, and hyphens".to_string());
+                }
+            }
+            UserDomain::External => {
+                // Directory user names commonly contain dots and @
+                if !self.username.chars().all(|c| c.is_alphanumeric() || "_-.@".contains(c)) {
+                    return Err("External username can only contain alphanumeric characters, underscores, hyphens, dots, and @".to_string());
+                }
+            }
+        }
+        
+        // Validate password; a missing password is generated by the service
+        match (&self.password, self.domain) {
+            (Some(_), UserDomain::External) => {
+                return Err("External users are authenticated externally and cannot have a password".to_string());
+            }
+            (Some(password), UserDomain::Local) => policy.check(&self.username, password)?,
+            (None, _) => {}
+        }
+        
+        // Validate roles
+        if self.roles.is_empty() {
+            return Err("At least one role must be specified".to_string());
+        }
+        
+        for role in &self.roles {
+            catalog.validate_role(role)?;
+        }
+        
+        Ok(())
+    }
+    
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Role {
+    pub role: String,
+    pub bucket: Option<String>,
+    pub scope: Option<String>,
+    pub collection: Option<String>,
+}
+
+impl Role {
+    #[allow(dead_code)] // Available for future use
+    pub fn new(role: String, bucket: Option<String>, scope: Option<String>, collection: Option<String>) -> Self {
+        Self {
+            role,
+            bucket,
+            scope,
+            collection,
+        }
+    }
+    
+    #[allow(dead_code)] // Available for future use
+    pub fn is_console_access(&self) -> bool {
+        roles::has_console_access(&self.role)
+    }
+    
+    #[allow(dead_code)] // Available for future use
+    pub fn is_bucket_specific(&self) -> bool {
+        self.bucket.is_some()
+    }
+    
+    #[allow(dead_code)] // Available for future use
+    pub fn is_scope_specific(&self) -> bool {
+        self.scope.is_some()
+    }
+    
+    #[allow(dead_code)] // Available for future use
+    pub fn is_collection_specific(&self) -> bool {
+        self.collection.is_some()
+    }
+
+    /// Parse a Couchbase role spec such as `data_reader[bucket:scope:collection]`
+    pub fn parse_spec(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let (role, target) = match spec.find('[') {
+            Some(start) => {
+                if !spec.ends_with(']') {
+                    return Err(format!("Invalid role spec '{}': missing closing ']'", spec));
+                }
+                (&spec[..start], Some(&spec[start + 1..spec.len() - 1]))
+            }
+            None => (spec, None),
+        };
+
+        if role.is_empty() {
+            return Err(format!("Invalid role spec '{}': missing role name", spec));
+        }
+
+        let mut parts = target
+            .map(|t| t.split(':').map(|p| p.to_string()).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter();
+
+        let role = Role {
+            role: role.to_string(),
+            bucket: parts.next(),
+            scope: parts.next(),
+            collection: parts.next(),
+        };
+
+        if parts.next().is_some() {
+            return Err(format!("Invalid role spec '{}': too many keyspace components", spec));
+        }
+
+        Ok(role)
+    }
+
+    /// Format the role as a Couchbase role spec, the inverse of `parse_spec`
+    pub fn to_spec(&self) -> String {
+        match &self.bucket {
+            Some(bucket) => {
+                let mut spec = format!("{}[{}", self.role, bucket);
+                if let Some(scope) = &self.scope {
+                    spec.push_str(&format!(":{}", scope));
+                    if let Some(collection) = &self.collection {
+                        spec.push_str(&format!(":{}", collection));
+                    }
+                }
+                spec.push(']');
+                spec
+            }
+            None => self.role.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserInfo {
+    pub username: String,
+    pub domain: UserDomain,
+    pub roles: Vec<Role>,
+    pub groups: Vec<String>,
+    /// The user's full name in Couchbase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+/// Response to a user creation; the generated password is only ever returned here
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedUser {
+    #[serde(flatten)]
+    pub user: UserInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordRequest {
+    /// Omit to have the service generate a strong password
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordChangeResponse {
+    pub username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_password: Option<String>,
+}
+
+// Password Policy Models
+const SPECIAL_CHARS: &str = "!@#$%^&*()-_=+[]{}?";
+
+// Common passwords rejected regardless of configuration
+pub const COMMON_PASSWORDS: &[&str] = &[
+    "password", "password1", "password123", "passw0rd", "p@ssw0rd", "123456", "12345678",
+    "123456789", "1234567890", "qwerty", "qwerty123", "qwertyuiop", "abc123", "111111",
+    "letmein", "welcome", "welcome1", "iloveyou", "admin", "admin123", "administrator",
+    "changeme", "secret", "monkey", "dragon", "football", "baseball", "sunshine", "trustno1",
+    "couchbase", "securepassword123!",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digits: bool,
+    pub require_special_chars: bool,
+    /// Lower-cased passwords that are always rejected
+    #[serde(skip)]
+    pub denylist: std::collections::HashSet<String>,
+}
+
+impl PasswordPolicy {
+    pub fn check(&self, username: &str, password: &str) -> Result<(), String> {
+        if password.is_empty() {
+            return Err("Password cannot be empty".to_string());
+        }
+
+        if password.chars().count() < self.min_length {
+            return Err(format!(
+                "Password must be at least {} characters long",
+                self.min_length
+            ));
+        }
+
+        let requirements = [
+            (self.require_uppercase, password.chars().any(|c| c.is_uppercase()), "an uppercase letter"),
+            (self.require_lowercase, password.chars().any(|c| c.is_lowercase()), "a lowercase letter"),
+            (self.require_digits, password.chars().any(|c| c.is_ascii_digit()), "a digit"),
+            (
+                self.require_special_chars,
+                password.chars().any(|c| !c.is_alphanumeric()),
+                "a special character",
+            ),
+        ];
+        for (required, present, label) in requirements {
+            if required && !present {
+                return Err(format!("Password must contain {}", label));
+            }
+        }
+
+        let lower = password.to_lowercase();
+        if lower == username.to_lowercase() {
+            return Err("Password cannot be the same as the username".to_string());
+        }
+
+        if self.denylist.contains(&lower) {
+            return Err("Password is too common; choose a different one".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Combine two policies, keeping the stricter setting of each
+    pub fn stricter(&self, other: &PasswordPolicy) -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: self.min_length.max(other.min_length),
+            require_uppercase: self.require_uppercase || other.require_uppercase,
+            require_lowercase: self.require_lowercase || other.require_lowercase,
+            require_digits: self.require_digits || other.require_digits,
+            require_special_chars: self.require_special_chars || other.require_special_chars,
+            denylist: self.denylist.union(&other.denylist).cloned().collect(),
+        }
+    }
+
+    /// Generate a random password that satisfies this policy
+    pub fn generate(&self) -> String {
+        use rand::seq::SliceRandom;
+        use rand::Rng;
+
+        const UPPER: &str = "ABCDEFGHJKLMNPQRSTUVWXYZ";
+        const LOWER: &str = "abcdefghijkmnopqrstuvwxyz";
+        const DIGITS: &str = "23456789";
+
+        let length = self.min_length.max(24);
+        let classes = [UPPER, LOWER, DIGITS, SPECIAL_CHARS];
+        let all: Vec<char> = classes.concat().chars().collect();
+
+        let mut rng = rand::thread_rng();
+        let pick = |rng: &mut rand::rngs::ThreadRng, set: &str| -> char {
+            let chars: Vec<char> = set.chars().collect();
+            chars[rng.gen_range(0..chars.len())]
+        };
+
+        // One character from every class guarantees any class requirement is met
+        let mut password: Vec<char> = classes.iter().map(|set| pick(&mut rng, set)).collect();
+        while password.len() < length {
+            password.push(all[rng.gen_range(0..all.len())]);
+        }
+        password.shuffle(&mut rng);
+
+        password.into_iter().collect()
+    }
+}
+
+// Role Template Models
+/// A named bundle of roles whose bucket, scope and collection may contain
+/// `{placeholder}` values filled in when the template is applied
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoleTemplate {
+    #[serde(default)]
+    pub description: String,
+    pub roles: Vec<Role>,
+}
+
+impl RoleTemplate {
+    /// Names of the placeholders used by this template, in first-use order
+    pub fn parameters(&self) -> Vec<String> {
+        let mut parameters: Vec<String> = Vec::new();
+        for role in &self.roles {
+            for value in [&role.bucket, &role.scope, &role.collection].into_iter().flatten() {
+                if let Some(name) = placeholder_name(value) {
+                    if !parameters.iter().any(|p| p == name) {
+                        parameters.push(name.to_string());
+                    }
+                }
+            }
+        }
+        parameters
+    }
+
+    pub fn expand(&self, params: &HashMap<String, String>) -> Result<Vec<Role>, String> {
+        let substitute = |value: &Option<String>| -> Result<Option<String>, String> {
+            match value.as_deref() {
+                Some(value) => match placeholder_name(value) {
+                    Some(name) => params
+                        .get(name)
+                        .map(|v| Some(v.clone()))
+                        .ok_or_else(|| format!("Role template requires parameter '{}'", name)),
+                    None => Ok(Some(value.to_string())),
+                },
+                None => Ok(None),
+            }
+        };
+
+        self.roles
+            .iter()
+            .map(|role| {
+                Ok(Role {
+                    role: role.role.clone(),
+                    bucket: substitute(&role.bucket)?,
+                    scope: substitute(&role.scope)?,
+                    collection: substitute(&role.collection)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Templates available even when none are configured
+    pub fn builtin() -> HashMap<String, RoleTemplate> {
+        let keyspace_role = |role: &str| Role {
+            role: role.to_string(),
+            bucket: Some("{bucket}".to_string()),
+            scope: Some("{scope}".to_string()),
+            collection: None,
+        };
+
+        HashMap::from([
+            (
+                "app-ro".to_string(),
+                RoleTemplate {
+                    description: "Application read-only access to a scope".to_string(),
+                    roles: vec![keyspace_role(roles::DATA_READER), keyspace_role("query_select")],
+                },
+            ),
+            (
+                "app-rw".to_string(),
+                RoleTemplate {
+                    description: "Application read-write access to a scope".to_string(),
+                    roles: vec![
+                        keyspace_role(roles::DATA_READER),
+                        keyspace_role(roles::DATA_WRITER),
+                        keyspace_role("query_select"),
+                    ],
+                },
+            ),
+        ])
+    }
+}
+
+fn placeholder_name(value: &str) -> Option<&str> {
+    value.strip_prefix('{')?.strip_suffix('}')
+}
+
+/// Configured role templates, keyed by name
+#[derive(Debug, Clone, Default)]
+pub struct RoleTemplates(pub HashMap<String, RoleTemplate>);
+
+impl RoleTemplates {
+    pub fn get(&self, name: &str) -> Option<&RoleTemplate> {
+        self.0.get(name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleTemplateInfo {
+    pub name: String,
+    pub description: String,
+    pub parameters: Vec<String>,
+    pub roles: Vec<Role>,
+}
+
+// Temporary Access Grant Models
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateGrantRequest {
+    pub roles: Vec<Role>,
+    /// How long the grant lasts, e.g. `90m`, `4h`, `1d` or a number of seconds
+    pub duration: String,
+    pub reason: Option<String>,
+}
+
+/// Roles temporarily added to a user, removed again once `expires_at` passes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Grant {
+    pub id: String,
+    pub username: String,
+    #[serde(default)]
+    pub domain: UserDomain,
+    /// Roles added by this grant; roles the user already held are not included
+    pub roles: Vec<Role>,
+    pub reason: Option<String>,
+    pub granted_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ListGrantsQuery {
+    pub username: Option<String>,
+    pub domain: Option<UserDomain>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GrantAction {
+    Granted,
+    Revoked,
+    Expired,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrantAuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub action: GrantAction,
+    pub grant_id: String,
+    pub username: String,
+    #[serde(default)]
+    pub domain: UserDomain,
+    pub roles: Vec<Role>,
+    pub actor: Option<String>,
+}
+
+/// Parse a duration such as `30s`, `90m`, `4h`, `2d` or a bare number of seconds
+pub fn parse_duration(value: &str) -> Result<chrono::Duration, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid duration '{}'. Use e.g. '90m', '4h' or '1d'", value);
+
+    let (number, unit_seconds) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1),
+        Some((i, 'm')) => (&value[..i], 60),
+        Some((i, 'h')) => (&value[..i], 3600),
+        Some((i, 'd')) => (&value[..i], 86400),
+        Some(_) => (value, 1),
+        None => return Err(invalid()),
+    };
+
+    let number: i64 = number.trim().parse().map_err(|_| invalid())?;
+    if number <= 0 {
+        return Err("Duration must be positive".to_string());
+    }
+
+    number
+        .checked_mul(unit_seconds)
+        .and_then(chrono::Duration::try_seconds)
+        .ok_or_else(invalid)
+}
+
+// Credential Rotation Models
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RotateCredentialsRequest {
+    /// Name of a configured secret sink to deliver the new password to
+    pub sink: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotationResult {
+    pub username: String,
+    pub rotated_at: DateTime<Utc>,
+    pub sink: Option<String>,
+    pub delivered: bool,
+    /// Only returned when there is no sink to deliver the new password to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotationPolicyRequest {
+    /// Rotation interval, e.g. `30d` or `12h`
+    pub interval: String,
+    /// Secret sink the new passwords are delivered to; required for a schedule
+    pub sink: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RotationPolicy {
+    pub username: String,
+    pub interval_seconds: i64,
+    pub sink: Option<String>,
+    pub last_rotated_at: Option<DateTime<Utc>>,
+    pub next_rotation_at: DateTime<Utc>,
+}
+
+// Bulk User Import/Export Models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UserFileFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Fail rows whose user already exists
+    #[default]
+    Create,
+    /// Overwrite existing users with the imported definition
+    Upsert,
+    /// Leave existing users untouched and report them as skipped
+    SkipExisting,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ImportUsersQuery {
+    pub format: Option<UserFileFormat>,
+    #[serde(default)]
+    pub mode: ImportMode,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ExportUsersQuery {
+    pub format: Option<UserFileFormat>,
+    pub domain: Option<UserDomain>,
+}
+
+/// One row of the CSV import/export format. Roles and groups are
+/// `;`-separated, roles use the Couchbase spec form `role[bucket:scope:collection]`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserCsvRecord {
+    pub username: String,
+    #[serde(default)]
+    pub domain: UserDomain,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub roles: String,
+    #[serde(default)]
+    pub groups: String,
+    #[serde(default)]
+    pub display_name: String,
 }
 
+/// A user as written by `GET /users/export`. Passwords are never exported.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct BucketInfo {
-    pub name: String,
-    pub ram_quota_mb: u32,
-    pub replica_number: u32,
-    pub eviction_policy: String,
-    pub compression_mode: String,
-    pub conflict_resolution_type: String,
-    pub status: String,
+pub struct UserExportRecord {
+    pub username: String,
+    pub domain: UserDomain,
+    pub roles: Vec<Role>,
+    pub groups: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportRowStatus {
+    Created,
+    Updated,
+    Skipped,
+    /// Row passed validation during a dry run
+    Valid,
+    Failed,
 }
 
-// Scope Management Models
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateScopeRequest {
-    pub scope_name: String,
+pub struct ImportRowResult {
+    pub row: usize,
+    pub username: String,
+    pub status: ImportRowStatus,
+    pub message: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ScopeInfo {
-    pub name: String,
-    pub collections: Vec<CollectionInfo>,
+pub struct ImportUsersResponse {
+    pub mode: ImportMode,
+    pub dry_run: bool,
+    pub total: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub results: Vec<ImportRowResult>,
 }
 
-// Collection Management Models
+// Permission Check Models
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateCollectionRequest {
-    pub collection_name: String,
-    pub max_ttl: Option<u32>,
-    pub history: Option<bool>,
+pub struct PermissionCheckRequest {
+    /// Couchbase permission strings, e.g. `cluster.bucket[orders].data.docs!read`
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CollectionInfo {
-    pub name: String,
-    pub max_ttl: Option<u32>,
-    pub history: Option<bool>,
-    pub scope: String,
+pub struct PermissionCheckResult {
+    pub permission: String,
+    pub decision: PermissionDecision,
+}
+
+/// A role a user receives through membership of a group
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InheritedRole {
+    pub group: String,
+    #[serde(flatten)]
+    pub role: Role,
 }
 
-// User Management Models
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateUserRequest {
+pub struct PermissionCheckResponse {
     pub username: String,
-    pub password: String,
+    pub results: Vec<PermissionCheckResult>,
     pub roles: Vec<Role>,
-    pub groups: Option<Vec<String>>,
-    pub display_name: Option<String>,
-    pub email: Option<String>,
+    pub inherited_roles: Vec<InheritedRole>,
 }
 
-impl CreateUserRequest {
-    #[allow(dead_code)] // Available for future use
-    pub fn has_console_access(&self) -> bool {
-        self.roles.iter().any(|role| roles::has_console_access(&role.role))
-    }
-    
-    #[allow(dead_code)] // Available for future use
-    pub fn get_bucket_permissions(&self) -> Vec<String> {
-        self.roles
-            .iter()
-            .filter_map(|role| role.bucket.clone())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect()
-    }
-    
-    pub fn validate(&self) -> Result<(), String> {
-        // Validate username
-        if self.username.is_empty() {
-            return Err("Username cannot be empty".to_string());
-        }
-        
-        if self.username.len() < 3 {
-            return Err("Username must be at least 3 characters long".to_string());
-        }
-        
-        if !self.username.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
-            return Err("Username can only contain alphanumeric characters, underscores, and hyphens".to_string());
-        }
-        
-        // Validate password
-        if self.password.is_empty() {
-            return Err("Password cannot be empty".to_string());
-        }
-        
-        if self.password.len() < 8 {
-            return Err("Password must be at least 8 characters long".to_string());
-        }
-        
-        // Validate roles
-        if self.roles.is_empty() {
-            return Err("At least one role must be specified".to_string());
-        }
-        
-        for role in &self.roles {
-            if !roles::is_valid_role(&role.role) {
-                return Err(format!("Invalid role: '{}'. Valid roles are: {:?}", 
-                    role.role, roles::ALL_ROLES));
-            }
-            
-            // Validate role-specific requirements
-            if roles::is_data_access_role(&role.role) && role.bucket.is_none() {
-                return Err(format!("Data access role '{}' requires a bucket to be specified", role.role));
-            }
-            
-            if role.scope.is_some() && role.bucket.is_none() {
-                return Err("Scope can only be specified when bucket is also specified".to_string());
-            }
-            
-            if role.collection.is_some() && role.scope.is_none() {
-                return Err("Collection can only be specified when scope is also specified".to_string());
-            }
-        }
-        
-        Ok(())
-    }
-    
+// RBAC Role Catalog Models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoleParameter {
+    Bucket,
+    Scope,
+    Collection,
 }
 
+/// A role as advertised by the cluster's `/settings/rbac/roles` endpoint
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Role {
+pub struct RoleDefinition {
     pub role: String,
-    pub bucket: Option<String>,
-    pub scope: Option<String>,
-    pub collection: Option<String>,
+    pub name: String,
+    pub description: String,
+    /// Keyspace parameters the role accepts; a bucket is required when present,
+    /// scope and collection narrow it further and default to `*`
+    pub parameters: Vec<RoleParameter>,
 }
 
-impl Role {
-    #[allow(dead_code)] // Available for future use
-    pub fn new(role: String, bucket: Option<String>, scope: Option<String>, collection: Option<String>) -> Self {
+impl RoleDefinition {
+    pub fn accepts(&self, parameter: RoleParameter) -> bool {
+        self.parameters.contains(&parameter)
+    }
+}
+
+/// The set of roles supported by the connected cluster
+#[derive(Debug, Clone)]
+pub struct RoleCatalog {
+    roles: HashMap<String, RoleDefinition>,
+}
+
+impl RoleCatalog {
+    pub fn new(definitions: Vec<RoleDefinition>) -> Self {
         Self {
-            role,
-            bucket,
-            scope,
-            collection,
+            roles: definitions
+                .into_iter()
+                .map(|def| (def.role.clone(), def))
+                .collect(),
         }
     }
-    
-    #[allow(dead_code)] // Available for future use
-    pub fn is_console_access(&self) -> bool {
-        matches!(self.role.as_str(), 
-            "admin" | "cluster_admin" | "replication_admin" | "views_admin" | "query_manage"
-        )
-    }
-    
-    #[allow(dead_code)] // Available for future use
-    pub fn is_bucket_specific(&self) -> bool {
-        self.bucket.is_some()
-    }
-    
-    #[allow(dead_code)] // Available for future use
-    pub fn is_scope_specific(&self) -> bool {
-        self.scope.is_some()
+
+    pub fn get(&self, role: &str) -> Option<&RoleDefinition> {
+        self.roles.get(role)
     }
-    
-    #[allow(dead_code)] // Available for future use
-    pub fn is_collection_specific(&self) -> bool {
-        self.collection.is_some()
+
+    /// All role definitions, sorted by role name
+    pub fn definitions(&self) -> Vec<&RoleDefinition> {
+        let mut definitions: Vec<_> = self.roles.values().collect();
+        definitions.sort_by(|a, b| a.role.cmp(&b.role));
+        definitions
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UserInfo {
-    pub username: String,
-    pub roles: Vec<Role>,
-    pub groups: Vec<String>,
+    pub fn validate_role(&self, role: &Role) -> Result<(), String> {
+        let definition = self.get(&role.role).ok_or_else(|| {
+            format!(
+                "Invalid role: '{}'. See GET /roles for the roles supported by this cluster",
+                role.role
+            )
+        })?;
+
+        if role.scope.is_some() && role.bucket.is_none() {
+            return Err("Scope can only be specified when bucket is also specified".to_string());
+        }
+
+        if role.collection.is_some() && role.scope.is_none() {
+            return Err("Collection can only be specified when scope is also specified".to_string());
+        }
+
+        if definition.accepts(RoleParameter::Bucket) && role.bucket.is_none() {
+            return Err(format!(
+                "Role '{}' requires a bucket to be specified (use '*' for all buckets)",
+                role.role
+            ));
+        }
+
+        let unsupported = [
+            (RoleParameter::Bucket, role.bucket.is_some(), "bucket"),
+            (RoleParameter::Scope, role.scope.is_some(), "scope"),
+            (RoleParameter::Collection, role.collection.is_some(), "collection"),
+        ];
+        for (parameter, given, label) in unsupported {
+            if given && !definition.accepts(parameter) {
+                return Err(format!("Role '{}' does not accept a {}", role.role, label));
+            }
+        }
+
+        Ok(())
+    }
 }
 
-// RBAC Role Constants
+// Well-known RBAC role names
 pub mod roles {
-    // Data Access Roles
     pub const DATA_READER: &str = "data_reader";
     pub const DATA_WRITER: &str = "data_writer";
-    pub const DATA_DCP_READER: &str = "data_dcp_reader";
-    pub const BUCKET_FULL_ACCESS: &str = "bucket_admin";
-    
-    // Administrative Roles (Console Access)
+    pub const BUCKET_FULL_ACCESS: &str = "bucket_full_access";
     pub const ADMIN: &str = "admin";
     pub const CLUSTER_ADMIN: &str = "cluster_admin";
-    pub const REPLICATION_ADMIN: &str = "replication_admin";
-    pub const VIEWS_ADMIN: &str = "views_admin";
-    pub const QUERY_MANAGE: &str = "query_manage";
-    
-    // Query Roles
-    pub const QUERY_SELECT: &str = "query_select";
-    pub const QUERY_INSERT: &str = "query_insert";
-    pub const QUERY_UPDATE: &str = "query_update";
-    pub const QUERY_DELETE: &str = "query_delete";
-    
-    // Role Categories
+
+    // Administrative roles that grant access to the web console
     pub const CONSOLE_ACCESS_ROLES: &[&str] = &[
-        ADMIN, CLUSTER_ADMIN, REPLICATION_ADMIN, VIEWS_ADMIN, QUERY_MANAGE
-    ];
-    
-    pub const DATA_ACCESS_ROLES: &[&str] = &[
-        DATA_READER, DATA_WRITER, DATA_DCP_READER, BUCKET_FULL_ACCESS
-    ];
-    
-    pub const QUERY_ROLES: &[&str] = &[
-        QUERY_SELECT, QUERY_INSERT, QUERY_UPDATE, QUERY_DELETE, QUERY_MANAGE
-    ];
-    
-    pub const ALL_ROLES: &[&str] = &[
-        DATA_READER, DATA_WRITER, DATA_DCP_READER, BUCKET_FULL_ACCESS,
-        ADMIN, CLUSTER_ADMIN, REPLICATION_ADMIN, VIEWS_ADMIN,
-        QUERY_SELECT, QUERY_INSERT, QUERY_UPDATE, QUERY_DELETE, QUERY_MANAGE
+        ADMIN, CLUSTER_ADMIN, "replication_admin", "views_admin", "query_manage_index",
+        "security_admin", "ro_admin", "bucket_admin",
     ];
-    
-    pub fn is_valid_role(role: &str) -> bool {
-        ALL_ROLES.contains(&role)
-    }
-    
+
     pub fn has_console_access(role: &str) -> bool {
         CONSOLE_ACCESS_ROLES.contains(&role)
     }
-    
-    pub fn is_data_access_role(role: &str) -> bool {
-        DATA_ACCESS_ROLES.contains(&role)
-    }
-    
+
     pub fn is_query_role(role: &str) -> bool {
-        QUERY_ROLES.contains(&role)
+        role.starts_with("query_")
+    }
+}
+
+// Pagination Models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn apply(&self, ordering: std::cmp::Ordering) -> std::cmp::Ordering {
+        match self {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+}
+
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketSortField {
+    #[default]
+    Name,
+    RamQuotaMb,
+    Status,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ListBucketsQuery {
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+    /// Only buckets whose name starts with this prefix
+    pub prefix: Option<String>,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub sort_by: BucketSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ListScopesQuery {
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+    /// Only scopes whose name starts with this prefix
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PageInfo {
+    /// Number of items matching the filters across all pages
+    pub total: usize,
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+    pub next_cursor: Option<String>,
+}
+
+/// An `ApiResponse` carrying one page of a list together with paging details
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PagedResponse<T> {
+    #[serde(flatten)]
+    pub response: ApiResponse<Vec<T>>,
+    pub pagination: PageInfo,
+}
+
+impl<T> PagedResponse<T> {
+    pub fn new(items: Vec<T>, pagination: PageInfo) -> Self {
+        Self {
+            response: ApiResponse::success(items),
+            pagination,
+        }
+    }
+
+    pub fn error(message: String) -> Self {
+        Self {
+            response: ApiResponse::error(message),
+            pagination: PageInfo::default(),
+        }
+    }
+
+    /// Page an in-memory list. Without a page size every item is returned.
+    pub fn from_items(items: Vec<T>, page: Option<usize>, page_size: Option<usize>) -> Self {
+        let total = items.len();
+        let Some(page_size) = page_size.map(|size| size.clamp(1, MAX_PAGE_SIZE)) else {
+            return Self::new(items, PageInfo { total, ..Default::default() });
+        };
+
+        let page = page.unwrap_or(1).max(1);
+        let items: Vec<T> = items
+            .into_iter()
+            .skip((page - 1).saturating_mul(page_size))
+            .take(page_size)
+            .collect();
+
+        Self::new(
+            items,
+            PageInfo {
+                total,
+                page: Some(page),
+                page_size: Some(page_size),
+                next_cursor: None,
+            },
+        )
     }
 }
 
@@ -242,6 +1756,9 @@ pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub message: Option<String>,
+    /// Per-field problems when a request fails validation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<FieldError>>,
 }
 
 impl<T> ApiResponse<T> {
@@ -250,6 +1767,7 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             message: None,
+            errors: None,
         }
     }
 
@@ -258,37 +1776,48 @@ impl<T> ApiResponse<T> {
             success: false,
             data: None,
             message: Some(message),
+            errors: None,
+        }
+    }
+
+    pub fn validation_failed(errors: Vec<FieldError>) -> Self {
+        Self {
+            success: false,
+            data: None,
+            message: Some(validation::summarize(&errors)),
+            errors: Some(errors),
         }
     }
 }
 
 // Couchbase REST API Models
 #[derive(Debug, Serialize, Deserialize)]
+/// Settings sent to the cluster manager; `None` fields are left to Couchbase
 pub struct CouchbaseBucketConfig {
     pub name: String,
+    pub bucket_type: BucketType,
     pub ram_quota_mb: u32,
-    pub replica_number: u32,
-    pub eviction_policy: String,
-    pub compression_mode: String,
-    pub conflict_resolution_type: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CouchbaseScopeConfig {
-    pub name: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CouchbaseCollectionConfig {
-    pub name: String,
+    pub replica_number: Option<u32>,
+    pub eviction_policy: Option<String>,
+    pub compression_mode: Option<String>,
+    pub conflict_resolution_type: Option<String>,
+    pub storage_backend: Option<StorageBackend>,
     pub max_ttl: Option<u32>,
-    pub history: Option<bool>,
+    pub flush_enabled: bool,
+    pub durability_min_level: Option<String>,
+    pub replica_index: Option<bool>,
+    pub history_retention_collection_default: Option<bool>,
+    pub history_retention_bytes: Option<u64>,
+    pub history_retention_seconds: Option<u64>,
+    pub num_vbuckets: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CouchbaseUserConfig {
     pub name: String,
-    pub password: String,
+    pub domain: UserDomain,
+    /// Left out of the request when `None`, keeping an existing user's password
+    pub password: Option<String>,
     pub roles: Vec<CouchbaseRole>,
 }
 
@@ -300,22 +1829,175 @@ pub struct CouchbaseRole {
     pub collection_name: Option<String>,
 }
 
-// Health Check Models
-#[derive(Debug, Serialize, Deserialize)]
-pub struct HealthCheck {
-    pub status: String,
-    pub timestamp: String,
-    pub version: String,
-    pub uptime: u64,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-// Metrics Models
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Metrics {
-    pub requests_total: u64,
-    pub requests_success: u64,
-    pub requests_error: u64,
-    pub couchbase_operations_total: u64,
-    pub couchbase_operations_success: u64,
-    pub couchbase_operations_error: u64,
+    #[test]
+    fn role_specs_round_trip() {
+        for (spec, bucket, scope, collection) in [
+            ("admin", None, None, None),
+            ("bucket_admin[travel]", Some("travel"), None, None),
+            ("bucket_admin[*]", Some("*"), None, None),
+            ("data_reader[travel:inventory]", Some("travel"), Some("inventory"), None),
+            ("data_writer[travel:inventory:hotel]", Some("travel"), Some("inventory"), Some("hotel")),
+        ] {
+            let role = Role::parse_spec(spec).unwrap();
+            assert_eq!(role.bucket.as_deref(), bucket, "{}", spec);
+            assert_eq!(role.scope.as_deref(), scope, "{}", spec);
+            assert_eq!(role.collection.as_deref(), collection, "{}", spec);
+            assert_eq!(role.to_spec(), spec);
+        }
+
+        let role = Role::parse_spec("  data_reader[travel]  ").unwrap();
+        assert_eq!((role.role.as_str(), role.to_spec().as_str()), ("data_reader", "data_reader[travel]"));
+    }
+
+    #[test]
+    fn malformed_role_specs() {
+        for (spec, error) in [
+            ("data_reader[travel", "missing closing ']'"),
+            ("[travel]", "missing role name"),
+            ("", "missing role name"),
+            ("data_reader[a:b:c:d]", "too many keyspace components"),
+        ] {
+            let message = Role::parse_spec(spec).unwrap_err();
+            assert!(message.contains(error), "{}: {}", spec, message);
+        }
+    }
+
+    fn catalog() -> RoleCatalog {
+        let definition = |role: &str, parameters: Vec<RoleParameter>| RoleDefinition {
+            role: role.to_string(),
+            name: role.to_string(),
+            description: String::new(),
+            parameters,
+        };
+        RoleCatalog::new(vec![
+            definition("admin", vec![]),
+            definition("bucket_admin", vec![RoleParameter::Bucket]),
+            definition(
+                "data_reader",
+                vec![RoleParameter::Bucket, RoleParameter::Scope, RoleParameter::Collection],
+            ),
+        ])
+    }
+
+    #[test]
+    fn catalog_roles_are_accepted_with_their_parameters() {
+        let catalog = catalog();
+        for spec in [
+            "admin",
+            "bucket_admin[travel]",
+            "bucket_admin[*]",
+            "data_reader[travel]",
+            "data_reader[travel:inventory]",
+            "data_reader[travel:inventory:hotel]",
+        ] {
+            assert_eq!(catalog.validate_role(&Role::parse_spec(spec).unwrap()), Ok(()), "{}", spec);
+        }
+        assert_eq!(
+            catalog.definitions().iter().map(|d| d.role.as_str()).collect::<Vec<_>>(),
+            ["admin", "bucket_admin", "data_reader"]
+        );
+    }
+
+    #[test]
+    fn catalog_rejects_unknown_roles_and_wrong_parameters() {
+        let catalog = catalog();
+        let role = |role: &str, bucket: Option<&str>, scope: Option<&str>, collection: Option<&str>| Role {
+            role: role.to_string(),
+            bucket: bucket.map(str::to_string),
+            scope: scope.map(str::to_string),
+            collection: collection.map(str::to_string),
+        };
+
+        for (role, error) in [
+            (role("data_writer", Some("travel"), None, None), "Invalid role: 'data_writer'"),
+            (role("bucket_admin", None, None, None), "requires a bucket"),
+            (role("data_reader", None, None, None), "requires a bucket"),
+            (role("admin", Some("travel"), None, None), "does not accept a bucket"),
+            (role("bucket_admin", Some("travel"), Some("inventory"), None), "does not accept a scope"),
+            (role("data_reader", None, Some("inventory"), None), "Scope can only be specified"),
+            (role("data_reader", Some("travel"), None, Some("hotel")), "Collection can only be specified"),
+        ] {
+            let message = catalog.validate_role(&role).unwrap_err();
+            assert!(message.contains(error), "{}: {}", role.to_spec(), message);
+        }
+    }
+
+    fn templates() -> RoleTemplates {
+        let template: RoleTemplate = serde_json::from_value(serde_json::json!({
+            "description": "Read-write access to a single collection",
+            "roles": [
+                {"role": "data_reader", "bucket": "{bucket}", "scope": "{scope}", "collection": "{collection}"},
+                {"role": "data_writer", "bucket": "{bucket}", "scope": "inventory", "collection": "{collection}"},
+                {"role": "query_select", "bucket": "{bucket}"}
+            ]
+        }))
+        .unwrap();
+        RoleTemplates(HashMap::from([("app-rw".to_string(), template)]))
+    }
+
+    fn create_request(json: serde_json::Value) -> CreateUserRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn templates_expand_placeholders_after_explicit_roles() {
+        let mut request = create_request(serde_json::json!({
+            "username": "app",
+            "roles": [{"role": "bucket_admin", "bucket": "audit"}],
+            "template": "app-rw",
+            "params": {"bucket": "travel", "scope": "tenant_a", "collection": "hotel"}
+        }));
+
+        request.apply_template(&templates()).unwrap();
+
+        let specs: Vec<String> = request.roles.iter().map(Role::to_spec).collect();
+        assert_eq!(
+            specs,
+            [
+                "bucket_admin[audit]",
+                "data_reader[travel:tenant_a:hotel]",
+                "data_writer[travel:inventory:hotel]",
+                "query_select[travel]",
+            ]
+        );
+        // The template is consumed, so applying again adds nothing
+        assert!(request.template.is_none() && request.params.is_none());
+        request.apply_template(&templates()).unwrap();
+        assert_eq!(request.roles.len(), 4);
+    }
+
+    #[test]
+    fn templates_need_every_placeholder() {
+        let mut request = create_request(serde_json::json!({
+            "username": "app",
+            "template": "app-rw",
+            "params": {"bucket": "travel", "collection": "hotel"}
+        }));
+        let message = request.apply_template(&templates()).unwrap_err();
+        assert!(message.contains("requires parameter 'scope'"), "{}", message);
+
+        let mut request = create_request(serde_json::json!({"username": "app", "template": "app-rw"}));
+        assert!(request.apply_template(&templates()).is_err());
+    }
+
+    #[test]
+    fn unknown_templates_are_rejected() {
+        let mut request = create_request(serde_json::json!({
+            "username": "app",
+            "roles": [{"role": "admin"}],
+            "template": "app-ro"
+        }));
+        let message = request.apply_template(&templates()).unwrap_err();
+        assert!(message.contains("Unknown role template 'app-ro'"), "{}", message);
+        assert_eq!(request.roles.len(), 1);
+
+        // Requests without a template are left alone
+        let mut request = create_request(serde_json::json!({"username": "app", "roles": [{"role": "admin"}]}));
+        request.apply_template(&templates()).unwrap();
+        assert_eq!(request.roles.len(), 1);
+    }
 }
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+use tracing::warn;
+
+use crate::{
+    config::PasswordPolicyConfig,
+    error::Result,
+    models::{PasswordPolicy, COMMON_PASSWORDS},
+    services::CouchbaseService,
+};
+
+/// Resolves the password policy to enforce: the configured policy, tightened by
+/// the cluster's own policy when `sync_with_cluster` is enabled.
+pub struct PasswordPolicyEnforcer {
+    base: PasswordPolicy,
+    sync_with_cluster: bool,
+}
+
+impl PasswordPolicyEnforcer {
+    pub fn from_config(config: &PasswordPolicyConfig) -> Result<Self> {
+        let mut denylist: HashSet<String> = COMMON_PASSWORDS.iter().map(|p| p.to_string()).collect();
+        denylist.extend(config.denylist.iter().map(|p| p.to_lowercase()));
+
+        if let Some(path) = &config.denylist_file {
+            let contents = std::fs::read_to_string(path)?;
+            denylist.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_lowercase),
+            );
+        }
+
+        Ok(Self {
+            base: PasswordPolicy {
+                min_length: config.min_length,
+                require_uppercase: config.require_uppercase,
+                require_lowercase: config.require_lowercase,
+                require_digits: config.require_digits,
+                require_special_chars: config.require_special_chars,
+                denylist,
+            },
+            sync_with_cluster: config.sync_with_cluster,
+        })
+    }
+
+    /// The policy to enforce right now. Falls back to the configured policy if the
+    /// cluster's policy cannot be read.
+    pub async fn effective_policy(&self, couchbase_service: &CouchbaseService) -> PasswordPolicy {
+        if !self.sync_with_cluster {
+            return self.base.clone();
+        }
+
+        match couchbase_service.get_password_policy().await {
+            Ok(cluster_policy) => self.base.stricter(&cluster_policy),
+            Err(e) => {
+                warn!("Failed to read cluster password policy, using configured policy: {}", e);
+                self.base.clone()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(min_length: usize) -> PasswordPolicy {
+        PasswordPolicy {
+            min_length,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digits: true,
+            require_special_chars: true,
+            denylist: ["correcthorse1!".to_string()].into_iter().collect(),
+        }
+    }
+
+    fn config() -> PasswordPolicyConfig {
+        PasswordPolicyConfig {
+            min_length: 12,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digits: false,
+            require_special_chars: false,
+            denylist: vec!["Tr0ub4dor&3".to_string()],
+            denylist_file: None,
+            sync_with_cluster: false,
+        }
+    }
+
+    #[test]
+    fn generated_passwords_satisfy_the_policy() {
+        for min_length in [0, 8, 24, 64] {
+            let policy = policy(min_length);
+            for _ in 0..200 {
+                let password = policy.generate();
+                assert!(password.chars().count() >= min_length.max(24));
+                assert_eq!(policy.check("app-user", &password), Ok(()), "{}", password);
+            }
+        }
+    }
+
+    #[test]
+    fn each_rule_is_enforced() {
+        let policy = policy(10);
+        assert_eq!(policy.check("app-user", "Aa1!aaaaaa"), Ok(()));
+
+        for (password, message) in [
+            ("", "Password cannot be empty"),
+            ("Aa1!aaaaa", "Password must be at least 10 characters long"),
+            ("aa1!aaaaaa", "Password must contain an uppercase letter"),
+            ("AA1!AAAAAA", "Password must contain a lowercase letter"),
+            ("Aaa!aaaaaa", "Password must contain a digit"),
+            ("Aa1aaaaaaa", "Password must contain a special character"),
+            ("CorrectHorse1!", "Password is too common; choose a different one"),
+        ] {
+            assert_eq!(policy.check("app-user", password), Err(message.to_string()), "{}", password);
+        }
+
+        assert_eq!(
+            policy.check("admin-user1!", "Admin-User1!"),
+            Err("Password cannot be the same as the username".to_string())
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_not_enforced() {
+        let policy = PasswordPolicy {
+            min_length: 4,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digits: false,
+            require_special_chars: false,
+            denylist: Default::default(),
+        };
+        assert_eq!(policy.check("app-user", "abcd"), Ok(()));
+        assert!(policy.check("app-user", "abc").is_err());
+    }
+
+    #[test]
+    fn stricter_keeps_the_strictest_setting_of_each() {
+        let lenient = PasswordPolicy {
+            min_length: 16,
+            require_uppercase: true,
+            require_lowercase: false,
+            require_digits: false,
+            require_special_chars: false,
+            denylist: ["one".to_string()].into_iter().collect(),
+        };
+        let strict = PasswordPolicy {
+            min_length: 8,
+            require_uppercase: false,
+            require_lowercase: true,
+            require_digits: true,
+            require_special_chars: false,
+            denylist: ["two".to_string()].into_iter().collect(),
+        };
+
+        for merged in [lenient.stricter(&strict), strict.stricter(&lenient)] {
+            assert_eq!(merged.min_length, 16);
+            assert!(merged.require_uppercase);
+            assert!(merged.require_lowercase);
+            assert!(merged.require_digits);
+            assert!(!merged.require_special_chars);
+            assert_eq!(merged.denylist.len(), 2);
+            assert!(merged.denylist.contains("one") && merged.denylist.contains("two"));
+        }
+    }
+
+    #[test]
+    fn configured_denylist_is_case_insensitive() {
+        let enforcer = PasswordPolicyEnforcer::from_config(&config()).unwrap();
+        assert!(enforcer.base.check("app-user", "tr0ub4dor&3").is_err());
+        assert!(enforcer.base.check("app-user", "TR0UB4DOR&3").is_err());
+        // Built-in common passwords are always included
+        assert!(COMMON_PASSWORDS.contains(&"securepassword123!"));
+        assert!(enforcer.base.check("app-user", "SecurePassword123!").is_err());
+    }
+}
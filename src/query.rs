@@ -0,0 +1,380 @@
+//! Support for `POST /query`: the read-only statement guard and incremental parsing
+//! of query service responses so rows can be streamed as they arrive.
+
+use axum::body::Bytes;
+use futures::{stream, Stream};
+use std::collections::VecDeque;
+
+use crate::models::QuerySummary;
+
+// Backticks inside an identifier are escaped by doubling them
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// `query_context` for statements that use bare collection names
+pub fn query_context(bucket: &str, scope: Option<&str>) -> String {
+    match scope {
+        Some(scope) => format!("default:{}.{}", quote_identifier(bucket), quote_identifier(scope)),
+        None => format!("default:{}", quote_identifier(bucket)),
+    }
+}
+
+/// Statements allowed in read-only mode. The query service's `readonly` flag is
+/// also set, so this is a fast, friendly rejection rather than the only check.
+const READ_ONLY_KEYWORDS: &[&str] = &["SELECT", "WITH", "EXPLAIN", "INFER", "ADVISE"];
+
+pub fn is_read_only_statement(statement: &str) -> bool {
+    leading_keyword(statement).is_some_and(|keyword| {
+        READ_ONLY_KEYWORDS
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(keyword))
+    })
+}
+
+// First word of the statement, after whitespace, comments and opening parentheses
+fn leading_keyword(statement: &str) -> Option<&str> {
+    let mut rest = statement;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/")?.1;
+        } else {
+            break;
+        }
+    }
+
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    (end > 0).then(|| &rest[..end])
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum State {
+    /// In the response document, looking for the top-level `results` key
+    #[default]
+    Envelope,
+    /// Seen `"results":`, waiting for the array
+    ExpectArray,
+    /// Inside the results array
+    Results,
+    /// Past the results array
+    AfterResults,
+}
+
+/// Splits a query service response into result rows as bytes arrive. Everything
+/// outside the `results` array (status, errors, metrics) is kept, with an empty
+/// results array, and parsed once the response is complete.
+#[derive(Default)]
+pub struct ResultsSplitter {
+    state: State,
+    envelope: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    // Strings at the top level of the envelope, to spot the `results` key
+    string: Vec<u8>,
+    last_string: Option<Vec<u8>>,
+    row: Vec<u8>,
+    row_depth: usize,
+    row_is_scalar: bool,
+}
+
+impl ResultsSplitter {
+    /// Feed the next chunk of the response and return the rows it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Bytes> {
+        let mut rows = Vec::new();
+        for &byte in chunk {
+            self.push(byte, &mut rows);
+        }
+        rows
+    }
+
+    /// The response without its rows, once all of it has been fed
+    pub fn envelope(&self) -> Option<serde_json::Value> {
+        serde_json::from_slice(&self.envelope).ok()
+    }
+
+    fn push(&mut self, byte: u8, rows: &mut Vec<Bytes>) {
+        match self.state {
+            State::Envelope | State::AfterResults => self.push_envelope(byte),
+            State::ExpectArray => {
+                if byte.is_ascii_whitespace() {
+                    self.envelope.push(byte);
+                } else if byte == b'[' {
+                    self.envelope.push(byte);
+                    self.state = State::Results;
+                } else {
+                    // `results` is not an array, e.g. null
+                    self.state = State::Envelope;
+                    self.push_envelope(byte);
+                }
+            }
+            State::Results => self.push_results(byte, rows),
+        }
+    }
+
+    fn push_envelope(&mut self, byte: u8) {
+        self.envelope.push(byte);
+        let top_level = self.depth == 1;
+
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                if top_level {
+                    self.last_string = Some(std::mem::take(&mut self.string));
+                }
+                return;
+            }
+            if top_level {
+                self.string.push(byte);
+            }
+            return;
+        }
+
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                self.string.clear();
+            }
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+            b',' => self.last_string = None,
+            b':' if top_level
+                && self.state == State::Envelope
+                && self.last_string.as_deref() == Some(b"results".as_slice()) =>
+            {
+                self.state = State::ExpectArray;
+            }
+            _ => {}
+        }
+    }
+
+    fn push_results(&mut self, byte: u8, rows: &mut Vec<Bytes>) {
+        if self.row.is_empty() {
+            match byte {
+                b']' => {
+                    self.envelope.push(byte);
+                    self.state = State::AfterResults;
+                }
+                b',' => {}
+                _ if byte.is_ascii_whitespace() => {}
+                _ => {
+                    self.row.push(byte);
+                    self.in_string = byte == b'"';
+                    self.row_depth = usize::from(matches!(byte, b'{' | b'['));
+                    self.row_is_scalar = !self.in_string && self.row_depth == 0;
+                }
+            }
+            return;
+        }
+
+        if self.row_is_scalar {
+            if byte == b',' || byte == b']' || byte.is_ascii_whitespace() {
+                rows.push(Bytes::from(std::mem::take(&mut self.row)));
+                self.push_results(byte, rows);
+            } else {
+                self.row.push(byte);
+            }
+            return;
+        }
+
+        self.row.push(byte);
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+            }
+        } else {
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.row_depth += 1,
+                b'}' | b']' => self.row_depth -= 1,
+                _ => {}
+            }
+        }
+
+        if !self.in_string && self.row_depth == 0 {
+            rows.push(Bytes::from(std::mem::take(&mut self.row)));
+        }
+    }
+}
+
+pub enum QueryEvent {
+    /// One result row as raw JSON
+    Row(Bytes),
+    /// Always the last event
+    Done(QuerySummary),
+}
+
+struct RowStream {
+    response: Option<reqwest::Response>,
+    splitter: ResultsSplitter,
+    pending: VecDeque<Bytes>,
+    row_count: usize,
+    max_rows: usize,
+}
+
+/// Rows of a query as they arrive, followed by a summary. Reading stops after
+/// `max_rows`; dropping the response closes the connection and cancels the query.
+pub fn stream_rows(response: reqwest::Response, max_rows: usize) -> impl Stream<Item = QueryEvent> {
+    let state = RowStream {
+        response: Some(response),
+        splitter: ResultsSplitter::default(),
+        pending: VecDeque::new(),
+        row_count: 0,
+        max_rows,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(row) = state.pending.pop_front() {
+                if state.row_count == state.max_rows {
+                    state.response = None;
+                    state.pending.clear();
+                    let summary = QuerySummary {
+                        status: "truncated".to_string(),
+                        row_count: state.row_count,
+                        truncated: true,
+                        ..Default::default()
+                    };
+                    return Some((QueryEvent::Done(summary), state));
+                }
+                state.row_count += 1;
+                return Some((QueryEvent::Row(row), state));
+            }
+
+            let response = state.response.as_mut()?;
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    let rows = state.splitter.feed(&chunk);
+                    state.pending.extend(rows);
+                }
+                Ok(None) => {
+                    state.response = None;
+                    let summary = summary(state.splitter.envelope(), state.row_count);
+                    return Some((QueryEvent::Done(summary), state));
+                }
+                Err(e) => {
+                    state.response = None;
+                    let summary = QuerySummary {
+                        status: "errors".to_string(),
+                        row_count: state.row_count,
+                        errors: vec![serde_json::json!({ "msg": e.to_string() })],
+                        ..Default::default()
+                    };
+                    return Some((QueryEvent::Done(summary), state));
+                }
+            }
+        }
+    })
+}
+
+fn summary(envelope: Option<serde_json::Value>, row_count: usize) -> QuerySummary {
+    let Some(envelope) = envelope else {
+        return QuerySummary {
+            status: "errors".to_string(),
+            row_count,
+            errors: vec![serde_json::json!({ "msg": "Malformed response from the query service" })],
+            ..Default::default()
+        };
+    };
+
+    let list = |key: &str| envelope[key].as_array().cloned().unwrap_or_default();
+    QuerySummary {
+        status: envelope["status"].as_str().unwrap_or("unknown").to_string(),
+        row_count,
+        truncated: false,
+        errors: list("errors"),
+        warnings: list("warnings"),
+        metrics: envelope.get("metrics").cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(chunks: &[&str]) -> (Vec<String>, serde_json::Value) {
+        let mut splitter = ResultsSplitter::default();
+        let rows = chunks
+            .iter()
+            .flat_map(|chunk| splitter.feed(chunk.as_bytes()))
+            .map(|row| String::from_utf8(row.to_vec()).unwrap())
+            .collect();
+        (rows, splitter.envelope().unwrap())
+    }
+
+    #[test]
+    fn read_only_statements() {
+        for statement in [
+            "SELECT * FROM airline",
+            "  select 1",
+            "(SELECT 1) UNION (SELECT 2)",
+            "-- comment\nSELECT 1",
+            "/* hint */ WITH a AS (SELECT 1) SELECT a",
+            "EXPLAIN DELETE FROM airline",
+            "INFER airline",
+        ] {
+            assert!(is_read_only_statement(statement), "{}", statement);
+        }
+        for statement in [
+            "DELETE FROM airline",
+            "UPSERT INTO airline VALUES ('k', {})",
+            "CREATE INDEX i ON airline(x)",
+            "DROP PRIMARY INDEX ON airline",
+            "/* SELECT */ UPDATE airline SET x = 1",
+            "/* unterminated SELECT",
+            "",
+        ] {
+            assert!(!is_read_only_statement(statement), "{}", statement);
+        }
+    }
+
+    #[test]
+    fn splits_rows_across_chunks() {
+        let (rows, envelope) = split(&[
+            r#"{"requestID":"r1","signature":{"results":"json"},"res"#,
+            r#"ults":[{"name":"a","tags":["x","]"]},{"name":"b\"}"#,
+            r#""}, 42 ,"str,ing",null,[1,2]],"status":"success","#,
+            r#""metrics":{"resultCount":6}}"#,
+        ]);
+
+        assert_eq!(
+            rows,
+            [
+                r#"{"name":"a","tags":["x","]"]}"#,
+                r#"{"name":"b\"}"}"#,
+                "42",
+                r#""str,ing""#,
+                "null",
+                "[1,2]",
+            ]
+        );
+        assert_eq!(envelope["status"], "success");
+        assert_eq!(envelope["results"], serde_json::json!([]));
+        assert_eq!(envelope["metrics"]["resultCount"], 6);
+    }
+
+    #[test]
+    fn keeps_errors_without_results() {
+        let (rows, envelope) = split(&[
+            r#"{"status":"errors","errors":[{"code":3000,"msg":"syntax error - at results"}]}"#,
+        ]);
+        assert!(rows.is_empty());
+
+        let summary = summary(Some(envelope), 0);
+        assert_eq!(summary.status, "errors");
+        assert_eq!(summary.errors[0]["code"], 3000);
+    }
+}
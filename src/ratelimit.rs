@@ -0,0 +1,208 @@
+use prometheus::{register_int_counter_vec, IntCounterVec};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::RateLimitConfig;
+
+static RATE_LIMITED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "couchbase_admin_rate_limited_requests_total",
+        "Requests rejected by the rate limiter, by route",
+        &["route"]
+    )
+    .expect("rate limit metric registers once")
+});
+
+// Buckets untouched for this long are full again and can be forgotten
+const IDLE_BUCKET_TTL: Duration = Duration::from_secs(600);
+
+// Idle buckets are pruned once more than this many are tracked
+const MAX_BUCKETS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    per_second: f64,
+    burst: f64,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Per-client token buckets. Routes with an override in `rate_limit.routes` get
+/// their own bucket per client; every other route shares the client's default bucket.
+pub struct RateLimiter {
+    enabled: bool,
+    default: Limit,
+    routes: HashMap<String, Limit>,
+    buckets: Mutex<HashMap<(String, String), TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let limit = |per_second: f64, burst: u32| Limit {
+            per_second: per_second.max(f64::MIN_POSITIVE),
+            burst: burst.max(1) as f64,
+        };
+
+        Self {
+            enabled: config.enabled,
+            default: limit(config.requests_per_second, config.burst),
+            routes: config
+                .routes
+                .iter()
+                // The config loader lowercases keys, so routes are matched case-insensitively
+                .map(|(route, l)| (route.to_lowercase(), limit(l.requests_per_second, l.burst)))
+                .collect(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `client` on `route` ("METHOD /path"). On rejection returns
+    /// how long until a token is available.
+    pub fn check(&self, client: &str, route: &str) -> Result<(), Duration> {
+        self.check_at(client, route, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, route: &str, now: Instant) -> Result<(), Duration> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let route_key = route.to_lowercase();
+        let (scope, limit) = match self.routes.get(&route_key) {
+            Some(limit) => (route_key.as_str(), *limit),
+            None => ("*", self.default),
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_BUCKETS {
+            buckets.retain(|_, bucket| now.saturating_duration_since(bucket.updated_at) < IDLE_BUCKET_TTL);
+        }
+
+        let bucket = buckets
+            .entry((client.to_string(), scope.to_string()))
+            .or_insert(TokenBucket {
+                tokens: limit.burst,
+                updated_at: now,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.burst);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            RATE_LIMITED.with_label_values(&[route]).inc();
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RouteRateLimit;
+
+    fn limiter(requests_per_second: f64, burst: u32, routes: &[(&str, f64, u32)]) -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            enabled: true,
+            requests_per_second,
+            burst,
+            routes: routes
+                .iter()
+                .map(|&(route, requests_per_second, burst)| {
+                    (route.to_string(), RouteRateLimit { requests_per_second, burst })
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn burst_is_available_at_once_then_rejected() {
+        let limiter = limiter(1.0, 3, &[]);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("user:a", "GET /buckets", now).is_ok());
+        }
+        let retry_after = limiter.check_at("user:a", "GET /buckets", now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(1));
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at("user:b", "GET /buckets", now).is_ok());
+    }
+
+    #[test]
+    fn tokens_refill_up_to_the_burst() {
+        let limiter = limiter(2.0, 2, &[]);
+        let now = Instant::now();
+        for _ in 0..2 {
+            assert!(limiter.check_at("user:a", "GET /users", now).is_ok());
+        }
+
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check_at("user:a", "GET /users", later).is_ok());
+        assert!(limiter.check_at("user:a", "GET /users", later).is_err());
+
+        // A long idle period refills no more than the burst
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..2 {
+            assert!(limiter.check_at("user:a", "GET /users", much_later).is_ok());
+        }
+        assert!(limiter.check_at("user:a", "GET /users", much_later).is_err());
+    }
+
+    #[test]
+    fn route_overrides_have_their_own_bucket() {
+        let limiter = limiter(100.0, 1, &[("POST /users", 1.0, 2)]);
+        let now = Instant::now();
+
+        assert!(limiter.check_at("user:a", "GET /buckets", now).is_ok());
+        // Routes without an override share the default bucket
+        assert!(limiter.check_at("user:a", "GET /users", now).is_err());
+
+        // Override routes are matched case-insensitively
+        assert!(limiter.check_at("user:a", "post /users", now).is_ok());
+        assert!(limiter.check_at("user:a", "POST /users", now).is_ok());
+        assert_eq!(
+            limiter.check_at("user:a", "POST /users", now).unwrap_err(),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn disabled_limiter_allows_everything() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            enabled: false,
+            requests_per_second: 1.0,
+            burst: 1,
+            routes: HashMap::new(),
+        });
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(limiter.check_at("user:a", "GET /buckets", now).is_ok());
+        }
+    }
+
+    #[test]
+    fn idle_buckets_are_pruned() {
+        let limiter = limiter(1.0, 1, &[]);
+        let now = Instant::now();
+        for client in 0..=MAX_BUCKETS {
+            limiter.check_at(&format!("ip:{}", client), "GET /buckets", now).unwrap();
+        }
+        let recent = now + IDLE_BUCKET_TTL - Duration::from_secs(1);
+        limiter.check_at("user:recent", "GET /buckets", recent).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_BUCKETS + 2);
+
+        limiter.check_at("user:a", "GET /buckets", now + IDLE_BUCKET_TTL).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert!(buckets.contains_key(&("user:recent".to_string(), "*".to_string())));
+    }
+}
@@ -0,0 +1,623 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    config::{RotationConfig, SecretSinkConfig},
+    error::{AppError, Result},
+    models::{RotationPolicy, RotationResult},
+    passwords::PasswordPolicyEnforcer,
+    services::CouchbaseService,
+    storage,
+};
+
+/// Rotates service account passwords, delivers them to configured secret sinks and
+/// runs per-user scheduled rotation policies persisted on local disk.
+pub struct CredentialRotator {
+    sinks: HashMap<String, SecretSinkConfig>,
+    client: reqwest::Client,
+    policy_store_path: PathBuf,
+    policies: Mutex<Vec<RotationPolicy>>,
+}
+
+impl CredentialRotator {
+    pub async fn open(config: &RotationConfig) -> Result<Self> {
+        let policy_store_path = PathBuf::from(&config.policy_store_path);
+        let policies = storage::read_json_or_default(&policy_store_path).await?;
+
+        Ok(Self {
+            sinks: config.sinks.clone(),
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(config.sink_timeout_seconds))
+                .build()?,
+            policy_store_path,
+            policies: Mutex::new(policies),
+        })
+    }
+
+    pub fn ensure_sink_exists(&self, sink: Option<&str>) -> Result<()> {
+        match sink {
+            Some(name) if !self.sinks.contains_key(name) => Err(AppError::Validation(format!(
+                "Unknown secret sink '{}'. Configured sinks: {:?}",
+                name,
+                self.sinks.keys().collect::<Vec<_>>()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Generate a new password for the user, hand it to the sink and then apply it.
+    /// Without a sink the password is applied and returned in the result instead.
+    pub async fn rotate(
+        &self,
+        couchbase_service: &CouchbaseService,
+        password_policy: &PasswordPolicyEnforcer,
+        username: &str,
+        sink: Option<&str>,
+    ) -> Result<RotationResult> {
+        self.ensure_sink_exists(sink)?;
+
+        let password = password_policy.effective_policy(couchbase_service).await.generate();
+        let rotated_at = Utc::now();
+
+        match sink {
+            Some(name) => {
+                self.deliver_and_apply(couchbase_service, &self.sinks[name], username, &password, rotated_at)
+                    .await?
+            }
+            None => couchbase_service.set_user_password(username, &password).await?,
+        }
+        info!("Rotated password for user '{}'", username);
+
+        self.record_rotation(username, rotated_at).await;
+
+        Ok(RotationResult {
+            username: username.to_string(),
+            rotated_at,
+            sink: sink.map(str::to_string),
+            delivered: sink.is_some(),
+            password: if sink.is_some() { None } else { Some(password) },
+        })
+    }
+
+    pub async fn list_policies(&self) -> Vec<RotationPolicy> {
+        self.policies.lock().await.clone()
+    }
+
+    pub async fn set_policy(
+        &self,
+        username: &str,
+        interval: Duration,
+        sink: Option<String>,
+    ) -> Result<RotationPolicy> {
+        // Nobody receives a scheduled rotation's password unless a sink does
+        let Some(sink) = sink else {
+            return Err(AppError::Validation(
+                "Scheduled rotation requires a secret sink to deliver the new password to".to_string(),
+            ));
+        };
+        self.ensure_sink_exists(Some(&sink))?;
+
+        let mut policies = self.policies.lock().await;
+        let last_rotated_at = policies
+            .iter()
+            .find(|p| p.username == username)
+            .and_then(|p| p.last_rotated_at);
+
+        let policy = RotationPolicy {
+            username: username.to_string(),
+            interval_seconds: interval.num_seconds(),
+            sink: Some(sink),
+            last_rotated_at,
+            next_rotation_at: last_rotated_at.unwrap_or_else(Utc::now) + interval,
+        };
+
+        policies.retain(|p| p.username != username);
+        policies.push(policy.clone());
+        storage::write_json_atomic(&self.policy_store_path, &*policies).await?;
+
+        Ok(policy)
+    }
+
+    pub async fn remove_policy(&self, username: &str) -> Result<RotationPolicy> {
+        let mut policies = self.policies.lock().await;
+        let index = policies
+            .iter()
+            .position(|p| p.username == username)
+            .ok_or_else(|| {
+                AppError::NotFound(format!("No rotation policy for user '{}'", username))
+            })?;
+
+        let policy = policies.remove(index);
+        storage::write_json_atomic(&self.policy_store_path, &*policies).await?;
+
+        Ok(policy)
+    }
+
+    /// Rotate every user whose policy is due
+    pub async fn rotate_due(
+        &self,
+        couchbase_service: &CouchbaseService,
+        password_policy: &PasswordPolicyEnforcer,
+    ) {
+        let now = Utc::now();
+        let due: Vec<RotationPolicy> = self
+            .policies
+            .lock()
+            .await
+            .iter()
+            .filter(|p| p.next_rotation_at <= now)
+            .cloned()
+            .collect();
+
+        for policy in due {
+            let Some(sink) = policy.sink.as_deref().and_then(|name| self.sinks.get(name)) else {
+                warn!(
+                    "Skipping scheduled rotation for user '{}': secret sink {:?} is not configured",
+                    policy.username, policy.sink
+                );
+                continue;
+            };
+
+            if let Err(e) = self
+                .rotate_scheduled(couchbase_service, password_policy, &policy.username, sink)
+                .await
+            {
+                warn!(
+                    "Scheduled rotation for user '{}' failed and will be retried: {}",
+                    policy.username, e
+                );
+            }
+        }
+    }
+
+    async fn rotate_scheduled(
+        &self,
+        couchbase_service: &CouchbaseService,
+        password_policy: &PasswordPolicyEnforcer,
+        username: &str,
+        sink: &SecretSinkConfig,
+    ) -> Result<()> {
+        let password = password_policy.effective_policy(couchbase_service).await.generate();
+        let rotated_at = Utc::now();
+
+        self.deliver_and_apply(couchbase_service, sink, username, &password, rotated_at)
+            .await?;
+        info!("Rotated password for user '{}' on schedule", username);
+
+        self.record_rotation(username, rotated_at).await;
+        Ok(())
+    }
+
+    // The password is delivered before it is applied, so a sink that can't be reached
+    // leaves the current password in place. If applying fails after delivery, the sink
+    // holds a password that was never set; the next rotation overwrites it.
+    async fn deliver_and_apply(
+        &self,
+        couchbase_service: &CouchbaseService,
+        sink: &SecretSinkConfig,
+        username: &str,
+        password: &str,
+        rotated_at: chrono::DateTime<Utc>,
+    ) -> Result<()> {
+        self.deliver(sink, username, password, rotated_at).await?;
+
+        if let Err(e) = couchbase_service.set_user_password(username, password).await {
+            warn!(
+                "Delivered a new password for user '{}' but could not apply it: {}",
+                username, e
+            );
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub async fn run_scheduler(
+        self: Arc<Self>,
+        couchbase_service: CouchbaseService,
+        password_policy: Arc<PasswordPolicyEnforcer>,
+        interval: std::time::Duration,
+    ) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.rotate_due(&couchbase_service, &password_policy).await;
+        }
+    }
+
+    // Move the user's policy, if any, to its next rotation time
+    async fn record_rotation(&self, username: &str, rotated_at: chrono::DateTime<Utc>) {
+        let mut policies = self.policies.lock().await;
+        let Some(policy) = policies.iter_mut().find(|p| p.username == username) else {
+            return;
+        };
+
+        policy.last_rotated_at = Some(rotated_at);
+        policy.next_rotation_at = rotated_at + Duration::seconds(policy.interval_seconds);
+
+        if let Err(e) = storage::write_json_atomic(&self.policy_store_path, &*policies).await {
+            warn!("Failed to persist rotation policies: {}", e);
+        }
+    }
+
+    async fn deliver(
+        &self,
+        sink: &SecretSinkConfig,
+        username: &str,
+        password: &str,
+        rotated_at: chrono::DateTime<Utc>,
+    ) -> Result<()> {
+        let payload = json!({
+            "username": username,
+            "password": password,
+            "rotated_at": rotated_at.to_rfc3339(),
+        });
+
+        match sink {
+            SecretSinkConfig::File { path } => {
+                let path = PathBuf::from(path.replace("{username}", username));
+                write_secret_file(&path, &serde_json::to_vec_pretty(&payload)?).await
+            }
+            SecretSinkConfig::KubernetesManifest { path, namespace, name } => {
+                let name = name
+                    .as_deref()
+                    .unwrap_or("{username}-couchbase")
+                    .replace("{username}", username);
+                let manifest = json!({
+                    "apiVersion": "v1",
+                    "kind": "Secret",
+                    "type": "Opaque",
+                    "metadata": {
+                        "name": name,
+                        "namespace": namespace,
+                        "annotations": {
+                            "couchbase-admin-service/rotated-at": rotated_at.to_rfc3339(),
+                        },
+                    },
+                    "stringData": {
+                        "username": username,
+                        "password": password,
+                    },
+                });
+                let yaml = serde_yaml::to_string(&manifest)
+                    .map_err(|e| AppError::Internal(e.to_string()))?;
+                let path = PathBuf::from(path.replace("{username}", username));
+                write_secret_file(&path, yaml.as_bytes()).await
+            }
+            SecretSinkConfig::Webhook { url, bearer_token } => {
+                let mut request = self.client.post(url).json(&payload);
+                if let Some(token) = bearer_token {
+                    request = request.bearer_auth(token);
+                }
+
+                let response = request.send().await?;
+                if !response.status().is_success() {
+                    return Err(AppError::Internal(format!(
+                        "Secret webhook responded with status {}",
+                        response.status()
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Secret files are only readable by the service's own user
+async fn write_secret_file(path: &Path, contents: &[u8]) -> Result<()> {
+    storage::ensure_parent_dir(path).await?;
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path).await?;
+    tokio::io::AsyncWriteExt::write_all(&mut file, contents).await?;
+
+    // The mode above only applies when the file is created
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PasswordPolicyConfig;
+    use crate::services::testing::{fake_cluster, puts, url, Fixture, Method};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rotation-test-{}-{}", uuid::Uuid::new_v4(), name))
+    }
+
+    fn config(sinks: Vec<(&str, SecretSinkConfig)>) -> RotationConfig {
+        RotationConfig {
+            policy_store_path: temp_path("policies.json").to_string_lossy().into_owned(),
+            check_interval_seconds: 60,
+            sink_timeout_seconds: 5,
+            sinks: sinks.into_iter().map(|(name, sink)| (name.to_string(), sink)).collect(),
+        }
+    }
+
+    fn file_sink(path: &Path) -> SecretSinkConfig {
+        SecretSinkConfig::File {
+            path: path.join("{username}.json").to_string_lossy().into_owned(),
+        }
+    }
+
+    fn password_policy() -> PasswordPolicyEnforcer {
+        PasswordPolicyEnforcer::from_config(&PasswordPolicyConfig {
+            min_length: 16,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digits: true,
+            require_special_chars: false,
+            denylist: vec![],
+            denylist_file: None,
+            sync_with_cluster: false,
+        })
+        .unwrap()
+    }
+
+    fn user(path: &'static str, id: &str) -> Fixture {
+        (Method::GET, path, 200, json!({"id": id, "domain": "local", "roles": []}))
+    }
+
+    fn policy(username: &str, sink: &str, next_rotation_in_seconds: i64) -> RotationPolicy {
+        RotationPolicy {
+            username: username.to_string(),
+            interval_seconds: 3600,
+            sink: Some(sink.to_string()),
+            last_rotated_at: None,
+            next_rotation_at: Utc::now() + Duration::seconds(next_rotation_in_seconds),
+        }
+    }
+
+    fn applied_password(request: &crate::services::testing::Recorded) -> String {
+        request.form()["password"].clone()
+    }
+
+    #[tokio::test]
+    async fn policies_are_stored_and_reloaded() {
+        let dir = temp_path("secrets");
+        let config = config(vec![("local", file_sink(&dir))]);
+        let rotator = CredentialRotator::open(&config).await.unwrap();
+
+        assert!(matches!(
+            rotator.set_policy("alice", Duration::days(30), None).await,
+            Err(AppError::Validation(_))
+        ));
+        assert!(matches!(
+            rotator.set_policy("alice", Duration::days(30), Some("vault".to_string())).await,
+            Err(AppError::Validation(_))
+        ));
+
+        let before = Utc::now();
+        let policy = rotator
+            .set_policy("alice", Duration::days(30), Some("local".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(policy.interval_seconds, 30 * 86400);
+        assert!(policy.next_rotation_at >= before + Duration::days(30));
+        assert!(policy.next_rotation_at <= Utc::now() + Duration::days(30));
+
+        // Replacing a policy keeps a single entry per user
+        rotator
+            .set_policy("alice", Duration::days(7), Some("local".to_string()))
+            .await
+            .unwrap();
+        let reopened = CredentialRotator::open(&config).await.unwrap();
+        let policies = reopened.list_policies().await;
+        assert_eq!(policies.len(), 1);
+        assert_eq!(policies[0].interval_seconds, 7 * 86400);
+
+        reopened.remove_policy("alice").await.unwrap();
+        assert!(matches!(reopened.remove_policy("alice").await, Err(AppError::NotFound(_))));
+        assert!(CredentialRotator::open(&config).await.unwrap().list_policies().await.is_empty());
+
+        let _ = std::fs::remove_file(&config.policy_store_path);
+    }
+
+    #[tokio::test]
+    async fn only_due_policies_are_rotated() {
+        let dir = temp_path("secrets");
+        let config = config(vec![("local", file_sink(&dir))]);
+        storage::write_json_atomic(
+            Path::new(&config.policy_store_path),
+            &vec![
+                policy("alice", "local", -60),
+                policy("bob", "local", 3600),
+                policy("carol", "removed", -60),
+            ],
+        )
+        .await
+        .unwrap();
+        let rotator = CredentialRotator::open(&config).await.unwrap();
+        let (service, requests) = fake_cluster(vec![
+            user("/settings/rbac/users/local/alice", "alice"),
+            user("/settings/rbac/users/local/bob", "bob"),
+            user("/settings/rbac/users/local/carol", "carol"),
+        ])
+        .await;
+
+        rotator.rotate_due(&service, &password_policy()).await;
+
+        let puts = puts(&requests);
+        assert_eq!(puts.len(), 1);
+        assert_eq!(puts[0].path, "/settings/rbac/users/local/alice");
+
+        let delivered: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join("alice.json")).unwrap()).unwrap();
+        assert_eq!(delivered["username"], "alice");
+        assert_eq!(delivered["password"], applied_password(&puts[0]).as_str());
+        assert!(!dir.join("bob.json").exists());
+
+        let policies = rotator.list_policies().await;
+        let alice = policies.iter().find(|p| p.username == "alice").unwrap();
+        let rotated_at = alice.last_rotated_at.unwrap();
+        assert_eq!(alice.next_rotation_at, rotated_at + Duration::seconds(3600));
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_file(&config.policy_store_path);
+    }
+
+    #[tokio::test]
+    async fn undeliverable_passwords_are_not_applied() {
+        let (service, requests) = fake_cluster(vec![
+            user("/settings/rbac/users/local/alice", "alice"),
+            (Method::POST, "/hooks/rotate", 503, json!({})),
+        ])
+        .await;
+        let config = config(vec![(
+            "hook",
+            SecretSinkConfig::Webhook {
+                url: url(&service, "/hooks/rotate"),
+                bearer_token: None,
+            },
+        )]);
+        storage::write_json_atomic(Path::new(&config.policy_store_path), &vec![policy("alice", "hook", -60)])
+            .await
+            .unwrap();
+        let rotator = CredentialRotator::open(&config).await.unwrap();
+
+        assert!(rotator
+            .rotate(&service, &password_policy(), "alice", Some("hook"))
+            .await
+            .is_err());
+        rotator.rotate_due(&service, &password_policy()).await;
+
+        assert!(puts(&requests).is_empty());
+        // Still due, so the next check retries it
+        let policies = rotator.list_policies().await;
+        assert!(policies[0].last_rotated_at.is_none());
+        assert!(policies[0].next_rotation_at <= Utc::now());
+
+        let _ = std::fs::remove_file(&config.policy_store_path);
+    }
+
+    #[tokio::test]
+    async fn rotations_without_a_sink_return_the_password() {
+        let (service, requests) = fake_cluster(vec![user("/settings/rbac/users/local/alice", "alice")]).await;
+        let config = config(vec![]);
+        let rotator = CredentialRotator::open(&config).await.unwrap();
+
+        let result = rotator
+            .rotate(&service, &password_policy(), "alice", None)
+            .await
+            .unwrap();
+
+        assert!(!result.delivered);
+        assert_eq!(result.password.unwrap(), applied_password(&puts(&requests)[0]));
+        assert!(matches!(
+            rotator.rotate(&service, &password_policy(), "alice", Some("vault")).await,
+            Err(AppError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn file_sink_writes_private_json() {
+        let dir = temp_path("secrets");
+        let (service, requests) = fake_cluster(vec![user("/settings/rbac/users/local/alice", "alice")]).await;
+        let config = config(vec![("local", file_sink(&dir))]);
+        let rotator = CredentialRotator::open(&config).await.unwrap();
+
+        // An existing, world-readable file is tightened on overwrite
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("alice.json"), "{}").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(dir.join("alice.json"), std::fs::Permissions::from_mode(0o644))
+                .unwrap();
+        }
+
+        let result = rotator
+            .rotate(&service, &password_policy(), "alice", Some("local"))
+            .await
+            .unwrap();
+        assert!(result.delivered);
+        assert!(result.password.is_none());
+
+        let delivered: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join("alice.json")).unwrap()).unwrap();
+        assert_eq!(delivered["password"], applied_password(&puts(&requests)[0]).as_str());
+        assert_eq!(delivered["rotated_at"], result.rotated_at.to_rfc3339().as_str());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join("alice.json")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn manifest_sink_writes_a_secret() {
+        let dir = temp_path("manifests");
+        let (service, requests) = fake_cluster(vec![user("/settings/rbac/users/local/alice", "alice")]).await;
+        let config = config(vec![(
+            "k8s",
+            SecretSinkConfig::KubernetesManifest {
+                path: dir.join("{username}.yaml").to_string_lossy().into_owned(),
+                namespace: "apps".to_string(),
+                name: None,
+            },
+        )]);
+        let rotator = CredentialRotator::open(&config).await.unwrap();
+
+        rotator
+            .rotate(&service, &password_policy(), "alice", Some("k8s"))
+            .await
+            .unwrap();
+
+        let manifest: serde_json::Value =
+            serde_yaml::from_slice(&std::fs::read(dir.join("alice.yaml")).unwrap()).unwrap();
+        assert_eq!(manifest["kind"], "Secret");
+        assert_eq!(manifest["metadata"]["name"], "alice-couchbase");
+        assert_eq!(manifest["metadata"]["namespace"], "apps");
+        assert_eq!(manifest["stringData"]["username"], "alice");
+        assert_eq!(
+            manifest["stringData"]["password"],
+            applied_password(&puts(&requests)[0]).as_str()
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn webhook_sink_posts_the_password() {
+        let (service, requests) = fake_cluster(vec![user("/settings/rbac/users/local/alice", "alice")]).await;
+        let config = config(vec![(
+            "hook",
+            SecretSinkConfig::Webhook {
+                url: url(&service, "/hooks/rotate"),
+                bearer_token: Some("token".to_string()),
+            },
+        )]);
+        let rotator = CredentialRotator::open(&config).await.unwrap();
+
+        rotator
+            .rotate(&service, &password_policy(), "alice", Some("hook"))
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap().clone();
+        let hook = requests.iter().position(|r| r.path == "/hooks/rotate").unwrap();
+        let apply = requests.iter().position(|r| r.method == Method::PUT).unwrap();
+        // Delivered before it is applied
+        assert!(hook < apply);
+
+        let body: serde_json::Value = serde_json::from_str(&requests[hook].body).unwrap();
+        assert_eq!(body["username"], "alice");
+        assert_eq!(body["password"], applied_password(&requests[apply]).as_str());
+    }
+}
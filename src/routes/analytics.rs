@@ -0,0 +1,182 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+
+use crate::{
+    error::{AppError, Result},
+    models::{
+        AnalyticsCollectionInfo, AnalyticsIngestionStatus, AnalyticsLinkInfo, AnalyticsScopeInfo, ApiResponse,
+        CreateAnalyticsCollectionRequest, CreateAnalyticsLinkRequest, CreateAnalyticsScopeRequest,
+        LOCAL_ANALYTICS_LINK,
+    },
+    routes::missing_collection,
+    services::CouchbaseService,
+    validation::{self, FieldError},
+};
+
+// Scopes
+pub async fn create_analytics_scope(
+    State(couchbase_service): State<CouchbaseService>,
+    Json(payload): Json<CreateAnalyticsScopeRequest>,
+) -> Result<Json<ApiResponse<AnalyticsScopeInfo>>> {
+    if let Err(errors) = validation::validate_create_analytics_scope(&payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    couchbase_service.create_analytics_scope(&payload.scope_name).await?;
+    Ok(Json(ApiResponse::success(AnalyticsScopeInfo {
+        name: payload.scope_name,
+    })))
+}
+
+pub async fn list_analytics_scopes(
+    State(couchbase_service): State<CouchbaseService>,
+) -> Result<Json<ApiResponse<Vec<AnalyticsScopeInfo>>>> {
+    let mut scopes = couchbase_service.list_analytics_scopes().await?;
+    scopes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Json(ApiResponse::success(scopes)))
+}
+
+pub async fn drop_analytics_scope(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(scope): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.drop_analytics_scope(&scope).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+// Collections
+pub async fn create_analytics_collection(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(scope): Path<String>,
+    Json(payload): Json<CreateAnalyticsCollectionRequest>,
+) -> Result<Json<ApiResponse<AnalyticsCollectionInfo>>> {
+    if let Err(errors) = validation::validate_create_analytics_collection(&payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    // Sources behind a remote link are in another cluster and can't be checked here
+    if payload.link_name == LOCAL_ANALYTICS_LINK {
+        let source = &payload.source;
+        if let Some(message) =
+            missing_collection(&couchbase_service, &source.bucket, &source.scope, &source.collection).await?
+        {
+            return Ok(Json(ApiResponse::validation_failed(vec![FieldError {
+                field: "source".to_string(),
+                message,
+            }])));
+        }
+    }
+
+    couchbase_service.create_analytics_collection(&scope, &payload).await?;
+    let collection = couchbase_service
+        .list_analytics_collections(&scope)
+        .await?
+        .into_iter()
+        .find(|collection| collection.name == payload.collection_name)
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Analytics collection '{}' not found in scope '{}'",
+                payload.collection_name, scope
+            ))
+        })?;
+
+    Ok(Json(ApiResponse::success(collection)))
+}
+
+pub async fn list_analytics_collections(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(scope): Path<String>,
+) -> Result<Json<ApiResponse<Vec<AnalyticsCollectionInfo>>>> {
+    let mut collections = couchbase_service.list_analytics_collections(&scope).await?;
+    collections.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Json(ApiResponse::success(collections)))
+}
+
+pub async fn drop_analytics_collection(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((scope, collection)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.drop_analytics_collection(&scope, &collection).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+// Links
+pub async fn create_analytics_link(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(scope): Path<String>,
+    Json(payload): Json<CreateAnalyticsLinkRequest>,
+) -> Result<Json<ApiResponse<AnalyticsLinkInfo>>> {
+    if let Err(errors) = validation::validate_create_analytics_link(&payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    couchbase_service.create_analytics_link(&scope, &payload).await?;
+    Ok(Json(ApiResponse::success(AnalyticsLinkInfo {
+        name: payload.link_name,
+        scope,
+        settings: payload.settings.redacted(),
+    })))
+}
+
+pub async fn list_analytics_links(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(scope): Path<String>,
+) -> Result<Json<ApiResponse<Vec<AnalyticsLinkInfo>>>> {
+    let links = couchbase_service.list_analytics_links(&scope).await?;
+    Ok(Json(ApiResponse::success(links)))
+}
+
+pub async fn get_analytics_link(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((scope, link)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<AnalyticsLinkInfo>>> {
+    let link_info = couchbase_service
+        .list_analytics_links(&scope)
+        .await?
+        .into_iter()
+        .find(|link_info| link_info.name == link)
+        .ok_or_else(|| AppError::NotFound(format!("Link '{}' not found in analytics scope '{}'", link, scope)))?;
+    Ok(Json(ApiResponse::success(link_info)))
+}
+
+pub async fn delete_analytics_link(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((scope, link)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    if link == LOCAL_ANALYTICS_LINK {
+        return Ok(Json(ApiResponse::error(format!(
+            "The '{}' link cannot be deleted",
+            LOCAL_ANALYTICS_LINK
+        ))));
+    }
+
+    couchbase_service.delete_analytics_link(&scope, &link).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+pub async fn connect_analytics_link(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((scope, link)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.set_analytics_link_connected(&scope, &link, true).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+pub async fn disconnect_analytics_link(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((scope, link)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.set_analytics_link_connected(&scope, &link, false).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+// Ingestion
+pub async fn analytics_ingestion(
+    State(couchbase_service): State<CouchbaseService>,
+) -> Result<Json<ApiResponse<Vec<AnalyticsIngestionStatus>>>> {
+    let mut statuses = couchbase_service.analytics_ingestion().await?;
+    statuses.sort_by(|a, b| (&a.scope, &a.collection).cmp(&(&b.scope, &b.collection)));
+    Ok(Json(ApiResponse::success(statuses)))
+}
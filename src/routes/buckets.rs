@@ -1,23 +1,24 @@
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     response::Json,
 };
 
 use crate::{
     error::Result,
-    models::{ApiResponse, BucketInfo, CreateBucketRequest, CouchbaseBucketConfig},
+    models::{
+        ApiResponse, BucketInfo, BucketSortField, BucketType, CreateBucketRequest,
+        CouchbaseBucketConfig, ListBucketsQuery, PagedResponse, StorageBackend, UpdateBucketRequest,
+    },
     services::CouchbaseService,
+    validation,
 };
 
 pub async fn create_bucket(
     State(couchbase_service): State<CouchbaseService>,
     Json(payload): Json<CreateBucketRequest>,
 ) -> Result<Json<ApiResponse<BucketInfo>>> {
-    // Validate bucket name
-    if payload.bucket_name.is_empty() {
-        return Ok(Json(ApiResponse::error(
-            "Bucket name cannot be empty".to_string(),
-        )));
+    if let Err(errors) = validation::validate_create_bucket(&payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
     }
 
     // Check if bucket already exists
@@ -29,28 +30,63 @@ pub async fn create_bucket(
         ))));
     }
 
-    // Create bucket configuration
+    // Create bucket configuration; memcached buckets take none of the persistence settings
+    let bucket_type = payload.bucket_type;
+    let memcached = bucket_type == BucketType::Memcached;
     let bucket_config = CouchbaseBucketConfig {
         name: payload.bucket_name.clone(),
-        ram_quota_mb: payload.ram_quota_mb.unwrap_or(100),
-        replica_number: payload.replica_number.unwrap_or(1),
-        eviction_policy: payload.eviction_policy.unwrap_or_else(|| "valueOnly".to_string()),
-        compression_mode: payload.compression_mode.unwrap_or_else(|| "passive".to_string()),
-        conflict_resolution_type: payload.conflict_resolution_type
-            .unwrap_or_else(|| "seqno".to_string()),
+        bucket_type,
+        ram_quota_mb: payload.ram_quota_mb.unwrap_or_else(|| {
+            validation::min_ram_quota_mb(payload.storage_backend, payload.num_vbuckets)
+        }),
+        replica_number: payload.replica_number.or((!memcached).then_some(1)),
+        eviction_policy: payload
+            .eviction_policy
+            .or_else(|| bucket_type.default_eviction_policy().map(str::to_string)),
+        compression_mode: payload
+            .compression_mode
+            .or_else(|| (!memcached).then(|| "passive".to_string())),
+        conflict_resolution_type: payload
+            .conflict_resolution_type
+            .or_else(|| (!memcached).then(|| "seqno".to_string())),
+        storage_backend: payload
+            .storage_backend
+            .or((bucket_type == BucketType::Couchbase).then_some(StorageBackend::Couchstore)),
+        max_ttl: payload.max_ttl,
+        flush_enabled: payload.flush_enabled.unwrap_or(false),
+        durability_min_level: payload.durability_min_level,
+        replica_index: payload.replica_index,
+        history_retention_collection_default: payload.history_retention_collection_default,
+        history_retention_bytes: payload.history_retention_bytes,
+        history_retention_seconds: payload.history_retention_seconds,
+        num_vbuckets: payload.num_vbuckets,
     };
 
+    couchbase_service
+        .ensure_ram_available(&bucket_config.name, bucket_config.ram_quota_mb)
+        .await?;
+
     // Create the bucket
     couchbase_service.create_bucket(&bucket_config).await?;
 
     // Return the created bucket info
     let bucket_info = BucketInfo {
         name: bucket_config.name,
+        bucket_type,
         ram_quota_mb: bucket_config.ram_quota_mb,
-        replica_number: bucket_config.replica_number,
-        eviction_policy: bucket_config.eviction_policy,
-        compression_mode: bucket_config.compression_mode,
-        conflict_resolution_type: bucket_config.conflict_resolution_type,
+        replica_number: bucket_config.replica_number.unwrap_or(0),
+        eviction_policy: bucket_config.eviction_policy.unwrap_or_default(),
+        compression_mode: bucket_config.compression_mode.unwrap_or_default(),
+        conflict_resolution_type: bucket_config.conflict_resolution_type.unwrap_or_default(),
+        storage_backend: bucket_config.storage_backend,
+        max_ttl: bucket_config.max_ttl.unwrap_or(0),
+        flush_enabled: bucket_config.flush_enabled,
+        durability_min_level: bucket_config.durability_min_level,
+        replica_index: bucket_config.replica_index,
+        history_retention_collection_default: bucket_config.history_retention_collection_default,
+        history_retention_bytes: bucket_config.history_retention_bytes,
+        history_retention_seconds: bucket_config.history_retention_seconds,
+        num_vbuckets: bucket_config.num_vbuckets,
         status: "healthy".to_string(),
     };
 
@@ -59,7 +95,101 @@ pub async fn create_bucket(
 
 pub async fn list_buckets(
     State(couchbase_service): State<CouchbaseService>,
-) -> Result<Json<ApiResponse<Vec<BucketInfo>>>> {
+    Query(query): Query<ListBucketsQuery>,
+) -> Result<Json<PagedResponse<BucketInfo>>> {
+    let mut buckets: Vec<BucketInfo> = couchbase_service
+        .list_buckets()
+        .await?
+        .into_iter()
+        .filter(|b| query.prefix.as_deref().is_none_or(|p| b.name.starts_with(p)))
+        .filter(|b| query.status.as_deref().is_none_or(|s| b.status == s))
+        .collect();
+
+    buckets.sort_by(|a, b| {
+        let ordering = match query.sort_by {
+            BucketSortField::Name => a.name.cmp(&b.name),
+            BucketSortField::RamQuotaMb => a.ram_quota_mb.cmp(&b.ram_quota_mb),
+            BucketSortField::Status => a.status.cmp(&b.status),
+        };
+        query.order.apply(ordering.then_with(|| a.name.cmp(&b.name)))
+    });
+
+    Ok(Json(PagedResponse::from_items(buckets, query.page, query.page_size)))
+}
+
+pub async fn update_bucket(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(bucket): Path<String>,
+    Json(payload): Json<UpdateBucketRequest>,
+) -> Result<Json<ApiResponse<BucketInfo>>> {
+    if payload.is_empty() {
+        return Ok(Json(ApiResponse::error(
+            "No bucket settings to update".to_string(),
+        )));
+    }
+
     let buckets = couchbase_service.list_buckets().await?;
-    Ok(Json(ApiResponse::success(buckets)))
+    let Some(existing) = buckets.into_iter().find(|b| b.name == bucket) else {
+        return Ok(Json(ApiResponse::error(format!(
+            "Bucket '{}' not found",
+            bucket
+        ))));
+    };
+
+    if let Err(errors) = validation::validate_update_bucket(&payload, &existing) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    // Only growing a bucket can run out of capacity
+    if let Some(ram_quota_mb) = payload.ram_quota_mb.filter(|&mb| mb > existing.ram_quota_mb) {
+        couchbase_service.ensure_ram_available(&bucket, ram_quota_mb).await?;
+    }
+
+    couchbase_service.update_bucket(&bucket, &payload).await?;
+
+    let bucket_info = BucketInfo {
+        ram_quota_mb: payload.ram_quota_mb.unwrap_or(existing.ram_quota_mb),
+        replica_number: payload.replica_number.unwrap_or(existing.replica_number),
+        eviction_policy: payload.eviction_policy.unwrap_or(existing.eviction_policy),
+        compression_mode: payload.compression_mode.unwrap_or(existing.compression_mode),
+        max_ttl: payload.max_ttl.unwrap_or(existing.max_ttl),
+        flush_enabled: payload.flush_enabled.unwrap_or(existing.flush_enabled),
+        durability_min_level: payload.durability_min_level.or(existing.durability_min_level),
+        ..existing
+    };
+
+    Ok(Json(ApiResponse::success(bucket_info)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::testing::{fake_cluster, Method};
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    #[tokio::test]
+    async fn buckets_that_do_not_fit_are_refused_with_conflict() {
+        let (service, requests) = fake_cluster(vec![
+            (
+                Method::GET,
+                "/pools/default",
+                200,
+                serde_json::json!({"memoryQuota": 2048, "nodes": [{"memoryTotal": 8u64 << 30}]}),
+            ),
+            (
+                Method::GET,
+                "/pools/default/buckets",
+                200,
+                serde_json::json!([{"name": "travel", "bucketType": "membase", "quota": {"rawRAM": 1536u64 << 20}}]),
+            ),
+        ])
+        .await;
+        let request: CreateBucketRequest =
+            serde_json::from_value(serde_json::json!({"bucket_name": "orders", "ram_quota_mb": 1024})).unwrap();
+
+        let error = create_bucket(State(service), Json(request)).await.err().unwrap();
+
+        assert_eq!(error.into_response().status(), StatusCode::CONFLICT);
+        assert!(requests.lock().unwrap().iter().all(|r| r.method == Method::GET));
+    }
 }
@@ -0,0 +1,14 @@
+use axum::{extract::State, response::Json};
+
+use crate::{
+    error::Result,
+    models::{ApiResponse, ClusterCapacity},
+    services::CouchbaseService,
+};
+
+pub async fn get_capacity(
+    State(couchbase_service): State<CouchbaseService>,
+) -> Result<Json<ApiResponse<ClusterCapacity>>> {
+    let capacity = couchbase_service.get_capacity().await?;
+    Ok(Json(ApiResponse::success(capacity)))
+}
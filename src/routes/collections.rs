@@ -1,55 +1,61 @@
 use axum::{
     extract::{Path, State},
-    response::Json,
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
 };
 
 use crate::{
+    conditional::{self, manifest_etag},
     error::Result,
     models::{ApiResponse, CollectionInfo, CreateCollectionRequest},
     services::CouchbaseService,
+    validation,
 };
 
 pub async fn create_collection(
     State(couchbase_service): State<CouchbaseService>,
     Path((bucket, scope)): Path<(String, String)>,
+    headers: HeaderMap,
     Json(payload): Json<CreateCollectionRequest>,
-) -> Result<Json<ApiResponse<CollectionInfo>>> {
-    // Validate collection name
-    if payload.collection_name.is_empty() {
-        return Ok(Json(ApiResponse::error(
-            "Collection name cannot be empty".to_string(),
-        )));
+) -> Result<Response> {
+    if let Err(errors) = validation::validate_create_collection(&payload) {
+        return Ok(Json(ApiResponse::<CollectionInfo>::validation_failed(errors)).into_response());
     }
 
     // Check if bucket exists
     let buckets = couchbase_service.list_buckets().await?;
     if !buckets.iter().any(|b| b.name == bucket) {
-        return Ok(Json(ApiResponse::error(format!(
+        return Ok(Json(ApiResponse::<CollectionInfo>::error(format!(
             "Bucket '{}' not found",
             bucket
-        ))));
+        )))
+        .into_response());
     }
 
+    conditional::check_manifest_if_match(&headers, &couchbase_service, &bucket).await?;
+
     // Check if scope exists
     let scopes = couchbase_service.list_scopes(&bucket).await?;
     if !scopes.iter().any(|s| s.name == scope) {
-        return Ok(Json(ApiResponse::error(format!(
+        return Ok(Json(ApiResponse::<CollectionInfo>::error(format!(
             "Scope '{}' not found in bucket '{}'",
             scope, bucket
-        ))));
+        )))
+        .into_response());
     }
 
     // Check if collection already exists
     let existing_collections = couchbase_service.list_collections(&bucket, &scope).await?;
     if existing_collections.iter().any(|c| c.name == payload.collection_name) {
-        return Ok(Json(ApiResponse::error(format!(
+        return Ok(Json(ApiResponse::<CollectionInfo>::error(format!(
             "Collection '{}' already exists in scope '{}' of bucket '{}'",
             payload.collection_name, scope, bucket
-        ))));
+        )))
+        .into_response());
     }
 
     // Create the collection
-    couchbase_service
+    let uid = couchbase_service
         .create_collection(
             &bucket,
             &scope,
@@ -59,6 +65,10 @@ pub async fn create_collection(
         )
         .await?;
 
+    couchbase_service
+        .provision_indexes(&bucket, &scope, &payload.collection_name, &payload.indexes)
+        .await?;
+
     // Return the created collection info
     let collection_info = CollectionInfo {
         name: payload.collection_name,
@@ -67,31 +77,61 @@ pub async fn create_collection(
         scope: scope.clone(),
     };
 
-    Ok(Json(ApiResponse::success(collection_info)))
+    Ok(conditional::with_etag(
+        Json(ApiResponse::success(collection_info)).into_response(),
+        &manifest_etag(&uid),
+    ))
 }
 
 pub async fn list_collections(
     State(couchbase_service): State<CouchbaseService>,
     Path((bucket, scope)): Path<(String, String)>,
-) -> Result<Json<ApiResponse<Vec<CollectionInfo>>>> {
+    headers: HeaderMap,
+) -> Result<Response> {
     // Check if bucket exists
     let buckets = couchbase_service.list_buckets().await?;
     if !buckets.iter().any(|b| b.name == bucket) {
-        return Ok(Json(ApiResponse::error(format!(
+        return Ok(Json(ApiResponse::<Vec<CollectionInfo>>::error(format!(
             "Bucket '{}' not found",
             bucket
-        ))));
+        )))
+        .into_response());
+    }
+
+    let manifest = couchbase_service.get_collection_manifest(&bucket).await?;
+    let etag = manifest_etag(&manifest.uid);
+    if conditional::is_not_modified(&headers, &etag) {
+        return Ok(conditional::not_modified(&etag));
     }
 
     // Check if scope exists
-    let scopes = couchbase_service.list_scopes(&bucket).await?;
-    if !scopes.iter().any(|s| s.name == scope) {
-        return Ok(Json(ApiResponse::error(format!(
+    let Some(scope_info) = manifest.scopes.into_iter().find(|s| s.name == scope) else {
+        return Ok(Json(ApiResponse::<Vec<CollectionInfo>>::error(format!(
             "Scope '{}' not found in bucket '{}'",
             scope, bucket
-        ))));
-    }
+        )))
+        .into_response());
+    };
+
+    Ok(conditional::with_etag(
+        Json(ApiResponse::success(scope_info.collections)).into_response(),
+        &etag,
+    ))
+}
+
+pub async fn delete_collection(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, collection)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    conditional::check_manifest_if_match(&headers, &couchbase_service, &bucket).await?;
+
+    let uid = couchbase_service
+        .delete_collection(&bucket, &scope, &collection)
+        .await?;
 
-    let collections = couchbase_service.list_collections(&bucket, &scope).await?;
-    Ok(Json(ApiResponse::success(collections)))
+    Ok(conditional::with_etag(
+        Json(ApiResponse::success(())).into_response(),
+        &manifest_etag(&uid),
+    ))
 }
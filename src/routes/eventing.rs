@@ -0,0 +1,132 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+
+use crate::{
+    error::Result,
+    models::{ApiResponse, EventingFunctionDefinition, EventingFunctionInfo, EventingLifecycleAction},
+    routes::missing_collection,
+    services::CouchbaseService,
+    validation::{self, FieldError},
+};
+
+// Field errors for source, metadata and binding keyspaces that do not exist
+async fn missing_keyspaces(
+    couchbase_service: &CouchbaseService,
+    definition: &EventingFunctionDefinition,
+) -> Result<Vec<FieldError>> {
+    let depcfg = &definition.depcfg;
+    let mut keyspaces = vec![
+        (
+            "depcfg.source_collection".to_string(),
+            (&depcfg.source_bucket, &depcfg.source_scope, &depcfg.source_collection),
+        ),
+        (
+            "depcfg.metadata_collection".to_string(),
+            (&depcfg.metadata_bucket, &depcfg.metadata_scope, &depcfg.metadata_collection),
+        ),
+    ];
+    keyspaces.extend(depcfg.buckets.iter().enumerate().map(|(i, binding)| {
+        (
+            format!("depcfg.buckets[{}].collection_name", i),
+            (&binding.bucket_name, &binding.scope_name, &binding.collection_name),
+        )
+    }));
+
+    let mut errors = Vec::new();
+    for (field, (bucket, scope, collection)) in keyspaces {
+        if let Some(message) = missing_collection(couchbase_service, bucket, scope, collection).await? {
+            errors.push(FieldError { field, message });
+        }
+    }
+    Ok(errors)
+}
+
+/// Create a function, or replace the definition of an undeployed one, from its
+/// exported definition. The function is left undeployed.
+pub async fn import_eventing_function(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+    Json(payload): Json<EventingFunctionDefinition>,
+) -> Result<Json<ApiResponse<EventingFunctionInfo>>> {
+    if let Err(errors) = validation::validate_eventing_function(&name, &payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    let errors = missing_keyspaces(&couchbase_service, &payload).await?;
+    if !errors.is_empty() {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    couchbase_service.import_eventing_function(&name, &payload).await?;
+    let function = couchbase_service.get_eventing_function(&name).await?;
+
+    Ok(Json(ApiResponse::success(function)))
+}
+
+pub async fn list_eventing_functions(
+    State(couchbase_service): State<CouchbaseService>,
+) -> Result<Json<ApiResponse<Vec<EventingFunctionInfo>>>> {
+    let mut functions = couchbase_service.list_eventing_functions().await?;
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Json(ApiResponse::success(functions)))
+}
+
+pub async fn get_eventing_function(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<EventingFunctionInfo>>> {
+    let function = couchbase_service.get_eventing_function(&name).await?;
+    Ok(Json(ApiResponse::success(function)))
+}
+
+pub async fn delete_eventing_function(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.delete_eventing_function(&name).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+// Lifecycle changes are asynchronous; the returned status shows the transition,
+// e.g. `deploying`
+async fn change_state(
+    couchbase_service: CouchbaseService,
+    name: String,
+    action: EventingLifecycleAction,
+) -> Result<Json<ApiResponse<EventingFunctionInfo>>> {
+    couchbase_service
+        .change_eventing_function_state(&name, action)
+        .await?;
+    let function = couchbase_service.get_eventing_function(&name).await?;
+    Ok(Json(ApiResponse::success(function)))
+}
+
+pub async fn deploy_eventing_function(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<EventingFunctionInfo>>> {
+    change_state(couchbase_service, name, EventingLifecycleAction::Deploy).await
+}
+
+pub async fn undeploy_eventing_function(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<EventingFunctionInfo>>> {
+    change_state(couchbase_service, name, EventingLifecycleAction::Undeploy).await
+}
+
+pub async fn pause_eventing_function(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<EventingFunctionInfo>>> {
+    change_state(couchbase_service, name, EventingLifecycleAction::Pause).await
+}
+
+pub async fn resume_eventing_function(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<EventingFunctionInfo>>> {
+    change_state(couchbase_service, name, EventingLifecycleAction::Resume).await
+}
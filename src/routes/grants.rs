@@ -0,0 +1,100 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+    Extension,
+};
+use std::sync::Arc;
+
+use crate::{
+    error::{AppError, Result},
+    grants::{GrantStore, NewGrant},
+    middleware::UserInfo as AuthenticatedUser,
+    models::{
+        parse_duration, ApiResponse, CreateGrantRequest, DomainQuery, Grant, GrantAuditEvent,
+        ListGrantsQuery,
+    },
+    services::CouchbaseService,
+};
+
+// Temporarily grant roles to a user
+pub async fn create_grant(
+    State(couchbase_service): State<CouchbaseService>,
+    State(grant_store): State<Arc<GrantStore>>,
+    Path(username): Path<String>,
+    Query(query): Query<DomainQuery>,
+    actor: Option<Extension<AuthenticatedUser>>,
+    Json(payload): Json<CreateGrantRequest>,
+) -> Result<Json<ApiResponse<Grant>>> {
+    if payload.roles.is_empty() {
+        return Ok(Json(ApiResponse::error(
+            "At least one role must be specified".to_string(),
+        )));
+    }
+
+    let duration = match parse_duration(&payload.duration) {
+        Ok(duration) => duration,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let catalog = couchbase_service.role_catalog().await?;
+    for role in &payload.roles {
+        if let Err(validation_error) = catalog.validate_role(role) {
+            return Ok(Json(ApiResponse::error(validation_error)));
+        }
+    }
+
+    let granted_by = actor.map(|Extension(user)| user.username);
+    match grant_store
+        .grant(
+            &couchbase_service,
+            NewGrant {
+                domain: query.domain,
+                username,
+                roles: payload.roles,
+                duration,
+                reason: payload.reason,
+                granted_by,
+            },
+        )
+        .await
+    {
+        Ok(grant) => Ok(Json(ApiResponse::success(grant))),
+        Err(AppError::Validation(message)) => Ok(Json(ApiResponse::error(message))),
+        Err(e) => Err(e),
+    }
+}
+
+// Revoke a temporary grant before it expires
+pub async fn revoke_grant(
+    State(couchbase_service): State<CouchbaseService>,
+    State(grant_store): State<Arc<GrantStore>>,
+    Path((username, grant_id)): Path<(String, String)>,
+    Query(query): Query<DomainQuery>,
+    actor: Option<Extension<AuthenticatedUser>>,
+) -> Result<Json<ApiResponse<Grant>>> {
+    let actor = actor.map(|Extension(user)| user.username);
+    let grant = grant_store
+        .revoke(&couchbase_service, query.domain, &username, &grant_id, actor)
+        .await?;
+    Ok(Json(ApiResponse::success(grant)))
+}
+
+// List active temporary grants
+pub async fn list_grants(
+    State(grant_store): State<Arc<GrantStore>>,
+    Query(query): Query<ListGrantsQuery>,
+) -> Result<Json<ApiResponse<Vec<Grant>>>> {
+    let grants = grant_store.list(query.username.as_deref(), query.domain).await;
+    Ok(Json(ApiResponse::success(grants)))
+}
+
+// List the grant audit trail
+pub async fn list_grant_audit(
+    State(grant_store): State<Arc<GrantStore>>,
+    Query(query): Query<ListGrantsQuery>,
+) -> Result<Json<ApiResponse<Vec<GrantAuditEvent>>>> {
+    let events = grant_store
+        .audit_events(query.username.as_deref(), query.domain)
+        .await?;
+    Ok(Json(ApiResponse::success(events)))
+}
@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+
+use crate::{
+    error::Result,
+    models::{ApiResponse, BuildIndexesRequest, CreateIndexRequest, IndexInfo},
+    routes::missing_collection,
+    services::CouchbaseService,
+    validation,
+};
+
+pub async fn create_index(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, collection)): Path<(String, String, String)>,
+    Json(payload): Json<CreateIndexRequest>,
+) -> Result<Json<ApiResponse<IndexInfo>>> {
+    if let Err(errors) = validation::validate_create_index(&payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    if let Some(message) = missing_collection(&couchbase_service, &bucket, &scope, &collection).await? {
+        return Ok(Json(ApiResponse::error(message)));
+    }
+
+    let definition = couchbase_service
+        .create_index(&bucket, &scope, &collection, &payload)
+        .await?;
+
+    // Deferred indexes wait for a build; others start building right away
+    let index_info = IndexInfo {
+        name: payload.name().to_string(),
+        bucket,
+        scope,
+        collection,
+        primary: payload.primary,
+        definition,
+        status: if payload.deferred { "Created" } else { "Building" }.to_string(),
+        progress: 0,
+        num_replica: payload.num_replica.unwrap_or(0),
+        num_partition: payload.num_partition,
+        hosts: vec![],
+    };
+
+    Ok(Json(ApiResponse::success(index_info)))
+}
+
+pub async fn list_indexes(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, collection)): Path<(String, String, String)>,
+) -> Result<Json<ApiResponse<Vec<IndexInfo>>>> {
+    if let Some(message) = missing_collection(&couchbase_service, &bucket, &scope, &collection).await? {
+        return Ok(Json(ApiResponse::error(message)));
+    }
+
+    let mut indexes = couchbase_service
+        .list_collection_indexes(&bucket, &scope, &collection)
+        .await?;
+    indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Json(ApiResponse::success(indexes)))
+}
+
+pub async fn drop_index(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, collection, index)): Path<(String, String, String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service
+        .drop_index(&bucket, &scope, &collection, &index)
+        .await?;
+
+    Ok(Json(ApiResponse::success(())))
+}
+
+pub async fn build_indexes(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, collection)): Path<(String, String, String)>,
+    payload: Option<Json<BuildIndexesRequest>>,
+) -> Result<Json<ApiResponse<Vec<String>>>> {
+    let Json(payload) = payload.unwrap_or_default();
+
+    if let Some(names) = &payload.index_names {
+        if let Some(error) = names.iter().find_map(|name| validation::validate_index_name(name).err()) {
+            return Ok(Json(ApiResponse::error(error)));
+        }
+    }
+
+    let built = couchbase_service
+        .build_indexes(&bucket, &scope, &collection, payload.index_names)
+        .await?;
+
+    Ok(Json(ApiResponse::success(built)))
+}
@@ -1,4 +1,57 @@
+pub mod analytics;
 pub mod buckets;
+pub mod capacity;
 pub mod collections;
+pub mod eventing;
+pub mod grants;
+pub mod indexes;
+pub mod query;
+pub mod rotation;
 pub mod scopes;
+pub mod search_indexes;
+pub mod topology;
 pub mod users;
+pub mod xdcr;
+
+use crate::{error::Result, services::CouchbaseService};
+
+// Error message when the scope is missing, checked against the cached manifest
+async fn missing_scope(couchbase_service: &CouchbaseService, bucket: &str, scope: &str) -> Result<Option<String>> {
+    let buckets = couchbase_service.list_buckets().await?;
+    if !buckets.iter().any(|b| b.name == bucket) {
+        return Ok(Some(format!("Bucket '{}' not found", bucket)));
+    }
+
+    let manifest = couchbase_service.get_collection_manifest(bucket).await?;
+    if !manifest.scopes.iter().any(|s| s.name == scope) {
+        return Ok(Some(format!("Scope '{}' not found in bucket '{}'", scope, bucket)));
+    }
+
+    Ok(None)
+}
+
+// Error message when the collection is missing, checked against the cached manifest
+async fn missing_collection(
+    couchbase_service: &CouchbaseService,
+    bucket: &str,
+    scope: &str,
+    collection: &str,
+) -> Result<Option<String>> {
+    let buckets = couchbase_service.list_buckets().await?;
+    if !buckets.iter().any(|b| b.name == bucket) {
+        return Ok(Some(format!("Bucket '{}' not found", bucket)));
+    }
+
+    let manifest = couchbase_service.get_collection_manifest(bucket).await?;
+    let Some(scope_info) = manifest.scopes.iter().find(|s| s.name == scope) else {
+        return Ok(Some(format!("Scope '{}' not found in bucket '{}'", scope, bucket)));
+    };
+    if !scope_info.collections.iter().any(|c| c.name == collection) {
+        return Ok(Some(format!(
+            "Collection '{}' not found in scope '{}' of bucket '{}'",
+            collection, scope, bucket
+        )));
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,133 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap,
+    },
+    response::{IntoResponse, Json, Response},
+};
+use futures::StreamExt;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    config::QueryConfig,
+    error::Result,
+    middleware::current_request_id,
+    models::{ApiResponse, QueryRequest, QueryResult},
+    query::{self, QueryEvent},
+    services::CouchbaseService,
+};
+
+/// Header carrying the caller's own Couchbase credentials, in `Authorization` format,
+/// so the query runs with their permissions instead of the configured query account
+pub const COUCHBASE_AUTHORIZATION_HEADER: &str = "x-couchbase-authorization";
+
+const NDJSON: &str = "application/x-ndjson";
+
+pub async fn run_query(
+    State(couchbase_service): State<CouchbaseService>,
+    State(query_config): State<Arc<QueryConfig>>,
+    headers: HeaderMap,
+    Json(payload): Json<QueryRequest>,
+) -> Result<Response> {
+    if payload.statement.trim().is_empty() {
+        return Ok(Json(ApiResponse::<QueryResult>::error(
+            "Statement cannot be empty".to_string(),
+        ))
+        .into_response());
+    }
+
+    if payload.scope.is_some() && payload.bucket.is_none() {
+        return Ok(Json(ApiResponse::<QueryResult>::error(
+            "Scope can only be specified when bucket is also specified".to_string(),
+        ))
+        .into_response());
+    }
+
+    // Callers can only opt out of read-only mode when the service allows writes
+    let read_only = query_config.read_only || payload.read_only.unwrap_or(true);
+    if read_only && !query::is_read_only_statement(&payload.statement) {
+        return Ok(Json(ApiResponse::<QueryResult>::error(
+            "Only SELECT, WITH, EXPLAIN, INFER and ADVISE statements are allowed in read-only mode"
+                .to_string(),
+        ))
+        .into_response());
+    }
+
+    let timeout_seconds = payload
+        .timeout_seconds
+        .unwrap_or(query_config.timeout_seconds)
+        .clamp(1, query_config.max_timeout_seconds.max(1));
+    let max_rows = payload
+        .max_rows
+        .unwrap_or(query_config.max_rows)
+        .min(query_config.max_rows);
+
+    let mut body = serde_json::json!({
+        "statement": payload.statement,
+        "timeout": format!("{}s", timeout_seconds),
+        "readonly": read_only,
+    });
+    if !payload.args.is_empty() {
+        body["args"] = serde_json::Value::Array(payload.args);
+    }
+    for (name, value) in payload.named_args {
+        body[format!("${}", name.trim_start_matches('$'))] = value;
+    }
+    if let Some(bucket) = &payload.bucket {
+        body["query_context"] = query::query_context(bucket, payload.scope.as_deref()).into();
+    }
+    if let Some(request_id) = current_request_id() {
+        body["client_context_id"] = request_id.into();
+    }
+
+    let authorization = headers
+        .get(COUCHBASE_AUTHORIZATION_HEADER)
+        .and_then(|value| value.to_str().ok());
+    let response = couchbase_service
+        .start_query(&body, authorization, Duration::from_secs(timeout_seconds))
+        .await?;
+    let events = query::stream_rows(response, max_rows);
+
+    let wants_ndjson = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains(NDJSON));
+
+    if wants_ndjson {
+        // One `{"row": ...}` line per row, then a `{"summary": ...}` line
+        let lines = events.map(|event| {
+            let line = match event {
+                QueryEvent::Row(row) => [b"{\"row\":".as_slice(), &row, b"}\n"].concat(),
+                QueryEvent::Done(summary) => {
+                    let mut line = serde_json::to_vec(&serde_json::json!({ "summary": summary }))
+                        .unwrap_or_default();
+                    line.push(b'\n');
+                    line
+                }
+            };
+            Ok::<_, Infallible>(Bytes::from(line))
+        });
+        return Ok(([(CONTENT_TYPE, NDJSON)], Body::from_stream(lines)).into_response());
+    }
+
+    let mut rows = Vec::new();
+    let mut summary = Default::default();
+    futures::pin_mut!(events);
+    while let Some(event) = events.next().await {
+        match event {
+            QueryEvent::Row(row) => rows.push(serde_json::from_slice(&row)?),
+            QueryEvent::Done(done) => summary = done,
+        }
+    }
+
+    if let Some(error) = summary.errors.first() {
+        let message = error["msg"].as_str().unwrap_or("Query failed").to_string();
+        return Ok(Json(ApiResponse::<QueryResult>::error(message)).into_response());
+    }
+
+    Ok(Json(ApiResponse::success(QueryResult { rows, summary })).into_response())
+}
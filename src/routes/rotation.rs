@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use std::sync::Arc;
+
+use crate::{
+    error::{AppError, Result},
+    models::{
+        parse_duration, ApiResponse, RotateCredentialsRequest, RotationPolicy,
+        RotationPolicyRequest, RotationResult, UserDomain,
+    },
+    passwords::PasswordPolicyEnforcer,
+    rotation::CredentialRotator,
+    services::CouchbaseService,
+};
+
+// Rotate a local user's password and optionally deliver it to a secret sink
+pub async fn rotate_credentials(
+    State(couchbase_service): State<CouchbaseService>,
+    State(password_policy): State<Arc<PasswordPolicyEnforcer>>,
+    State(rotator): State<Arc<CredentialRotator>>,
+    Path(username): Path<String>,
+    payload: Option<Json<RotateCredentialsRequest>>,
+) -> Result<Json<ApiResponse<RotationResult>>> {
+    let Json(payload) = payload.unwrap_or_default();
+
+    match rotator
+        .rotate(
+            &couchbase_service,
+            &password_policy,
+            &username,
+            payload.sink.as_deref(),
+        )
+        .await
+    {
+        Ok(result) => Ok(Json(ApiResponse::success(result))),
+        Err(AppError::Validation(message)) => Ok(Json(ApiResponse::error(message))),
+        Err(e) => Err(e),
+    }
+}
+
+// Create or replace a user's scheduled rotation policy
+pub async fn set_rotation_policy(
+    State(couchbase_service): State<CouchbaseService>,
+    State(rotator): State<Arc<CredentialRotator>>,
+    Path(username): Path<String>,
+    Json(payload): Json<RotationPolicyRequest>,
+) -> Result<Json<ApiResponse<RotationPolicy>>> {
+    let interval = match parse_duration(&payload.interval) {
+        Ok(interval) => interval,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    // Make sure the user exists before scheduling anything for it
+    couchbase_service.get_user(UserDomain::Local, &username).await?;
+
+    match rotator.set_policy(&username, interval, payload.sink).await {
+        Ok(policy) => Ok(Json(ApiResponse::success(policy))),
+        Err(AppError::Validation(message)) => Ok(Json(ApiResponse::error(message))),
+        Err(e) => Err(e),
+    }
+}
+
+// Stop scheduled rotation for a user
+pub async fn delete_rotation_policy(
+    State(rotator): State<Arc<CredentialRotator>>,
+    Path(username): Path<String>,
+) -> Result<Json<ApiResponse<RotationPolicy>>> {
+    let policy = rotator.remove_policy(&username).await?;
+    Ok(Json(ApiResponse::success(policy)))
+}
+
+// List scheduled rotation policies
+pub async fn list_rotation_policies(
+    State(rotator): State<Arc<CredentialRotator>>,
+) -> Result<Json<ApiResponse<Vec<RotationPolicy>>>> {
+    let policies = rotator.list_policies().await;
+    Ok(Json(ApiResponse::success(policies)))
+}
@@ -1,46 +1,51 @@
 use axum::{
-    extract::{Path, State},
-    response::Json,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
 };
 
 use crate::{
+    conditional::{self, manifest_etag},
     error::Result,
-    models::{ApiResponse, CreateScopeRequest, ScopeInfo},
+    models::{ApiResponse, CreateScopeRequest, ListScopesQuery, PagedResponse, ScopeInfo},
     services::CouchbaseService,
+    validation,
 };
 
 pub async fn create_scope(
     State(couchbase_service): State<CouchbaseService>,
     Path(bucket): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<CreateScopeRequest>,
-) -> Result<Json<ApiResponse<ScopeInfo>>> {
-    // Validate scope name
-    if payload.scope_name.is_empty() {
-        return Ok(Json(ApiResponse::error(
-            "Scope name cannot be empty".to_string(),
-        )));
+) -> Result<Response> {
+    if let Err(errors) = validation::validate_create_scope(&payload) {
+        return Ok(Json(ApiResponse::<ScopeInfo>::validation_failed(errors)).into_response());
     }
 
     // Check if bucket exists
     let buckets = couchbase_service.list_buckets().await?;
     if !buckets.iter().any(|b| b.name == bucket) {
-        return Ok(Json(ApiResponse::error(format!(
+        return Ok(Json(ApiResponse::<ScopeInfo>::error(format!(
             "Bucket '{}' not found",
             bucket
-        ))));
+        )))
+        .into_response());
     }
 
+    conditional::check_manifest_if_match(&headers, &couchbase_service, &bucket).await?;
+
     // Check if scope already exists
     let existing_scopes = couchbase_service.list_scopes(&bucket).await?;
     if existing_scopes.iter().any(|s| s.name == payload.scope_name) {
-        return Ok(Json(ApiResponse::error(format!(
+        return Ok(Json(ApiResponse::<ScopeInfo>::error(format!(
             "Scope '{}' already exists in bucket '{}'",
             payload.scope_name, bucket
-        ))));
+        )))
+        .into_response());
     }
 
     // Create the scope
-    couchbase_service
+    let uid = couchbase_service
         .create_scope(&bucket, &payload.scope_name)
         .await?;
 
@@ -50,22 +55,58 @@ pub async fn create_scope(
         collections: vec![],
     };
 
-    Ok(Json(ApiResponse::success(scope_info)))
+    Ok(conditional::with_etag(
+        Json(ApiResponse::success(scope_info)).into_response(),
+        &manifest_etag(&uid),
+    ))
 }
 
 pub async fn list_scopes(
     State(couchbase_service): State<CouchbaseService>,
     Path(bucket): Path<String>,
-) -> Result<Json<ApiResponse<Vec<ScopeInfo>>>> {
+    Query(query): Query<ListScopesQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
     // Check if bucket exists
     let buckets = couchbase_service.list_buckets().await?;
     if !buckets.iter().any(|b| b.name == bucket) {
-        return Ok(Json(ApiResponse::error(format!(
+        return Ok(Json(PagedResponse::<ScopeInfo>::error(format!(
             "Bucket '{}' not found",
             bucket
-        ))));
+        )))
+        .into_response());
+    }
+
+    let manifest = couchbase_service.get_collection_manifest(&bucket).await?;
+    let etag = manifest_etag(&manifest.uid);
+    if conditional::is_not_modified(&headers, &etag) {
+        return Ok(conditional::not_modified(&etag));
     }
 
-    let scopes = couchbase_service.list_scopes(&bucket).await?;
-    Ok(Json(ApiResponse::success(scopes)))
+    let mut scopes: Vec<ScopeInfo> = manifest
+        .scopes
+        .into_iter()
+        .filter(|s| query.prefix.as_deref().is_none_or(|p| s.name.starts_with(p)))
+        .collect();
+    scopes.sort_by(|a, b| query.order.apply(a.name.cmp(&b.name)));
+
+    Ok(conditional::with_etag(
+        Json(PagedResponse::from_items(scopes, query.page, query.page_size)).into_response(),
+        &etag,
+    ))
+}
+
+pub async fn delete_scope(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    conditional::check_manifest_if_match(&headers, &couchbase_service, &bucket).await?;
+
+    let uid = couchbase_service.delete_scope(&bucket, &scope).await?;
+
+    Ok(conditional::with_etag(
+        Json(ApiResponse::success(())).into_response(),
+        &manifest_etag(&uid),
+    ))
 }
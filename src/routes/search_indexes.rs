@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+
+use crate::{
+    error::{AppError, Result},
+    models::{ApiResponse, SearchIndexDefinition, SearchIndexInfo},
+    routes::missing_scope,
+    services::CouchbaseService,
+    validation,
+};
+
+pub async fn upsert_search_index(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, index)): Path<(String, String, String)>,
+    Json(payload): Json<SearchIndexDefinition>,
+) -> Result<Json<ApiResponse<SearchIndexInfo>>> {
+    // The definition check covers the index name
+    if let Err(errors) = validation::validate_search_index_path(&bucket, &scope, None) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+    if let Err(errors) = validation::validate_search_index_definition(&index, &payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    if let Some(message) = missing_scope(&couchbase_service, &bucket, &scope).await? {
+        return Ok(Json(ApiResponse::error(message)));
+    }
+
+    couchbase_service
+        .upsert_search_index(&bucket, &scope, &index, &payload)
+        .await?;
+    let index_info = couchbase_service.get_search_index(&bucket, &scope, &index).await?;
+
+    Ok(Json(ApiResponse::success(index_info)))
+}
+
+pub async fn list_search_indexes(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<Vec<SearchIndexInfo>>>> {
+    if let Err(errors) = validation::validate_search_index_path(&bucket, &scope, None) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+    if let Some(message) = missing_scope(&couchbase_service, &bucket, &scope).await? {
+        return Ok(Json(ApiResponse::error(message)));
+    }
+
+    let mut indexes = couchbase_service.list_search_indexes(&bucket, &scope).await?;
+    indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Json(ApiResponse::success(indexes)))
+}
+
+pub async fn get_search_index(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, index)): Path<(String, String, String)>,
+) -> Result<Json<ApiResponse<SearchIndexInfo>>> {
+    if let Err(errors) = validation::validate_search_index_path(&bucket, &scope, Some(&index)) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    let index_info = couchbase_service.get_search_index(&bucket, &scope, &index).await?;
+    Ok(Json(ApiResponse::success(index_info)))
+}
+
+/// The bare definition, in the format accepted by `PUT`, for keeping in a file
+pub async fn get_search_index_definition(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, index)): Path<(String, String, String)>,
+) -> Result<Json<SearchIndexDefinition>> {
+    if let Err(errors) = validation::validate_search_index_path(&bucket, &scope, Some(&index)) {
+        return Err(AppError::Validation(validation::summarize(&errors)));
+    }
+
+    let index_info = couchbase_service.get_search_index(&bucket, &scope, &index).await?;
+    Ok(Json(index_info.definition))
+}
+
+pub async fn delete_search_index(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, index)): Path<(String, String, String)>,
+) -> Result<Json<ApiResponse<()>>> {
+    if let Err(errors) = validation::validate_search_index_path(&bucket, &scope, Some(&index)) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    couchbase_service.delete_search_index(&bucket, &scope, &index).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+pub async fn search(
+    State(couchbase_service): State<CouchbaseService>,
+    Path((bucket, scope, index)): Path<(String, String, String)>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Json<ApiResponse<serde_json::Value>>> {
+    if let Err(errors) = validation::validate_search_index_path(&bucket, &scope, Some(&index)) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+    if !payload.is_object() {
+        return Ok(Json(ApiResponse::error(
+            "Search request must be a JSON object".to_string(),
+        )));
+    }
+
+    let result = couchbase_service.search(&bucket, &scope, &index, &payload).await?;
+    Ok(Json(ApiResponse::success(result)))
+}
@@ -0,0 +1,28 @@
+use axum::{
+    extract::{Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Json, Response},
+};
+
+use crate::{
+    error::{AppError, Result},
+    models::{ApiResponse, TopologyFormat, TopologyQuery},
+    services::CouchbaseService,
+};
+
+pub async fn get_topology(
+    State(couchbase_service): State<CouchbaseService>,
+    Query(query): Query<TopologyQuery>,
+) -> Result<Response> {
+    let topology = couchbase_service.get_topology().await?;
+
+    match query.format {
+        TopologyFormat::Json => Ok(Json(ApiResponse::success(topology)).into_response()),
+        // YAML is the bare document, without the response envelope, so it can be
+        // used directly as provisioning input
+        TopologyFormat::Yaml => {
+            let yaml = serde_yaml::to_string(&topology).map_err(|e| AppError::Internal(e.to_string()))?;
+            Ok(([(CONTENT_TYPE, "application/yaml")], yaml).into_response())
+        }
+    }
+}
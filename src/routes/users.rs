@@ -1,28 +1,54 @@
 use axum::{
-    extract::{Path, State},
-    response::Json,
+    body::Bytes,
+    extract::{FromRequest, Multipart, Path, Query, Request, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Json, Response},
 };
+use base64::{engine::general_purpose, Engine as _};
+use std::collections::HashSet;
+use std::sync::Arc;
 
 use crate::{
-    error::Result,
+    error::{AppError, Result},
     models::{
-        ApiResponse, CreateUserRequest, CouchbaseRole, CouchbaseUserConfig, UserInfo, Role,
-        roles,
+        ApiResponse, ChangePasswordRequest, CreateUserRequest, CreatedUser, CouchbaseRole,
+        CouchbaseUserConfig, ExportUsersQuery, ImportMode, ImportRowResult, ImportRowStatus,
+        DomainQuery, ImportUsersQuery, ImportUsersResponse, ListUsersQuery, PageInfo,
+        PagedResponse, PasswordChangeResponse, PasswordPolicy, MAX_PAGE_SIZE,
+        PermissionCheckRequest, PermissionCheckResponse, PermissionCheckResult, PermissionDecision,
+        RoleCatalog, RoleDefinition, RoleTemplateInfo, RoleTemplates, UserCsvRecord,
+        UserDomain, UserExportRecord, UserFileFormat, UserInfo, UserSortField, Role, roles,
     },
+    passwords::PasswordPolicyEnforcer,
     services::CouchbaseService,
 };
 
+// Upper bound on the size of an uploaded import file
+const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+
+// A 1-based row number paired with the parsed user or the reason it could not be parsed
+type ImportRow = (usize, std::result::Result<CreateUserRequest, String>);
+
 pub async fn create_user(
     State(couchbase_service): State<CouchbaseService>,
-    Json(payload): Json<CreateUserRequest>,
-) -> Result<Json<ApiResponse<UserInfo>>> {
+    State(role_templates): State<Arc<RoleTemplates>>,
+    State(password_policy): State<Arc<PasswordPolicyEnforcer>>,
+    Json(mut payload): Json<CreateUserRequest>,
+) -> Result<Json<ApiResponse<CreatedUser>>> {
+    // Expand the role template into concrete roles
+    if let Err(template_error) = payload.apply_template(&role_templates) {
+        return Ok(Json(ApiResponse::error(template_error)));
+    }
+
     // Validate the request
-    if let Err(validation_error) = payload.validate() {
+    let catalog = couchbase_service.role_catalog().await?;
+    let policy = password_policy.effective_policy(&couchbase_service).await;
+    if let Err(validation_error) = payload.validate(&catalog, &policy) {
         return Ok(Json(ApiResponse::error(validation_error)));
     }
 
     // Check if user already exists
-    match couchbase_service.get_user(&payload.username).await {
+    match couchbase_service.get_user(payload.domain, &payload.username).await {
         Ok(_) => {
             return Ok(Json(ApiResponse::error(format!(
                 "User '{}' already exists",
@@ -46,10 +72,17 @@ pub async fn create_user(
         })
         .collect();
 
+    // Generate a password when none was supplied; external users never have one
+    let generated_password = match (&payload.password, payload.domain) {
+        (None, UserDomain::Local) => Some(policy.generate()),
+        _ => None,
+    };
+
     // Create user configuration
     let user_config = CouchbaseUserConfig {
         name: payload.username.clone(),
-        password: payload.password,
+        domain: payload.domain,
+        password: payload.password.or_else(|| generated_password.clone()),
         roles: couchbase_roles,
     };
 
@@ -57,107 +90,208 @@ pub async fn create_user(
     couchbase_service.create_user(&user_config).await?;
 
     // Return the created user info
+    // Couchbase takes the username as the new user's name
     let user_info = UserInfo {
+        display_name: Some(payload.username.clone()),
         username: payload.username,
+        domain: payload.domain,
         roles: payload.roles,
         groups: payload.groups.unwrap_or_default(),
     };
 
-    Ok(Json(ApiResponse::success(user_info)))
+    Ok(Json(ApiResponse::success(CreatedUser {
+        user: user_info,
+        generated_password,
+    })))
+}
+
+// Change a local user's password, generating one if none is supplied
+pub async fn change_password(
+    State(couchbase_service): State<CouchbaseService>,
+    State(password_policy): State<Arc<PasswordPolicyEnforcer>>,
+    Path(username): Path<String>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<Json<ApiResponse<PasswordChangeResponse>>> {
+    let policy = password_policy.effective_policy(&couchbase_service).await;
+
+    let (password, generated_password) = match payload.password {
+        Some(password) => {
+            if let Err(validation_error) = policy.check(&username, &password) {
+                return Ok(Json(ApiResponse::error(validation_error)));
+            }
+            (password, None)
+        }
+        None => {
+            let password = policy.generate();
+            (password.clone(), Some(password))
+        }
+    };
+
+    couchbase_service.set_user_password(&username, &password).await?;
+
+    Ok(Json(ApiResponse::success(PasswordChangeResponse {
+        username,
+        generated_password,
+    })))
+}
+
+// Get the password policy currently enforced for new and changed passwords
+pub async fn get_password_policy(
+    State(couchbase_service): State<CouchbaseService>,
+    State(password_policy): State<Arc<PasswordPolicyEnforcer>>,
+) -> Result<Json<ApiResponse<PasswordPolicy>>> {
+    let policy = password_policy.effective_policy(&couchbase_service).await;
+    Ok(Json(ApiResponse::success(policy)))
 }
 
 pub async fn list_users(
     State(couchbase_service): State<CouchbaseService>,
-) -> Result<Json<ApiResponse<Vec<UserInfo>>>> {
-    let users = couchbase_service.list_users().await?;
-    Ok(Json(ApiResponse::success(users)))
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<PagedResponse<UserInfo>>> {
+    let cursor = parse_cursor(query.cursor.as_deref())?;
+    let page_size = query.page_size.map(|size| size.clamp(1, MAX_PAGE_SIZE));
+    let sort_by = query.sort_by.unwrap_or_default();
+
+    // Couchbase pages, sorts and searches users itself; role, bucket, group and
+    // domain filters are not supported there, so those fall back to paging in memory
+    if let (Some(page_size), false) = (page_size, query.has_local_filters()) {
+        let start_from = match cursor {
+            Some(UserCursor::StartFrom(domain, name)) => Some((domain, name)),
+            Some(UserCursor::Offset(_)) => return Err(cursor_mismatch()),
+            None => None,
+        };
+
+        let page = couchbase_service
+            .list_users_page(page_size, start_from, sort_by, query.order, query.search.as_deref())
+            .await?;
+
+        return Ok(Json(PagedResponse::new(
+            page.users,
+            PageInfo {
+                total: page.total,
+                page: None,
+                page_size: Some(page_size),
+                next_cursor: page
+                    .next
+                    .map(|(domain, name)| UserCursor::StartFrom(domain, name).encode()),
+            },
+        )));
+    }
+
+    let mut users: Vec<UserInfo> = couchbase_service
+        .list_users(query.domain)
+        .await?
+        .into_iter()
+        .filter(|user| query.matches(user))
+        .collect();
+
+    users.sort_by(|a, b| {
+        let ordering = match sort_by {
+            UserSortField::Username => a.username.cmp(&b.username),
+            UserSortField::Domain => a
+                .domain
+                .to_string()
+                .cmp(&b.domain.to_string())
+                .then_with(|| a.username.cmp(&b.username)),
+        };
+        query.order.apply(ordering)
+    });
+
+    let total = users.len();
+    let Some(page_size) = page_size else {
+        return Ok(Json(PagedResponse::new(users, PageInfo { total, ..Default::default() })));
+    };
+
+    let offset = match cursor {
+        Some(UserCursor::Offset(offset)) => offset,
+        Some(UserCursor::StartFrom(..)) => return Err(cursor_mismatch()),
+        None => 0,
+    };
+
+    let next_offset = offset.saturating_add(page_size);
+    let users: Vec<UserInfo> = users.into_iter().skip(offset).take(page_size).collect();
+
+    Ok(Json(PagedResponse::new(
+        users,
+        PageInfo {
+            total,
+            page: None,
+            page_size: Some(page_size),
+            next_cursor: (next_offset < total).then(|| UserCursor::Offset(next_offset).encode()),
+        },
+    )))
 }
 
 pub async fn get_user(
     State(couchbase_service): State<CouchbaseService>,
     Path(username): Path<String>,
+    Query(query): Query<DomainQuery>,
 ) -> Result<Json<ApiResponse<UserInfo>>> {
-    let user = couchbase_service.get_user(&username).await?;
+    let user = couchbase_service.get_user(query.domain, &username).await?;
     Ok(Json(ApiResponse::success(user)))
 }
 
 pub async fn delete_user(
     State(couchbase_service): State<CouchbaseService>,
     Path(username): Path<String>,
+    Query(query): Query<DomainQuery>,
 ) -> Result<Json<ApiResponse<()>>> {
-    couchbase_service.delete_user(&username).await?;
+    couchbase_service.delete_user(query.domain, &username).await?;
     Ok(Json(ApiResponse::success(())))
 }
 
-// Get available roles
-pub async fn get_available_roles() -> Result<Json<ApiResponse<serde_json::Value>>> {
-    let roles_info = serde_json::json!({
-        "console_access_roles": roles::CONSOLE_ACCESS_ROLES,
-        "data_access_roles": roles::DATA_ACCESS_ROLES,
-        "query_roles": roles::QUERY_ROLES,
-        "all_roles": roles::ALL_ROLES,
-        "role_descriptions": {
-            "admin": "Full administrative access to Couchbase console and all resources",
-            "cluster_admin": "Cluster administration access",
-            "replication_admin": "Replication management access",
-            "views_admin": "Views management access",
-            "query_manage": "Query management access",
-            "data_reader": "Read access to data (requires bucket)",
-            "data_writer": "Write access to data (requires bucket)",
-            "data_dcp_reader": "DCP read access (requires bucket)",
-            "bucket_full_access": "Full access to specific bucket",
-            "query_select": "SELECT query access",
-            "query_insert": "INSERT query access",
-            "query_update": "UPDATE query access",
-            "query_delete": "DELETE query access"
-        }
-    });
-    
-    Ok(Json(ApiResponse::success(roles_info)))
+// Get available roles from the cluster's role catalog
+pub async fn get_available_roles(
+    State(couchbase_service): State<CouchbaseService>,
+) -> Result<Json<ApiResponse<Vec<RoleDefinition>>>> {
+    let catalog = couchbase_service.role_catalog().await?;
+    let definitions = catalog.definitions().into_iter().cloned().collect();
+    Ok(Json(ApiResponse::success(definitions)))
+}
+
+// List configured role templates
+pub async fn list_role_templates(
+    State(role_templates): State<Arc<RoleTemplates>>,
+) -> Result<Json<ApiResponse<Vec<RoleTemplateInfo>>>> {
+    let mut templates: Vec<RoleTemplateInfo> = role_templates
+        .0
+        .iter()
+        .map(|(name, template)| RoleTemplateInfo {
+            name: name.clone(),
+            description: template.description.clone(),
+            parameters: template.parameters(),
+            roles: template.roles.clone(),
+        })
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Json(ApiResponse::success(templates)))
 }
 
 // Update user roles
 pub async fn update_user_roles(
     State(couchbase_service): State<CouchbaseService>,
     Path(username): Path<String>,
+    Query(query): Query<DomainQuery>,
     Json(roles): Json<Vec<Role>>,
 ) -> Result<Json<ApiResponse<UserInfo>>> {
     // Validate roles
+    let catalog = couchbase_service.role_catalog().await?;
     for role in &roles {
-        if !roles::is_valid_role(&role.role) {
-            return Ok(Json(ApiResponse::error(format!(
-                "Invalid role: '{}'. Valid roles are: {:?}", 
-                role.role, roles::ALL_ROLES
-            ))));
+        if let Err(validation_error) = catalog.validate_role(role) {
+            return Ok(Json(ApiResponse::error(validation_error)));
         }
     }
 
     // Check if user exists
-    let mut user = couchbase_service.get_user(&username).await?;
+    let mut user = couchbase_service.get_user(query.domain, &username).await?;
     
-    // Update roles
+    // Update roles, keeping the user's password, display name and groups
+    couchbase_service
+        .set_user_roles(query.domain, &username, &roles)
+        .await?;
     user.roles = roles;
     
-    // Convert to Couchbase format and update
-    let couchbase_roles: Vec<CouchbaseRole> = user
-        .roles
-        .iter()
-        .map(|role| CouchbaseRole {
-            role: role.role.clone(),
-            bucket_name: role.bucket.clone(),
-            scope_name: role.scope.clone(),
-            collection_name: role.collection.clone(),
-        })
-        .collect();
-
-    let user_config = CouchbaseUserConfig {
-        name: username.clone(),
-        password: "SecurePassword123!".to_string(), // Use a default password for updates
-        roles: couchbase_roles,
-    };
-
-    couchbase_service.update_user(&user_config).await?;
-    
     Ok(Json(ApiResponse::success(user)))
 }
 
@@ -165,8 +299,9 @@ pub async fn update_user_roles(
 pub async fn get_user_permissions(
     State(couchbase_service): State<CouchbaseService>,
     Path(username): Path<String>,
+    Query(query): Query<DomainQuery>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>> {
-    let user = couchbase_service.get_user(&username).await?;
+    let user = couchbase_service.get_user(query.domain, &username).await?;
     
     let console_access = user.roles.iter().any(|role| roles::has_console_access(&role.role));
     let bucket_permissions: Vec<String> = user
@@ -179,19 +314,708 @@ pub async fn get_user_permissions(
     
     let permissions = serde_json::json!({
         "username": user.username,
+        "domain": user.domain,
         "console_access": console_access,
         "bucket_permissions": bucket_permissions,
         "roles": user.roles,
         "groups": user.groups,
         "permission_summary": {
             "can_access_console": console_access,
-            "can_read_data": user.roles.iter().any(|r| r.role == "data_reader"),
-            "can_write_data": user.roles.iter().any(|r| r.role == "data_writer"),
+            "can_read_data": user.roles.iter().any(|r| r.role == roles::DATA_READER),
+            "can_write_data": user.roles.iter().any(|r| r.role == roles::DATA_WRITER),
             "can_run_queries": user.roles.iter().any(|r| roles::is_query_role(&r.role)),
-            "can_manage_buckets": user.roles.iter().any(|r| r.role == "bucket_full_access"),
-            "can_administer_cluster": user.roles.iter().any(|r| r.role == "admin" || r.role == "cluster_admin")
+            "can_manage_buckets": user.roles.iter().any(|r| r.role == roles::BUCKET_FULL_ACCESS),
+            "can_administer_cluster": user.roles.iter().any(|r| r.role == roles::ADMIN || r.role == roles::CLUSTER_ADMIN)
         }
     });
     
     Ok(Json(ApiResponse::success(permissions)))
 }
+
+// Check whether a user is granted each of the given permissions
+pub async fn check_user_permissions(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(username): Path<String>,
+    Query(query): Query<DomainQuery>,
+    Json(payload): Json<PermissionCheckRequest>,
+) -> Result<Json<ApiResponse<PermissionCheckResponse>>> {
+    if payload.permissions.is_empty() {
+        return Ok(Json(ApiResponse::error(
+            "At least one permission must be specified".to_string(),
+        )));
+    }
+
+    if let Some(invalid) = payload
+        .permissions
+        .iter()
+        .find(|p| !p.starts_with("cluster.") || !p.contains('!') || p.contains(','))
+    {
+        return Ok(Json(ApiResponse::error(format!(
+            "Invalid permission '{}'. Expected a form like 'cluster.bucket[orders].data.docs!read'",
+            invalid
+        ))));
+    }
+
+    // Resolve the user first so unknown users are reported as 404
+    let user = couchbase_service.get_user(query.domain, &username).await?;
+    let inherited_roles = couchbase_service.get_inherited_roles(&user).await?;
+    let granted = couchbase_service
+        .check_permissions(query.domain, &username, &payload.permissions)
+        .await?;
+
+    let results = payload
+        .permissions
+        .into_iter()
+        .map(|permission| {
+            let decision = if granted.get(&permission).copied().unwrap_or(false) {
+                PermissionDecision::Allow
+            } else {
+                PermissionDecision::Deny
+            };
+            PermissionCheckResult {
+                permission,
+                decision,
+            }
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(PermissionCheckResponse {
+        username: user.username,
+        results,
+        roles: user.roles,
+        inherited_roles,
+    })))
+}
+
+// Bulk import users from a CSV or JSON file
+pub async fn import_users(
+    State(couchbase_service): State<CouchbaseService>,
+    State(role_templates): State<Arc<RoleTemplates>>,
+    State(password_policy): State<Arc<PasswordPolicyEnforcer>>,
+    Query(query): Query<ImportUsersQuery>,
+    request: Request,
+) -> Result<Json<ApiResponse<ImportUsersResponse>>> {
+    let (data, detected_format) = read_import_payload(request).await?;
+
+    let format = match query.format.or(detected_format) {
+        Some(format) => format,
+        None => {
+            return Ok(Json(ApiResponse::error(
+                "Unable to detect import format; pass ?format=csv or ?format=json".to_string(),
+            )));
+        }
+    };
+
+    let rows = match parse_import_rows(&data, format) {
+        Ok(rows) => rows,
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+    };
+
+    let catalog = couchbase_service.role_catalog().await?;
+    let policy = password_policy.effective_policy(&couchbase_service).await;
+    let existing: HashSet<(UserDomain, String)> = couchbase_service
+        .list_users(None)
+        .await?
+        .into_iter()
+        .map(|u| (u.domain, u.username))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut results = Vec::with_capacity(rows.len());
+
+    for (row, parsed) in rows {
+        let request = match parsed.and_then(|mut request| {
+            request.apply_template(&role_templates)?;
+            Ok(request)
+        }) {
+            Ok(request) => request,
+            Err(e) => {
+                results.push(ImportRowResult {
+                    row,
+                    username: String::new(),
+                    status: ImportRowStatus::Failed,
+                    message: Some(e),
+                });
+                continue;
+            }
+        };
+
+        let username = request.username.clone();
+        let (status, message) = import_user_row(
+            &couchbase_service,
+            &catalog,
+            &policy,
+            request,
+            &existing,
+            &mut seen,
+            &query,
+        )
+        .await;
+
+        results.push(ImportRowResult {
+            row,
+            username,
+            status,
+            message,
+        });
+    }
+
+    let count = |status: ImportRowStatus| results.iter().filter(|r| r.status == status).count();
+    let response = ImportUsersResponse {
+        mode: query.mode,
+        dry_run: query.dry_run,
+        total: results.len(),
+        created: count(ImportRowStatus::Created),
+        updated: count(ImportRowStatus::Updated),
+        skipped: count(ImportRowStatus::Skipped),
+        failed: count(ImportRowStatus::Failed),
+        results,
+    };
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+// Export all users as CSV or JSON; passwords are never included
+pub async fn export_users(
+    State(couchbase_service): State<CouchbaseService>,
+    Query(query): Query<ExportUsersQuery>,
+) -> Result<Response> {
+    let users = couchbase_service.list_users(query.domain).await?;
+    let format = query.format.unwrap_or(UserFileFormat::Json);
+
+    let (body, content_type, extension) = match format {
+        UserFileFormat::Json => {
+            let records: Vec<UserExportRecord> = users
+                .into_iter()
+                .map(|user| UserExportRecord {
+                    username: user.username,
+                    domain: user.domain,
+                    roles: user.roles,
+                    groups: user.groups,
+                    display_name: user.display_name,
+                })
+                .collect();
+            (serde_json::to_vec_pretty(&records)?, "application/json", "json")
+        }
+        UserFileFormat::Csv => (write_csv(users)?, "text/csv", "csv"),
+    };
+
+    Ok((
+        [
+            (CONTENT_TYPE, content_type.to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"users.{}\"", extension),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+fn write_csv(users: Vec<UserInfo>) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for user in users {
+        writer
+            .serialize(UserCsvRecord {
+                username: user.username,
+                domain: user.domain,
+                password: String::new(),
+                roles: user.roles.iter().map(Role::to_spec).collect::<Vec<_>>().join(";"),
+                groups: user.groups.join(";"),
+                display_name: user.display_name.unwrap_or_default(),
+            })
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    writer.into_inner().map_err(|e| AppError::Internal(e.to_string()))
+}
+
+async fn import_user_row(
+    couchbase_service: &CouchbaseService,
+    catalog: &RoleCatalog,
+    policy: &PasswordPolicy,
+    request: CreateUserRequest,
+    existing: &HashSet<(UserDomain, String)>,
+    seen: &mut HashSet<(UserDomain, String)>,
+    options: &ImportUsersQuery,
+) -> (ImportRowStatus, Option<String>) {
+    if let Err(e) = request.validate(catalog, policy) {
+        return (ImportRowStatus::Failed, Some(e));
+    }
+
+    let key = (request.domain, request.username.clone());
+    if !seen.insert(key.clone()) {
+        return (
+            ImportRowStatus::Failed,
+            Some(format!("User '{}' appears more than once in the import", request.username)),
+        );
+    }
+
+    let exists = existing.contains(&key);
+
+    // Imports never generate passwords; existing users keep theirs when none is given
+    if !exists && request.domain == UserDomain::Local && request.password.is_none() {
+        return (
+            ImportRowStatus::Failed,
+            Some("Password is required for new users".to_string()),
+        );
+    }
+    if exists {
+        match options.mode {
+            ImportMode::Create => {
+                return (
+                    ImportRowStatus::Failed,
+                    Some(format!("User '{}' already exists", request.username)),
+                );
+            }
+            ImportMode::SkipExisting => {
+                return (
+                    ImportRowStatus::Skipped,
+                    Some(format!("User '{}' already exists", request.username)),
+                );
+            }
+            ImportMode::Upsert => {}
+        }
+    }
+
+    if options.dry_run {
+        let message = if exists { "Would update user" } else { "Would create user" };
+        return (ImportRowStatus::Valid, Some(message.to_string()));
+    }
+
+    match couchbase_service.import_user(&request, exists).await {
+        Ok(()) if exists => (ImportRowStatus::Updated, None),
+        Ok(()) => (ImportRowStatus::Created, None),
+        Err(e) => (ImportRowStatus::Failed, Some(e.to_string())),
+    }
+}
+
+// Read the uploaded file from either a multipart form (field `file`) or the raw body
+async fn read_import_payload(request: Request) -> Result<(Bytes, Option<UserFileFormat>)> {
+    let content_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+
+    if content_type.starts_with("multipart/form-data") {
+        let mut multipart = Multipart::from_request(request, &())
+            .await
+            .map_err(|e| AppError::Validation(e.body_text()))?;
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| AppError::Validation(e.body_text()))?
+        {
+            if field.name() != Some("file") {
+                continue;
+            }
+
+            let format = field
+                .file_name()
+                .and_then(format_from_file_name)
+                .or_else(|| field.content_type().and_then(format_from_content_type));
+            let data = field
+                .bytes()
+                .await
+                .map_err(|e| AppError::Validation(e.body_text()))?;
+            return Ok((data, format));
+        }
+
+        return Err(AppError::Validation(
+            "Multipart upload must contain a 'file' field".to_string(),
+        ));
+    }
+
+    let data = axum::body::to_bytes(request.into_body(), MAX_IMPORT_BYTES)
+        .await
+        .map_err(|e| AppError::Validation(format!("Failed to read request body: {}", e)))?;
+    Ok((data, format_from_content_type(&content_type)))
+}
+
+fn format_from_content_type(content_type: &str) -> Option<UserFileFormat> {
+    if content_type.contains("json") {
+        Some(UserFileFormat::Json)
+    } else if content_type.contains("csv") {
+        Some(UserFileFormat::Csv)
+    } else {
+        None
+    }
+}
+
+fn format_from_file_name(file_name: &str) -> Option<UserFileFormat> {
+    let lower = file_name.to_ascii_lowercase();
+    if lower.ends_with(".json") {
+        Some(UserFileFormat::Json)
+    } else if lower.ends_with(".csv") {
+        Some(UserFileFormat::Csv)
+    } else {
+        None
+    }
+}
+
+// Parse the import file into numbered rows; a malformed row becomes a per-row error
+// rather than failing the whole import
+fn parse_import_rows(
+    data: &[u8],
+    format: UserFileFormat,
+) -> std::result::Result<Vec<ImportRow>, String> {
+    match format {
+        UserFileFormat::Json => {
+            let values: Vec<serde_json::Value> = serde_json::from_slice(data)
+                .map_err(|e| format!("Import file must be a JSON array of users: {}", e))?;
+            Ok(values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    let parsed = serde_json::from_value::<CreateUserRequest>(value)
+                        .map_err(|e| format!("Invalid user definition: {}", e));
+                    (i + 1, parsed)
+                })
+                .collect())
+        }
+        UserFileFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(data);
+            Ok(reader
+                .deserialize::<UserCsvRecord>()
+                .enumerate()
+                .map(|(i, record)| {
+                    let parsed = record
+                        .map_err(|e| format!("Invalid CSV row: {}", e))
+                        .and_then(csv_record_to_request);
+                    (i + 1, parsed)
+                })
+                .collect())
+        }
+    }
+}
+
+fn csv_record_to_request(record: UserCsvRecord) -> std::result::Result<CreateUserRequest, String> {
+    let roles = split_list(&record.roles)
+        .iter()
+        .map(|spec| Role::parse_spec(spec))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let groups = split_list(&record.groups);
+
+    Ok(CreateUserRequest {
+        username: record.username,
+        domain: record.domain,
+        password: Some(record.password).filter(|s| !s.is_empty()),
+        roles,
+        groups: if groups.is_empty() { None } else { Some(groups) },
+        display_name: Some(record.display_name).filter(|s| !s.is_empty()),
+        email: None,
+        template: None,
+        params: None,
+    })
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// A cursor is client input, so a tampered one is a bad request
+fn parse_cursor(cursor: Option<&str>) -> Result<Option<UserCursor>> {
+    cursor.map(UserCursor::decode).transpose().map_err(AppError::Validation)
+}
+
+fn cursor_mismatch() -> AppError {
+    AppError::Validation("Cursor does not belong to this query".to_string())
+}
+
+// Opaque position in a user listing, handed out as `pagination.next_cursor`
+#[derive(Debug, PartialEq, Eq)]
+enum UserCursor {
+    // Couchbase paging: the first user of the next page
+    StartFrom(UserDomain, String),
+    // In-memory paging: number of matching users already returned
+    Offset(usize),
+}
+
+impl UserCursor {
+    fn encode(&self) -> String {
+        let raw = match self {
+            UserCursor::StartFrom(domain, name) => format!("cb:{}:{}", domain, name),
+            UserCursor::Offset(offset) => format!("off:{}", offset),
+        };
+        general_purpose::URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    fn decode(cursor: &str) -> std::result::Result<Self, String> {
+        let invalid = || "Invalid pagination cursor".to_string();
+        let raw = general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid)?;
+
+        if let Some(offset) = raw.strip_prefix("off:") {
+            return offset.parse().map(UserCursor::Offset).map_err(|_| invalid());
+        }
+
+        let rest = raw.strip_prefix("cb:").ok_or_else(invalid)?;
+        let (domain, name) = rest.split_once(':').ok_or_else(invalid)?;
+        let domain = match domain {
+            "local" => UserDomain::Local,
+            "external" => UserDomain::External,
+            _ => return Err(invalid()),
+        };
+        Ok(UserCursor::StartFrom(domain, name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::testing::{fake_cluster, Method};
+    use axum::http::StatusCode;
+
+    fn encoded(raw: &str) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        for cursor in [
+            UserCursor::StartFrom(UserDomain::Local, "alice".to_string()),
+            UserCursor::StartFrom(UserDomain::External, "cn=bob:ou=people".to_string()),
+            UserCursor::StartFrom(UserDomain::Local, String::new()),
+            UserCursor::Offset(0),
+            UserCursor::Offset(usize::MAX),
+        ] {
+            assert_eq!(UserCursor::decode(&cursor.encode()), Ok(cursor));
+        }
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for cursor in [
+            String::new(),
+            "not base64!".to_string(),
+            general_purpose::URL_SAFE_NO_PAD.encode([0xff, 0xfe]),
+            encoded("off:"),
+            encoded("off:-1"),
+            encoded("off:18446744073709551616"),
+            encoded("cb:local"),
+            encoded("cb:ldap:alice"),
+            encoded("page:2"),
+        ] {
+            assert_eq!(
+                UserCursor::decode(&cursor),
+                Err("Invalid pagination cursor".to_string()),
+                "{}",
+                cursor
+            );
+        }
+    }
+
+    fn users(rows: std::result::Result<Vec<ImportRow>, String>) -> Vec<CreateUserRequest> {
+        rows.unwrap()
+            .into_iter()
+            .map(|(row, parsed)| parsed.unwrap_or_else(|e| panic!("row {}: {}", row, e)))
+            .collect()
+    }
+
+    #[test]
+    fn json_import_rows() {
+        let data = br#"[
+            {"username": "alice", "password": "Secret-123", "roles": [{"role": "admin"}], "display_name": "Alice"},
+            {"username": "bob", "domain": "external", "groups": ["ops"]},
+            {"roles": []}
+        ]"#;
+        let rows = parse_import_rows(data, UserFileFormat::Json).unwrap();
+        assert_eq!(rows.iter().map(|(row, _)| *row).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let alice = rows[0].1.as_ref().unwrap();
+        assert_eq!(alice.password.as_deref(), Some("Secret-123"));
+        assert_eq!(alice.display_name.as_deref(), Some("Alice"));
+        let bob = rows[1].1.as_ref().unwrap();
+        assert_eq!(bob.domain, UserDomain::External);
+        assert_eq!(bob.groups, Some(vec!["ops".to_string()]));
+        assert!(rows[2].1.as_ref().unwrap_err().starts_with("Invalid user definition"));
+
+        assert!(parse_import_rows(b"{}", UserFileFormat::Json).is_err());
+    }
+
+    #[test]
+    fn csv_import_rows() {
+        let data = b"username,domain,password,roles,groups,display_name,email\n\
+            alice,local,Secret-123,admin; data_reader[travel:inventory] ,ops;dev,Alice,alice@example.com\n\
+            bob,external,,,,,\n\
+            carol,local,x,data_reader[a:b:c:d],,,\n\
+            dave,ldap,,,,,\n";
+        let rows = parse_import_rows(data, UserFileFormat::Csv).unwrap();
+        assert_eq!(rows.len(), 4);
+
+        let alice = rows[0].1.as_ref().unwrap();
+        assert_eq!(alice.password.as_deref(), Some("Secret-123"));
+        assert_eq!(
+            alice.roles,
+            [
+                Role::parse_spec("admin").unwrap(),
+                Role::parse_spec("data_reader[travel:inventory]").unwrap()
+            ]
+        );
+        assert_eq!(alice.groups, Some(vec!["ops".to_string(), "dev".to_string()]));
+        assert_eq!(alice.display_name.as_deref(), Some("Alice"));
+
+        // Empty columns mean "not given"
+        let bob = rows[1].1.as_ref().unwrap();
+        assert_eq!(bob.domain, UserDomain::External);
+        assert_eq!((&bob.password, &bob.groups, &bob.display_name), (&None, &None, &None));
+        assert!(bob.roles.is_empty());
+
+        assert!(rows[2].1.as_ref().unwrap_err().contains("too many keyspace components"));
+        assert!(rows[3].1.as_ref().unwrap_err().starts_with("Invalid CSV row"));
+    }
+
+    #[test]
+    fn csv_export_imports_back() {
+        let exported = vec![
+            UserInfo {
+                username: "alice".to_string(),
+                domain: UserDomain::Local,
+                roles: vec![
+                    Role::parse_spec("admin").unwrap(),
+                    Role::parse_spec("data_writer[travel:inventory:hotel]").unwrap(),
+                ],
+                groups: vec!["ops".to_string(), "dev".to_string()],
+                display_name: Some("Alice, Ops".to_string()),
+            },
+            UserInfo {
+                username: "bob".to_string(),
+                domain: UserDomain::External,
+                roles: vec![],
+                groups: vec![],
+                display_name: None,
+            },
+        ];
+
+        let imported = users(parse_import_rows(&write_csv(exported.clone()).unwrap(), UserFileFormat::Csv));
+        assert_eq!(imported.len(), exported.len());
+        for (user, row) in exported.into_iter().zip(imported) {
+            assert_eq!(row.username, user.username);
+            assert_eq!(row.domain, user.domain);
+            assert_eq!(row.password, None);
+            assert_eq!(row.roles, user.roles);
+            assert_eq!(row.groups.unwrap_or_default(), user.groups);
+            assert_eq!(row.display_name, user.display_name);
+        }
+    }
+
+    #[test]
+    fn tampered_cursor_is_a_bad_request() {
+        assert!(matches!(parse_cursor(None), Ok(None)));
+        assert!(matches!(parse_cursor(Some(&encoded("off:20"))), Ok(Some(UserCursor::Offset(20)))));
+
+        let error = parse_cursor(Some(&encoded("off:many"))).unwrap_err();
+        assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(cursor_mismatch().into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    async fn check(
+        service: CouchbaseService,
+        permissions: &[&str],
+    ) -> Result<Json<ApiResponse<PermissionCheckResponse>>> {
+        check_user_permissions(
+            State(service),
+            Path("alice".to_string()),
+            Query(DomainQuery::default()),
+            Json(PermissionCheckRequest {
+                permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn malformed_permissions_are_rejected_before_asking_the_cluster() {
+        let (service, requests) = fake_cluster(vec![]).await;
+
+        for permissions in [
+            &[][..],
+            &["bucket[travel].data.docs!read"],
+            &["cluster.bucket[travel].data.docs"],
+            &["cluster.admin.security!read,cluster.admin.security!write"],
+            &["cluster.bucket[travel].data.docs!read", "read"],
+        ] {
+            let Json(response) = check(service.clone(), permissions).await.unwrap();
+            assert!(!response.success, "{:?}", permissions);
+        }
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn permission_decisions_follow_the_cluster() {
+        let (service, requests) = fake_cluster(vec![
+            (
+                Method::GET,
+                "/settings/rbac/users/local/alice",
+                200,
+                serde_json::json!({
+                    "id": "alice",
+                    "domain": "local",
+                    "groups": ["ops"],
+                    "roles": [{"role": "data_reader", "bucket_name": "travel", "origins": [{"type": "user"}]}]
+                }),
+            ),
+            (
+                Method::GET,
+                "/settings/rbac/groups/ops",
+                200,
+                serde_json::json!({"id": "ops", "roles": [{"role": "data_writer", "bucket_name": "travel"}]}),
+            ),
+            (
+                Method::POST,
+                "/pools/default/checkPermissions",
+                200,
+                serde_json::json!({
+                    "cluster.bucket[travel].data.docs!read": true,
+                    "cluster.bucket[travel].data.docs!write": false
+                }),
+            ),
+        ])
+        .await;
+        let permissions = [
+            "cluster.bucket[travel].data.docs!read",
+            "cluster.bucket[travel].data.docs!write",
+            "cluster.admin.security!read",
+        ];
+
+        let Json(response) = check(service, &permissions).await.unwrap();
+
+        let data = response.data.unwrap();
+        assert_eq!(data.username, "alice");
+        let decisions: Vec<_> = data.results.iter().map(|r| (r.permission.as_str(), r.decision)).collect();
+        // Permissions missing from the cluster's answer are denied
+        assert_eq!(
+            decisions,
+            [
+                (permissions[0], PermissionDecision::Allow),
+                (permissions[1], PermissionDecision::Deny),
+                (permissions[2], PermissionDecision::Deny),
+            ]
+        );
+        assert_eq!(data.roles[0].to_spec(), "data_reader[travel]");
+        assert_eq!(data.inherited_roles[0].group, "ops");
+        assert_eq!(data.inherited_roles[0].role.to_spec(), "data_writer[travel]");
+
+        let requests = requests.lock().unwrap();
+        let check = requests.iter().find(|r| r.method == Method::POST).unwrap();
+        assert_eq!(check.body, permissions.join(","));
+    }
+
+    #[tokio::test]
+    async fn permissions_of_unknown_users_are_not_found() {
+        let (service, _) = fake_cluster(vec![]).await;
+        let error = check(service, &["cluster.admin.security!read"]).await.err().unwrap();
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+
+use crate::{
+    error::Result,
+    models::{
+        ApiResponse, CreateRemoteClusterRequest, CreateReplicationRequest, RemoteClusterInfo,
+        ReplicationInfo, ReplicationStats,
+    },
+    services::CouchbaseService,
+    validation,
+};
+
+// Remote Cluster References
+pub async fn create_remote_cluster(
+    State(couchbase_service): State<CouchbaseService>,
+    Json(payload): Json<CreateRemoteClusterRequest>,
+) -> Result<Json<ApiResponse<RemoteClusterInfo>>> {
+    if let Err(errors) = validation::validate_create_remote_cluster(&payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    let existing = couchbase_service.list_remote_clusters().await?;
+    if existing.iter().any(|cluster| cluster.name == payload.name) {
+        return Ok(Json(ApiResponse::error(format!(
+            "Remote cluster '{}' already exists",
+            payload.name
+        ))));
+    }
+
+    let remote_cluster = couchbase_service.create_remote_cluster(&payload).await?;
+    Ok(Json(ApiResponse::success(remote_cluster)))
+}
+
+pub async fn list_remote_clusters(
+    State(couchbase_service): State<CouchbaseService>,
+) -> Result<Json<ApiResponse<Vec<RemoteClusterInfo>>>> {
+    let mut remote_clusters = couchbase_service.list_remote_clusters().await?;
+    remote_clusters.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Json(ApiResponse::success(remote_clusters)))
+}
+
+pub async fn delete_remote_cluster(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.delete_remote_cluster(&name).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+// Replications
+pub async fn create_replication(
+    State(couchbase_service): State<CouchbaseService>,
+    Json(payload): Json<CreateReplicationRequest>,
+) -> Result<Json<ApiResponse<ReplicationInfo>>> {
+    if let Err(errors) = validation::validate_create_replication(&payload) {
+        return Ok(Json(ApiResponse::validation_failed(errors)));
+    }
+
+    let buckets = couchbase_service.list_buckets().await?;
+    if !buckets.iter().any(|b| b.name == payload.source_bucket) {
+        return Ok(Json(ApiResponse::error(format!(
+            "Bucket '{}' not found",
+            payload.source_bucket
+        ))));
+    }
+
+    let remote_clusters = couchbase_service.list_remote_clusters().await?;
+    let Some(remote_cluster) = remote_clusters.iter().find(|c| c.name == payload.remote_cluster) else {
+        return Ok(Json(ApiResponse::error(format!(
+            "Remote cluster '{}' not found",
+            payload.remote_cluster
+        ))));
+    };
+
+    let id = couchbase_service.create_replication(&payload).await?;
+
+    // Stats only appear once the replication has been running for a while
+    let replication = ReplicationInfo {
+        id,
+        source_bucket: payload.source_bucket,
+        remote_cluster: Some(remote_cluster.name.clone()),
+        remote_cluster_uuid: remote_cluster.uuid.clone(),
+        target_bucket: payload.target_bucket,
+        status: "running".to_string(),
+        filter_expression: payload.filter_expression,
+        collection_mappings: payload.collection_mappings,
+        stats: ReplicationStats::default(),
+        errors: vec![],
+    };
+
+    Ok(Json(ApiResponse::success(replication)))
+}
+
+pub async fn list_replications(
+    State(couchbase_service): State<CouchbaseService>,
+) -> Result<Json<ApiResponse<Vec<ReplicationInfo>>>> {
+    let mut replications = couchbase_service.list_replications().await?;
+    replications.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(Json(ApiResponse::success(replications)))
+}
+
+pub async fn get_replication(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<ReplicationInfo>>> {
+    let replication = couchbase_service.get_replication(&id).await?;
+    Ok(Json(ApiResponse::success(replication)))
+}
+
+pub async fn pause_replication(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.set_replication_paused(&id, true).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+pub async fn resume_replication(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.set_replication_paused(&id, false).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+pub async fn delete_replication(
+    State(couchbase_service): State<CouchbaseService>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<()>>> {
+    couchbase_service.delete_replication(&id).await?;
+    Ok(Json(ApiResponse::success(())))
+}
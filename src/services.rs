@@ -1,19 +1,97 @@
 use crate::{
+    cache::ManagementCache,
     config::Config,
     error::{AppError, Result},
+    query::quote_identifier,
+    telemetry, validation,
+    cache,
     models::{
-        BucketInfo, CollectionInfo, CouchbaseBucketConfig, CouchbaseUserConfig, Role, ScopeInfo, UserInfo,
+        BucketInfo, BucketTopology, ClusterCapacity, ClusterTopology, DiskCapacity, RamCapacity,
+        ServiceCapacity, UpdateBucketRequest, CreateIndexRequest, IndexInfo, PRIMARY_INDEX_NAME, SearchIndexDefinition,
+        SearchIndexInfo, SEARCH_INDEX_TYPE, CreateRemoteClusterRequest, CreateReplicationRequest, RemoteClusterInfo,
+        ReplicationInfo, ReplicationStats, XdcrEncryption, EventingFunctionDefinition, EventingFunctionInfo,
+        EventingLifecycleAction, AnalyticsCollectionInfo, AnalyticsIngestionStatus, AnalyticsLinkInfo,
+        AnalyticsLinkSettings, AnalyticsScopeInfo, CreateAnalyticsCollectionRequest, CreateAnalyticsLinkRequest,
+        LOCAL_ANALYTICS_LINK, CollectionInfo, CollectionManifest,
+        BucketType, CouchbaseBucketConfig, CouchbaseUserConfig, CreateUserRequest, StorageBackend, InheritedRole, Role, PasswordPolicy, RoleCatalog, RoleDefinition, RoleParameter, ScopeInfo, SortOrder, UserDomain,
+        UserInfo, UserSortField,
     },
 };
-use reqwest::Client;
-use std::time::Duration;
+use base64::{engine::general_purpose, Engine as _};
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::{header::HeaderValue, Client, RequestBuilder, Response};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, Semaphore};
+use tracing::{debug, info, warn, Instrument, Span};
+
+// `/indexStatus` states of a built index and of a deferred one that has not been built
+const READY_INDEX_STATUS: &str = "Ready";
+const DEFERRED_INDEX_STATUS: &str = "Created";
+
+const INDEX_PROVISION_ATTEMPTS: u32 = 10;
+
+// Source type of search indexes fed from a bucket's collections
+const SEARCH_SOURCE_TYPE: &str = "gocbcore";
+
+// Eventing settings that hold a function's deployment state rather than its definition
+const EVENTING_STATE_SETTINGS: &[&str] = &["deployment_status", "processing_status"];
+
+// Eventing errors for a lifecycle change the function's current state does not allow
+const EVENTING_STATE_ERRORS: &[&str] = &[
+    "ERR_APP_ALREADY_DEPLOYED",
+    "ERR_APP_NOT_DEPLOYED",
+    "ERR_APP_NOT_UNDEPLOYED",
+    "ERR_APP_NOT_BOOTSTRAPPED",
+    "ERR_APP_PAUSED",
+];
+
+// Analytics error codes for a scope, collection or link that does not exist, and
+// for one that already does
+const ANALYTICS_NOT_FOUND_CODES: &[u64] = &[24006, 24025, 24034, 24044, 24045];
+const ANALYTICS_EXISTS_CODES: &[u64] = &[24039, 24040, 24055];
+
+// Per-node memory quota keys in `/pools/default`, by service
+const SERVICE_QUOTAS: &[(&str, &str)] = &[
+    ("kv", "memoryQuota"),
+    ("index", "indexMemoryQuota"),
+    ("fts", "ftsMemoryQuota"),
+    ("cbas", "cbasMemoryQuota"),
+    ("eventing", "eventingMemoryQuota"),
+];
+
+/// One page of users from `/settings/rbac/users`
+pub struct UserPage {
+    pub users: Vec<UserInfo>,
+    pub total: usize,
+    /// First user of the next page, if there is one
+    pub next: Option<(UserDomain, String)>,
+}
 
 #[derive(Clone)]
 pub struct CouchbaseService {
     client: Client,
     base_url: String,
+    query_url: String,
+    search_url: String,
+    eventing_url: String,
+    analytics_url: String,
+    // Account for `POST /query` when the caller sends no credentials of their own
+    query_account: Option<(String, String)>,
     username: String,
     password: String,
+    role_catalog: Arc<RwLock<Option<CachedRoleCatalog>>>,
+    role_catalog_ttl: Duration,
+    topology_concurrency: usize,
+    cache: Arc<ManagementCache>,
+    // Caps concurrent calls to the cluster across all requests and background tasks
+    outbound: Arc<Semaphore>,
+}
+
+struct CachedRoleCatalog {
+    catalog: Arc<RoleCatalog>,
+    fetched_at: Instant,
 }
 
 impl CouchbaseService {
@@ -25,31 +103,91 @@ impl CouchbaseService {
         Ok(Self {
             client,
             base_url: config.couchbase.host.clone(),
+            query_url: config.couchbase.query_host.clone(),
+            search_url: config.couchbase.search_host.clone(),
+            eventing_url: config.couchbase.eventing_host.clone(),
+            analytics_url: config.couchbase.analytics_host.clone(),
+            query_account: config
+                .query
+                .username
+                .clone()
+                .map(|username| (username, config.query.password.clone().unwrap_or_default())),
             username: config.couchbase.username.clone(),
             password: config.couchbase.password.clone(),
+            role_catalog: Arc::new(RwLock::new(None)),
+            role_catalog_ttl: Duration::from_secs(config.couchbase.role_catalog_refresh_seconds.max(1)),
+            topology_concurrency: config.couchbase.topology_concurrency.max(1),
+            cache: Arc::new(ManagementCache::new(&config.cache)),
+            outbound: Arc::new(Semaphore::new(config.couchbase.max_concurrent_requests.max(1))),
         })
     }
 
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let mut request = request.build()?;
+        if let Some(request_id) = crate::middleware::current_request_id() {
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                request
+                    .headers_mut()
+                    .insert(crate::middleware::REQUEST_ID_HEADER, value);
+            }
+        }
+
+        let span = telemetry::couchbase_span(request.method(), request.url().path());
+        telemetry::inject_context(&span, request.headers_mut());
+
+        async {
+            let _permit = self
+                .outbound
+                .acquire()
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            let response = self.client.execute(request).await?;
+
+            let status = response.status().as_u16();
+            Span::current().record("http.status_code", status);
+            debug!(status, url = %response.url().path(), "Couchbase responded");
+            Ok(response)
+        }
+        .instrument(span)
+        .await
+    }
+
     // Bucket Management
     pub async fn create_bucket(&self, request: &CouchbaseBucketConfig) -> Result<()> {
         let url = format!("{}/pools/default/buckets", self.base_url);
         
-        let params = [
-            ("name", request.name.as_str()),
-            ("ramQuotaMB", &request.ram_quota_mb.to_string()),
-            ("replicaNumber", &request.replica_number.to_string()),
-            ("evictionPolicy", request.eviction_policy.as_str()),
-            ("compressionMode", request.compression_mode.as_str()),
-            ("conflictResolutionType", request.conflict_resolution_type.as_str()),
+        let mut params: Vec<(&str, String)> = vec![
+            ("name", request.name.clone()),
+            ("bucketType", request.bucket_type.as_str().to_string()),
+            ("ramQuotaMB", request.ram_quota_mb.to_string()),
+            ("flushEnabled", u8::from(request.flush_enabled).to_string()),
         ];
 
-        let response = self
+        let optional = [
+            ("replicaNumber", request.replica_number.map(|v| v.to_string())),
+            ("evictionPolicy", request.eviction_policy.clone()),
+            ("compressionMode", request.compression_mode.clone()),
+            ("conflictResolutionType", request.conflict_resolution_type.clone()),
+            ("storageBackend", request.storage_backend.map(|b| b.as_str().to_string())),
+            ("maxTTL", request.max_ttl.map(|v| v.to_string())),
+            ("durabilityMinLevel", request.durability_min_level.clone()),
+            ("replicaIndex", request.replica_index.map(|v| u8::from(v).to_string())),
+            (
+                "historyRetentionCollectionDefault",
+                request.history_retention_collection_default.map(|v| v.to_string()),
+            ),
+            ("historyRetentionBytes", request.history_retention_bytes.map(|v| v.to_string())),
+            ("historyRetentionSeconds", request.history_retention_seconds.map(|v| v.to_string())),
+            ("numVBuckets", request.num_vbuckets.map(|v| v.to_string())),
+        ];
+        params.extend(optional.into_iter().filter_map(|(key, value)| Some((key, value?))));
+
+        let request = self
             .client
             .post(&url)
             .basic_auth(&self.username, Some(&self.password))
-            .form(&params)
-            .send()
-            .await?;
+            .form(&params);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
@@ -60,18 +198,23 @@ impl CouchbaseService {
             });
         }
 
+        self.cache.invalidate_buckets();
+
         Ok(())
     }
 
     pub async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
+        if let Some(buckets) = self.cache.buckets() {
+            return Ok(buckets);
+        }
+
         let url = format!("{}/pools/default/buckets", self.base_url);
         
-        let response = self
+        let request = self
             .client
             .get(&url)
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await?;
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
@@ -83,12 +226,30 @@ impl CouchbaseService {
         }
 
         let buckets: Vec<serde_json::Value> = response.json().await?;
+        let manifest_uids: HashMap<String, String> = buckets
+            .iter()
+            .filter_map(|bucket| {
+                Some((
+                    bucket["name"].as_str()?.to_string(),
+                    bucket["collectionsManifestUid"].as_str()?.to_string(),
+                ))
+            })
+            .collect();
+
         let bucket_infos: Result<Vec<BucketInfo>> = buckets
             .into_iter()
             .map(|bucket| {
                 Ok(BucketInfo {
                     name: bucket["name"].as_str().unwrap_or("").to_string(),
-                    ram_quota_mb: bucket["quota"]["ram"].as_u64().unwrap_or(0) as u32,
+                    bucket_type: bucket["bucketType"]
+                        .as_str()
+                        .and_then(BucketType::from_couchbase)
+                        .unwrap_or_default(),
+                    ram_quota_mb: (bucket["quota"]["rawRAM"]
+                        .as_u64()
+                        .or(bucket["quota"]["ram"].as_u64())
+                        .unwrap_or(0)
+                        / (1024 * 1024)) as u32,
                     replica_number: bucket["replicaNumber"].as_u64().unwrap_or(0) as u32,
                     eviction_policy: bucket["evictionPolicy"].as_str().unwrap_or("").to_string(),
                     compression_mode: bucket["compressionMode"].as_str().unwrap_or("").to_string(),
@@ -96,29 +257,136 @@ impl CouchbaseService {
                         .as_str()
                         .unwrap_or("")
                         .to_string(),
+                    storage_backend: bucket["storageBackend"]
+                        .as_str()
+                        .and_then(StorageBackend::from_couchbase),
+                    max_ttl: bucket["maxTTL"].as_u64().unwrap_or(0) as u32,
+                    // Flushable buckets expose a flush controller
+                    flush_enabled: bucket["controllers"]["flush"].is_string(),
+                    durability_min_level: bucket["durabilityMinLevel"].as_str().map(str::to_string),
+                    replica_index: bucket["replicaIndex"].as_bool(),
+                    history_retention_collection_default: bucket["historyRetentionCollectionDefault"]
+                        .as_bool(),
+                    history_retention_bytes: bucket["historyRetentionBytes"].as_u64(),
+                    history_retention_seconds: bucket["historyRetentionSeconds"].as_u64(),
+                    num_vbuckets: bucket["numVBuckets"].as_u64().map(|v| v as u32),
                     status: bucket["status"].as_str().unwrap_or("").to_string(),
                 })
             })
             .collect();
 
-        bucket_infos
+        let bucket_infos = bucket_infos?;
+        self.cache.put_buckets(bucket_infos.clone(), &manifest_uids);
+        Ok(bucket_infos)
+    }
+
+    /// Change settings of an existing bucket
+    pub async fn update_bucket(&self, bucket_name: &str, request: &UpdateBucketRequest) -> Result<()> {
+        let url = format!("{}/pools/default/buckets/{}", self.base_url, bucket_name);
+
+        let params: Vec<(&str, String)> = [
+            ("ramQuotaMB", request.ram_quota_mb.map(|v| v.to_string())),
+            ("replicaNumber", request.replica_number.map(|v| v.to_string())),
+            ("evictionPolicy", request.eviction_policy.clone()),
+            ("compressionMode", request.compression_mode.clone()),
+            ("maxTTL", request.max_ttl.map(|v| v.to_string())),
+            ("flushEnabled", request.flush_enabled.map(|v| u8::from(v).to_string())),
+            ("durabilityMinLevel", request.durability_min_level.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+
+        let request = self
+            .client
+            .post(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .form(&params);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        self.cache.invalidate_buckets();
+
+        Ok(())
+    }
+
+    // Capacity
+    async fn get_pool(&self) -> Result<serde_json::Value> {
+        let url = format!("{}/pools/default", self.base_url);
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Service quotas from `/pools/default`, with data service usage taken from the
+    /// current bucket quotas
+    pub async fn get_capacity(&self) -> Result<ClusterCapacity> {
+        let (pool, buckets) = tokio::try_join!(self.get_pool(), cache::bypass(self.list_buckets()))?;
+        Ok(cluster_capacity(&pool, &buckets))
+    }
+
+    /// Fail with a conflict if giving `bucket_name` a quota of `ram_quota_mb` would
+    /// overcommit the data service quota. An existing bucket's current quota counts as free.
+    pub async fn ensure_ram_available(&self, bucket_name: &str, ram_quota_mb: u32) -> Result<()> {
+        let (pool, buckets) = tokio::try_join!(self.get_pool(), cache::bypass(self.list_buckets()))?;
+        let capacity = cluster_capacity(&pool, &buckets);
+        let Some(data) = capacity.services.iter().find(|s| s.service == "kv") else {
+            return Ok(());
+        };
+
+        let current_mb = buckets
+            .iter()
+            .find(|b| b.name == bucket_name)
+            .map_or(0, |b| b.ram_quota_mb as u64);
+        let free_mb = data.free_mb.unwrap_or(0) + current_mb;
+
+        if ram_quota_mb as u64 > free_mb {
+            return Err(AppError::Conflict(format!(
+                "Bucket '{}' needs {} MB of RAM per node but only {} MB of the {} MB data service quota is free",
+                bucket_name, ram_quota_mb, free_mb, data.quota_mb
+            )));
+        }
+
+        Ok(())
     }
 
     // Scope Management
-    pub async fn create_scope(&self, bucket_name: &str, scope_name: &str) -> Result<()> {
+    /// Returns the bucket's new manifest uid
+    pub async fn create_scope(&self, bucket_name: &str, scope_name: &str) -> Result<String> {
         let url = format!("{}/pools/default/buckets/{}/scopes", self.base_url, bucket_name);
         
         let params: Vec<(String, String)> = vec![
             ("name".to_string(), scope_name.to_string()),
         ];
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .basic_auth(&self.username, Some(&self.password))
-            .form(&params)
-            .send()
-            .await?;
+            .form(&params);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
@@ -129,18 +397,57 @@ impl CouchbaseService {
             });
         }
 
-        Ok(())
+        self.cache.invalidate_manifest(bucket_name);
+
+        manifest_uid(response).await
+    }
+
+    /// Returns the bucket's new manifest uid
+    pub async fn delete_scope(&self, bucket_name: &str, scope_name: &str) -> Result<String> {
+        let url = format!(
+            "{}/pools/default/buckets/{}/scopes/{}",
+            self.base_url, bucket_name, scope_name
+        );
+
+        let request = self
+            .client
+            .delete(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("Scope '{}' not found", scope_name)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        self.cache.invalidate_manifest(bucket_name);
+
+        manifest_uid(response).await
     }
 
     pub async fn list_scopes(&self, bucket_name: &str) -> Result<Vec<ScopeInfo>> {
+        Ok(self.get_collection_manifest(bucket_name).await?.scopes)
+    }
+
+    pub async fn get_collection_manifest(&self, bucket_name: &str) -> Result<CollectionManifest> {
+        if let Some(manifest) = self.cache.manifest(bucket_name) {
+            return Ok(manifest);
+        }
+
         let url = format!("{}/pools/default/buckets/{}/scopes", self.base_url, bucket_name);
         
-        let response = self
+        let request = self
             .client
             .get(&url)
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await?;
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
@@ -178,10 +485,16 @@ impl CouchbaseService {
             })
             .collect();
 
-        scope_infos
+        let manifest = CollectionManifest {
+            uid: uid_from_manifest(&scopes_data)?,
+            scopes: scope_infos?,
+        };
+        self.cache.put_manifest(bucket_name, manifest.clone());
+        Ok(manifest)
     }
 
     // Collection Management
+    /// Returns the bucket's new manifest uid
     pub async fn create_collection(
         &self,
         bucket_name: &str,
@@ -189,7 +502,7 @@ impl CouchbaseService {
         collection_name: &str,
         max_ttl: Option<u32>,
         history: Option<bool>,
-    ) -> Result<()> {
+    ) -> Result<String> {
         let url = format!(
             "{}/pools/default/buckets/{}/scopes/{}/collections",
             self.base_url, bucket_name, scope_name
@@ -207,13 +520,12 @@ impl CouchbaseService {
             params.push(("history".to_string(), hist.to_string()));
         }
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .basic_auth(&self.username, Some(&self.password))
-            .form(&params)
-            .send()
-            .await?;
+            .form(&params);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
@@ -224,7 +536,47 @@ impl CouchbaseService {
             });
         }
 
-        Ok(())
+        self.cache.invalidate_manifest(bucket_name);
+
+        manifest_uid(response).await
+    }
+
+    /// Returns the bucket's new manifest uid
+    pub async fn delete_collection(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        collection_name: &str,
+    ) -> Result<String> {
+        let url = format!(
+            "{}/pools/default/buckets/{}/scopes/{}/collections/{}",
+            self.base_url, bucket_name, scope_name, collection_name
+        );
+
+        let request = self
+            .client
+            .delete(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!(
+                    "Collection '{}' not found",
+                    collection_name
+                )));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        self.cache.invalidate_manifest(bucket_name);
+
+        manifest_uid(response).await
     }
 
     pub async fn list_collections(&self, bucket_name: &str, scope_name: &str) -> Result<Vec<CollectionInfo>> {
@@ -238,135 +590,131 @@ impl CouchbaseService {
         Ok(scope.collections)
     }
 
-    // User Management
-    pub async fn create_user(&self, request: &CouchbaseUserConfig) -> Result<()> {
-        let url = format!("{}/settings/rbac/users/local/{}", self.base_url, request.name);
-        
-        // Convert roles to form-encoded format
-        let mut params: Vec<(String, String)> = vec![
-            ("name".to_string(), request.name.clone()),
-            ("password".to_string(), request.password.clone()),
-        ];
-
-        // Add roles - Couchbase expects roles as separate parameters
-        // We need to send all roles in a single "roles" parameter as a comma-separated string
-        let mut role_strings = Vec::new();
-        for role in &request.roles {
-            if role.bucket_name.is_none() && role.scope_name.is_none() && role.collection_name.is_none() {
-                // Global role
-                role_strings.push(role.role.clone());
-            } else {
-                // Bucket/scope/collection specific role - format as "role[bucket:scope:collection]"
-                let role_spec = if let Some(bucket) = &role.bucket_name {
-                    let mut spec = format!("{}[{}", role.role, bucket);
-                    if let Some(scope) = &role.scope_name {
-                        spec.push_str(&format!(":{}", scope));
-                        if let Some(collection) = &role.collection_name {
-                            spec.push_str(&format!(":{}", collection));
-                        }
-                    }
-                    spec.push(']');
-                    spec
-                } else {
-                    role.role.clone()
-                };
-                role_strings.push(role_spec);
+    // Topology
+    /// Fetch every bucket's collection manifest, at most `topology_concurrency` at a time.
+    /// Indexes are best-effort: if the index service can't be read, the topology is
+    /// returned without them and `indexes_error` says why.
+    pub async fn get_topology(&self) -> Result<ClusterTopology> {
+        let (buckets, indexes) = tokio::join!(self.list_buckets(), self.list_indexes());
+        let buckets = buckets?;
+        let (indexes, indexes_error) = match indexes {
+            Ok(indexes) => (indexes, None),
+            Err(e) => {
+                warn!("Failed to list indexes for the topology: {}", e);
+                (Vec::new(), Some(e.to_string()))
             }
+        };
+
+        let mut indexes_by_bucket: HashMap<String, Vec<IndexInfo>> = HashMap::new();
+        for index in indexes {
+            indexes_by_bucket.entry(index.bucket.clone()).or_default().push(index);
         }
-        
-        // Join all roles with comma separation
-        if !role_strings.is_empty() {
-            params.push(("roles".to_string(), role_strings.join(",")));
-        }
 
-        let response = self
+        let buckets = stream::iter(buckets)
+            .map(|bucket| {
+                let indexes = indexes_by_bucket.remove(&bucket.name).unwrap_or_default();
+                async move {
+                    let manifest = self.get_collection_manifest(&bucket.name).await?;
+                    Ok::<_, AppError>(BucketTopology {
+                        bucket,
+                        manifest_uid: manifest.uid,
+                        scopes: manifest.scopes,
+                        indexes,
+                    })
+                }
+            })
+            .buffered(self.topology_concurrency)
+            .try_collect()
+            .await?;
+
+        Ok(ClusterTopology {
+            generated_at: chrono::Utc::now(),
+            buckets,
+            indexes_error,
+        })
+    }
+
+    // Index Management
+    /// Run a N1QL statement on the query service and return its results
+    async fn execute_statement(&self, statement: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/query/service", self.query_url);
+
+        let request = self
             .client
-            .put(&url)
+            .post(&url)
             .basic_auth(&self.username, Some(&self.password))
-            .form(&params)
-            .send()
-            .await?;
+            .form(&[("statement", statement)]);
+        let response = self.send(request).await?;
 
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        let status = response.status().as_u16();
+        let body: serde_json::Value = response.json().await?;
+        if let Some(error) = body["errors"].as_array().and_then(|errors| errors.first()) {
+            return Err(query_error(error, status));
+        }
+        if !(200..300).contains(&status) {
             return Err(AppError::CouchbaseApi {
-                message: error_text,
+                message: body.to_string(),
                 status,
             });
         }
 
-        Ok(())
+        Ok(body["results"].clone())
     }
 
-    pub async fn list_users(&self) -> Result<Vec<UserInfo>> {
-        let url = format!("{}/settings/rbac/users", self.base_url);
-        
-        let response = self
-            .client
-            .get(&url)
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await?;
+    /// Submit a query and return the response once the query service has accepted it,
+    /// leaving the body to be streamed. `authorization` is the caller's own Couchbase
+    /// `Authorization` header; without one the configured query account is used. Queries
+    /// never run as the service's admin account, so with neither they are refused.
+    pub async fn start_query(
+        &self,
+        body: &serde_json::Value,
+        authorization: Option<&str>,
+        timeout: Duration,
+    ) -> Result<Response> {
+        let url = format!("{}/query/service", self.query_url);
+
+        // Leave the query service time to report its own timeout
+        let request = self.client.post(&url).json(body).timeout(timeout + Duration::from_secs(5));
+        let request = match (authorization, &self.query_account) {
+            (Some(authorization), _) => request.header(reqwest::header::AUTHORIZATION, authorization),
+            (None, Some((username, password))) => request.basic_auth(username, Some(password)),
+            (None, None) => {
+                return Err(AppError::Auth(
+                    "Queries need Couchbase credentials in an X-Couchbase-Authorization header, \
+                     or a QUERY_USERNAME account configured for the service"
+                        .to_string(),
+                ))
+            }
+        };
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(AppError::CouchbaseApi {
-                message: error_text,
-                status,
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            return Err(match body["errors"].as_array().and_then(|errors| errors.first()) {
+                Some(error) => query_error(error, status),
+                None => AppError::CouchbaseApi {
+                    message: body.to_string(),
+                    status,
+                },
             });
         }
 
-        let users_data: Vec<serde_json::Value> = response.json().await?;
-        let user_infos: Result<Vec<UserInfo>> = users_data
-            .into_iter()
-            .map(|user| {
-                let roles: Result<Vec<Role>> = user["roles"]
-                    .as_array()
-                    .unwrap_or(&vec![])
-                    .iter()
-                    .map(|role| {
-                        Ok(Role {
-                            role: role["role"].as_str().unwrap_or("").to_string(),
-                            bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
-                            scope: role["scope_name"].as_str().map(|s| s.to_string()),
-                            collection: role["collection_name"].as_str().map(|s| s.to_string()),
-                        })
-                    })
-                    .collect();
-
-                Ok(UserInfo {
-                    username: user["id"].as_str().unwrap_or("").to_string(),
-                    roles: roles?,
-                    groups: user["groups"]
-                        .as_array()
-                        .unwrap_or(&vec![])
-                        .iter()
-                        .map(|g| g.as_str().unwrap_or("").to_string())
-                        .collect(),
-                })
-            })
-            .collect();
-
-        user_infos
+        Ok(response)
     }
 
-    pub async fn get_user(&self, username: &str) -> Result<UserInfo> {
-        let url = format!("{}/settings/rbac/users/local/{}", self.base_url, username);
-        
-        let response = self
+    /// Every GSI index in the cluster, from `/indexStatus`
+    pub async fn list_indexes(&self) -> Result<Vec<IndexInfo>> {
+        let url = format!("{}/indexStatus", self.base_url);
+
+        let request = self
             .client
             .get(&url)
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await?;
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
-            if status == 404 {
-                return Err(AppError::NotFound(format!("User '{}' not found", username)));
-            }
             let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
             return Err(AppError::CouchbaseApi {
                 message: error_text,
@@ -374,139 +722,2738 @@ impl CouchbaseService {
             });
         }
 
-        let user: serde_json::Value = response.json().await?;
-        
-        // Debug: Print the raw response to understand the structure
-        println!("Raw Couchbase API response for user {}: {}", username, serde_json::to_string_pretty(&user).unwrap_or_else(|_| "Failed to serialize".to_string()));
-        
-        let roles: Result<Vec<Role>> = user["roles"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|role| {
-                // Handle different role formats that Couchbase might return
-                if role.is_string() {
-                    // Simple string role (e.g., "cluster_admin")
-                    Ok(Role {
-                        role: role.as_str().unwrap_or("").to_string(),
-                        bucket: None,
-                        scope: None,
-                        collection: None,
-                    })
-                } else if role.is_object() {
-                    // Object role with bucket/scope/collection info
-                    Ok(Role {
-                        role: role["role"].as_str().unwrap_or("").to_string(),
-                        bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
-                        scope: role["scope_name"].as_str().map(|s| s.to_string()),
-                        collection: role["collection_name"].as_str().map(|s| s.to_string()),
-                    })
-                } else {
-                    // Fallback - try to extract role name from any field
-                    let role_str = role["role"].as_str()
-                        .or_else(|| role["name"].as_str())
-                        .or_else(|| role.as_str())
-                        .unwrap_or("unknown");
-                    
-                    Ok(Role {
-                        role: role_str.to_string(),
-                        bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
-                        scope: role["scope_name"].as_str().map(|s| s.to_string()),
-                        collection: role["collection_name"].as_str().map(|s| s.to_string()),
-                    })
-                }
+        let body: serde_json::Value = response.json().await?;
+        let statuses = body["indexes"].as_array().map(Vec::as_slice).unwrap_or_default();
+        Ok(index_infos(statuses))
+    }
+
+    pub async fn list_collection_indexes(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        collection_name: &str,
+    ) -> Result<Vec<IndexInfo>> {
+        Ok(self
+            .list_indexes()
+            .await?
+            .into_iter()
+            .filter(|index| {
+                index.bucket == bucket_name
+                    && index.scope == scope_name
+                    && index.collection == collection_name
             })
-            .collect();
+            .collect())
+    }
 
-        Ok(UserInfo {
-            username: user["id"].as_str().unwrap_or("").to_string(),
-            roles: roles?,
-            groups: user["groups"]
-                .as_array()
-                .unwrap_or(&vec![])
-                .iter()
-                .map(|g| g.as_str().unwrap_or("").to_string())
+    pub async fn create_index(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        collection_name: &str,
+        request: &CreateIndexRequest,
+    ) -> Result<String> {
+        let statement = create_index_statement(&keyspace(bucket_name, scope_name, collection_name), request);
+        self.execute_statement(&statement).await?;
+        info!(bucket_name, scope_name, collection_name, index = request.name(), "Created index");
+        Ok(statement)
+    }
+
+    /// Create indexes on a collection that was just created. The query service can take
+    /// a moment to see a new collection, so creates are retried while it is not found.
+    /// If an index cannot be created the collection is dropped again, so a failed
+    /// create leaves nothing behind.
+    pub async fn provision_indexes(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        collection_name: &str,
+        requests: &[CreateIndexRequest],
+    ) -> Result<()> {
+        let result = self
+            .create_new_collection_indexes(bucket_name, scope_name, collection_name, requests)
+            .await;
+        if result.is_err() {
+            if let Err(e) = self.delete_collection(bucket_name, scope_name, collection_name).await {
+                warn!(
+                    bucket_name,
+                    scope_name,
+                    collection_name,
+                    "Failed to drop collection after index creation failed: {}",
+                    e
+                );
+            }
+        }
+        result
+    }
+
+    async fn create_new_collection_indexes(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        collection_name: &str,
+        requests: &[CreateIndexRequest],
+    ) -> Result<()> {
+        for request in requests {
+            let mut attempt = 1;
+            loop {
+                match self.create_index(bucket_name, scope_name, collection_name, request).await {
+                    Err(AppError::NotFound(_)) if attempt < INDEX_PROVISION_ATTEMPTS => {
+                        attempt += 1;
+                        tokio::time::sleep(Duration::from_millis(500)).await;
+                    }
+                    result => {
+                        result?;
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn drop_index(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        collection_name: &str,
+        index_name: &str,
+    ) -> Result<()> {
+        let keyspace = keyspace(bucket_name, scope_name, collection_name);
+        let statement = if index_name == PRIMARY_INDEX_NAME {
+            format!("DROP PRIMARY INDEX ON {}", keyspace)
+        } else {
+            format!("DROP INDEX {} ON {}", quote_identifier(index_name), keyspace)
+        };
+        self.execute_statement(&statement).await?;
+        info!(bucket_name, scope_name, collection_name, index = index_name, "Dropped index");
+        Ok(())
+    }
+
+    /// Build deferred indexes; all not-yet-built indexes on the collection when
+    /// `index_names` is `None`. Returns the names of the indexes being built.
+    pub async fn build_indexes(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        collection_name: &str,
+        index_names: Option<Vec<String>>,
+    ) -> Result<Vec<String>> {
+        let index_names = match index_names {
+            Some(names) => names,
+            None => self
+                .list_collection_indexes(bucket_name, scope_name, collection_name)
+                .await?
+                .into_iter()
+                .filter(|index| index.status == DEFERRED_INDEX_STATUS)
+                .map(|index| index.name)
                 .collect(),
-        })
+        };
+        if index_names.is_empty() {
+            return Ok(index_names);
+        }
+
+        let names: Vec<String> = index_names.iter().map(|name| quote_identifier(name)).collect();
+        let statement = format!(
+            "BUILD INDEX ON {}({})",
+            keyspace(bucket_name, scope_name, collection_name),
+            names.join(", ")
+        );
+        self.execute_statement(&statement).await?;
+        Ok(index_names)
     }
 
-    pub async fn delete_user(&self, username: &str) -> Result<()> {
-        let url = format!("{}/settings/rbac/users/local/{}", self.base_url, username);
-        
-        let response = self
-            .client
-            .delete(&url)
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
+    // Search Index Management
+    fn search_index_url(&self, bucket_name: &str, scope_name: &str) -> String {
+        format!(
+            "{}/api/bucket/{}/scope/{}/index",
+            self.search_url,
+            encode_path_segment(bucket_name),
+            encode_path_segment(scope_name)
+        )
+    }
+
+    fn search_index_item_url(&self, bucket_name: &str, scope_name: &str, index_name: &str) -> String {
+        format!(
+            "{}/{}",
+            self.search_index_url(bucket_name, scope_name),
+            encode_path_segment(index_name)
+        )
+    }
+
+    /// Call the search service, which reports failures as `{"status": "fail", "error": ...}`
+    async fn search_api(&self, request: RequestBuilder) -> Result<serde_json::Value> {
+        let request = request.basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        let body = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+        if !(200..300).contains(&status) || body["status"] == "fail" {
+            return Err(search_error(&body, status));
+        }
+
+        Ok(body)
+    }
+
+    // The definition as stored by the search service, including its `uuid`
+    async fn get_search_index_definition(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        index_name: &str,
+    ) -> Result<serde_json::Value> {
+        let url = self.search_index_item_url(bucket_name, scope_name, index_name);
+        match self.search_api(self.client.get(&url)).await {
+            Ok(body) => Ok(body["indexDef"].clone()),
+            Err(AppError::NotFound(_)) => Err(AppError::NotFound(format!(
+                "Search index '{}' not found in scope '{}' of bucket '{}'",
+                index_name, scope_name, bucket_name
+            ))),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Status and document count from the index's stats; `Unknown` and `None` when
+    /// the search service has none, e.g. right after the index was created
+    async fn search_index_stats(&self, full_name: &str) -> (String, Option<u64>) {
+        let url = format!("{}/api/nsstats/index/{}", self.search_url, encode_path_segment(full_name));
+        let stats = match self.search_api(self.client.get(&url)).await {
+            Ok(stats) if stats.is_object() => stats,
+            Ok(_) => return ("Unknown".to_string(), None),
+            Err(e) => {
+                debug!(index = full_name, error = %e, "No search index stats");
+                return ("Unknown".to_string(), None);
+            }
+        };
+
+        let pending = stats["num_mutations_to_index"].as_u64();
+        let partitions_missing = match (stats["num_pindexes_actual"].as_u64(), stats["num_pindexes_target"].as_u64()) {
+            (Some(actual), Some(target)) => actual < target,
+            _ => false,
+        };
+        let status = match pending {
+            Some(0) if !partitions_missing => "Ready",
+            Some(_) => "Building",
+            None => "Unknown",
+        };
+
+        (status.to_string(), stats["doc_count"].as_u64())
+    }
+
+    async fn search_index_info(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        definition: &serde_json::Value,
+    ) -> SearchIndexInfo {
+        let full_name = definition["name"].as_str().unwrap_or_default();
+        let name = full_name
+            .strip_prefix(&format!("{}.{}.", bucket_name, scope_name))
+            .unwrap_or(full_name);
+        let (status, doc_count) = self.search_index_stats(full_name).await;
+
+        SearchIndexInfo {
+            name: name.to_string(),
+            bucket: bucket_name.to_string(),
+            scope: scope_name.to_string(),
+            status,
+            doc_count,
+            definition: portable_search_definition(name, definition),
+        }
+    }
+
+    pub async fn list_search_indexes(&self, bucket_name: &str, scope_name: &str) -> Result<Vec<SearchIndexInfo>> {
+        let url = self.search_index_url(bucket_name, scope_name);
+        let body = self.search_api(self.client.get(&url)).await?;
+
+        // `indexDefs` is null when the scope has no search indexes
+        let definitions: Vec<serde_json::Value> = body["indexDefs"]["indexDefs"]
+            .as_object()
+            .map(|definitions| definitions.values().cloned().collect())
+            .unwrap_or_default();
+
+        Ok(stream::iter(definitions)
+            .map(|definition| async move { self.search_index_info(bucket_name, scope_name, &definition).await })
+            .buffered(self.topology_concurrency)
+            .collect()
+            .await)
+    }
+
+    pub async fn get_search_index(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        index_name: &str,
+    ) -> Result<SearchIndexInfo> {
+        let definition = self
+            .get_search_index_definition(bucket_name, scope_name, index_name)
             .await?;
+        Ok(self.search_index_info(bucket_name, scope_name, &definition).await)
+    }
+
+    /// Create the index, or replace the definition of an existing one
+    pub async fn upsert_search_index(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        index_name: &str,
+        definition: &SearchIndexDefinition,
+    ) -> Result<()> {
+        let existing = match self
+            .get_search_index_definition(bucket_name, scope_name, index_name)
+            .await
+        {
+            Ok(existing) => Some(existing),
+            Err(AppError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut body = serde_json::json!({
+            "type": definition.index_type,
+            "name": index_name,
+            "sourceType": SEARCH_SOURCE_TYPE,
+            "sourceName": bucket_name,
+            "params": definition.params,
+        });
+        if let Some(plan_params) = &definition.plan_params {
+            body["planParams"] = plan_params.clone();
+        }
+        if let Some(source_params) = &definition.source_params {
+            body["sourceParams"] = source_params.clone();
+        }
+        // Updates must name the definition they replace
+        if let Some(uuid) = existing.as_ref().and_then(|existing| existing["uuid"].as_str()) {
+            body["uuid"] = uuid.into();
+        }
+
+        let url = self.search_index_item_url(bucket_name, scope_name, index_name);
+        self.search_api(self.client.put(&url).json(&body)).await?;
+
+        let created = existing.is_none();
+        info!(bucket_name, scope_name, index = index_name, created, "Applied search index definition");
+        Ok(())
+    }
+
+    pub async fn delete_search_index(&self, bucket_name: &str, scope_name: &str, index_name: &str) -> Result<()> {
+        let url = self.search_index_item_url(bucket_name, scope_name, index_name);
+        match self.search_api(self.client.delete(&url)).await {
+            Ok(_) => {}
+            Err(AppError::NotFound(_)) => {
+                return Err(AppError::NotFound(format!(
+                    "Search index '{}' not found in scope '{}' of bucket '{}'",
+                    index_name, scope_name, bucket_name
+                )))
+            }
+            Err(e) => return Err(e),
+        }
+        info!(bucket_name, scope_name, index = index_name, "Deleted search index");
+        Ok(())
+    }
+
+    /// Run a search request against the index and return the search service's result
+    pub async fn search(
+        &self,
+        bucket_name: &str,
+        scope_name: &str,
+        index_name: &str,
+        request: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let url = format!("{}/query", self.search_index_item_url(bucket_name, scope_name, index_name));
+        self.search_api(self.client.post(&url).json(request)).await
+    }
+
+    // XDCR
+    pub async fn list_remote_clusters(&self) -> Result<Vec<RemoteClusterInfo>> {
+        let url = format!("{}/pools/default/remoteClusters", self.base_url);
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
-            if status == 404 {
-                return Err(AppError::NotFound(format!("User '{}' not found", username)));
-            }
             let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(AppError::CouchbaseApi {
-                message: error_text,
-                status,
-            });
+            return Err(xdcr_error(&error_text, status));
         }
 
-        Ok(())
+        let references: Vec<serde_json::Value> = response.json().await?;
+        Ok(references
+            .iter()
+            .filter(|reference| reference["deleted"].as_bool() != Some(true))
+            .map(remote_cluster_info)
+            .collect())
     }
 
-    pub async fn update_user(&self, request: &CouchbaseUserConfig) -> Result<()> {
-        let url = format!("{}/settings/rbac/users/local/{}", self.base_url, request.name);
-        
-        let mut params: Vec<(String, String)> = vec![
-            ("name".to_string(), request.name.clone()),
-            ("password".to_string(), request.password.clone()),
-        ];
+    pub async fn create_remote_cluster(&self, request: &CreateRemoteClusterRequest) -> Result<RemoteClusterInfo> {
+        let url = format!("{}/pools/default/remoteClusters", self.base_url);
 
-        // Add roles - Couchbase expects simple role names for basic roles
-        for role in &request.roles {
-            if role.bucket_name.is_none() && role.scope_name.is_none() && role.collection_name.is_none() {
-                // Global role
-                params.push(("roles".to_string(), role.role.clone()));
-            } else {
-                // Bucket/scope/collection specific role - format as "role[bucket:scope:collection]"
-                let role_spec = if let Some(bucket) = &role.bucket_name {
-                    let mut spec = format!("{}[{}", role.role, bucket);
-                    if let Some(scope) = &role.scope_name {
-                        spec.push_str(&format!(":{}", scope));
-                        if let Some(collection) = &role.collection_name {
-                            spec.push_str(&format!(":{}", collection));
-                        }
-                    }
-                    spec.push(']');
-                    spec
-                } else {
-                    role.role.clone()
-                };
-                params.push(("roles".to_string(), role_spec));
-            }
+        let mut params: Vec<(&str, String)> = vec![
+            ("name", request.name.clone()),
+            ("hostname", request.hostname.clone()),
+            ("username", request.username.clone()),
+            ("password", request.password.clone()),
+            (
+                "demandEncryption",
+                u8::from(request.encryption != XdcrEncryption::None).to_string(),
+            ),
+        ];
+        if request.encryption != XdcrEncryption::None {
+            params.push(("encryptionType", request.encryption.as_str().to_string()));
+        }
+        if let Some(certificate) = &request.certificate {
+            params.push(("certificate", certificate.clone()));
         }
 
-        let response = self
+        let http_request = self
             .client
-            .put(&url)
+            .post(&url)
             .basic_auth(&self.username, Some(&self.password))
-            .form(&params)
-            .send()
-            .await?;
+            .form(&params);
+        let response = self.send(http_request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(xdcr_error(&error_text, status));
+        }
+
+        let reference: serde_json::Value = response.json().await?;
+        info!(name = %request.name, hostname = %request.hostname, "Created remote cluster reference");
+        Ok(remote_cluster_info(&reference))
+    }
+
+    pub async fn delete_remote_cluster(&self, name: &str) -> Result<()> {
+        let url = format!(
+            "{}/pools/default/remoteClusters/{}",
+            self.base_url,
+            encode_path_segment(name)
+        );
+
+        let request = self
+            .client
+            .delete(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
             if status == 404 {
-                return Err(AppError::NotFound(format!("User '{}' not found", request.name)));
+                return Err(AppError::NotFound(format!("Remote cluster '{}' not found", name)));
             }
             let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(xdcr_error(&error_text, status));
+        }
+
+        info!(name, "Deleted remote cluster reference");
+        Ok(())
+    }
+
+    // Replication tasks from `/pools/default/tasks`
+    async fn xdcr_tasks(&self) -> Result<Vec<serde_json::Value>> {
+        let url = format!("{}/pools/default/tasks", self.base_url);
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
             return Err(AppError::CouchbaseApi {
                 message: error_text,
                 status,
             });
         }
 
-        Ok(())
+        let tasks: Vec<serde_json::Value> = response.json().await?;
+        Ok(tasks.into_iter().filter(|task| task["type"] == "xdcr").collect())
+    }
+
+    async fn get_replication_settings(&self, id: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/settings/replications/{}", self.base_url, encode_path_segment(id));
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("Replication '{}' not found", id)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(xdcr_error(&error_text, status));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Latest weighted average replication latency from the stats service, if any
+    async fn replication_latency_ms(&self, remote_cluster_uuid: &str, source_bucket: &str, target_bucket: &str) -> Option<f64> {
+        let url = format!(
+            "{}/pools/default/stats/range/xdcr_wtavg_docs_latency_seconds",
+            self.base_url
+        );
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .query(&[
+                ("sourceBucketName", source_bucket),
+                ("targetClusterUUID", remote_cluster_uuid),
+                ("targetBucketName", target_bucket),
+                ("pipelineType", "Main"),
+                ("nodesAggregation", "max"),
+                ("start", "-60"),
+                ("step", "10"),
+            ]);
+        let response = match self.send(request).await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return None,
+        };
+
+        let body: serde_json::Value = response.json().await.ok()?;
+        let seconds: f64 = body["data"][0]["values"]
+            .as_array()?
+            .last()?[1]
+            .as_str()?
+            .parse()
+            .ok()?;
+        Some(seconds * 1000.0)
+    }
+
+    async fn replication_info(
+        &self,
+        task: &serde_json::Value,
+        remote_clusters: &[RemoteClusterInfo],
+    ) -> Result<ReplicationInfo> {
+        let id = task["id"].as_str().unwrap_or_default();
+        let mut parts = id.splitn(3, '/');
+        let (remote_cluster_uuid, source_bucket, target_bucket) = (
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+        );
+
+        let (settings, latency_ms) = tokio::join!(
+            self.get_replication_settings(id),
+            self.replication_latency_ms(remote_cluster_uuid, source_bucket, target_bucket)
+        );
+        let settings = settings?;
+
+        let errors = task["errors"]
+            .as_array()
+            .map(|errors| {
+                errors
+                    .iter()
+                    .map(|error| {
+                        error
+                            .as_str()
+                            .or(error["ErrorMsg"].as_str())
+                            .map_or_else(|| error.to_string(), str::to_string)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(ReplicationInfo {
+            id: id.to_string(),
+            source_bucket: source_bucket.to_string(),
+            remote_cluster: remote_clusters
+                .iter()
+                .find(|cluster| cluster.uuid == remote_cluster_uuid)
+                .map(|cluster| cluster.name.clone()),
+            remote_cluster_uuid: remote_cluster_uuid.to_string(),
+            target_bucket: target_bucket.to_string(),
+            status: task["status"].as_str().unwrap_or("unknown").to_string(),
+            filter_expression: settings["filterExpression"]
+                .as_str()
+                .filter(|expression| !expression.is_empty())
+                .map(str::to_string),
+            collection_mappings: collection_mappings(&settings),
+            stats: ReplicationStats {
+                changes_left: task["changesLeft"].as_u64(),
+                docs_checked: task["docsChecked"].as_u64(),
+                latency_ms,
+            },
+            errors,
+        })
+    }
+
+    pub async fn list_replications(&self) -> Result<Vec<ReplicationInfo>> {
+        let (tasks, remote_clusters) = tokio::try_join!(self.xdcr_tasks(), self.list_remote_clusters())?;
+
+        let remote_clusters = &remote_clusters;
+        stream::iter(tasks)
+            .map(|task| async move { self.replication_info(&task, remote_clusters).await })
+            .buffered(self.topology_concurrency)
+            .try_collect()
+            .await
+    }
+
+    pub async fn get_replication(&self, id: &str) -> Result<ReplicationInfo> {
+        let (tasks, remote_clusters) = tokio::try_join!(self.xdcr_tasks(), self.list_remote_clusters())?;
+        let task = tasks
+            .iter()
+            .find(|task| task["id"] == id)
+            .ok_or_else(|| AppError::NotFound(format!("Replication '{}' not found", id)))?;
+
+        self.replication_info(task, &remote_clusters).await
+    }
+
+    /// Start a continuous replication and return its id
+    pub async fn create_replication(&self, request: &CreateReplicationRequest) -> Result<String> {
+        let url = format!("{}/controller/createReplication", self.base_url);
+
+        let mut params: Vec<(&str, String)> = vec![
+            ("fromBucket", request.source_bucket.clone()),
+            ("toCluster", request.remote_cluster.clone()),
+            ("toBucket", request.target_bucket.clone()),
+            ("replicationType", "continuous".to_string()),
+        ];
+        if !request.collection_mappings.is_empty() {
+            params.push(("collectionsExplicitMapping", "true".to_string()));
+            params.push((
+                "colMappingRules",
+                serde_json::to_string(&request.collection_mappings)?,
+            ));
+        }
+
+        let optional = [
+            ("filterExpression", request.filter_expression.clone()),
+            ("filterExpiration", request.filter_expirations.map(|v| v.to_string())),
+            ("filterDeletion", request.filter_deletions.map(|v| v.to_string())),
+            ("priority", request.priority.map(|p| p.as_str().to_string())),
+        ];
+        params.extend(optional.into_iter().filter_map(|(key, value)| Some((key, value?))));
+
+        let http_request = self
+            .client
+            .post(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .form(&params);
+        let response = self.send(http_request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(xdcr_error(&error_text, status));
+        }
+
+        let body: serde_json::Value = response.json().await?;
+        let id = body["id"].as_str().unwrap_or_default().to_string();
+        info!(
+            replication = %id,
+            source_bucket = %request.source_bucket,
+            remote_cluster = %request.remote_cluster,
+            target_bucket = %request.target_bucket,
+            "Created replication"
+        );
+        Ok(id)
+    }
+
+    pub async fn set_replication_paused(&self, id: &str, paused: bool) -> Result<()> {
+        let url = format!("{}/settings/replications/{}", self.base_url, encode_path_segment(id));
+
+        let request = self
+            .client
+            .post(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .form(&[("pauseRequested", paused.to_string())]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("Replication '{}' not found", id)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(xdcr_error(&error_text, status));
+        }
+
+        info!(replication = id, paused, "Changed replication state");
+        Ok(())
+    }
+
+    pub async fn delete_replication(&self, id: &str) -> Result<()> {
+        let url = format!("{}/controller/cancelXDCR/{}", self.base_url, encode_path_segment(id));
+
+        let request = self
+            .client
+            .delete(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("Replication '{}' not found", id)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(xdcr_error(&error_text, status));
+        }
+
+        info!(replication = id, "Deleted replication");
+        Ok(())
+    }
+
+    // Eventing
+    async fn eventing_api(&self, request: RequestBuilder) -> Result<serde_json::Value> {
+        let request = request.basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        let body = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+        if !(200..300).contains(&status) {
+            return Err(eventing_error(&body, status));
+        }
+
+        Ok(body)
+    }
+
+    /// Deployment status of every function, from `/api/v1/status`
+    async fn eventing_statuses(&self) -> Result<Vec<serde_json::Value>> {
+        let url = format!("{}/api/v1/status", self.eventing_url);
+        let body = self.eventing_api(self.client.get(&url)).await?;
+        Ok(body["apps"].as_array().cloned().unwrap_or_default())
+    }
+
+    pub async fn list_eventing_functions(&self) -> Result<Vec<EventingFunctionInfo>> {
+        let url = format!("{}/api/v1/functions", self.eventing_url);
+        let (functions, statuses) =
+            tokio::try_join!(self.eventing_api(self.client.get(&url)), self.eventing_statuses())?;
+
+        functions
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|function| eventing_function_info(function, &statuses))
+            .collect()
+    }
+
+    pub async fn get_eventing_function(&self, name: &str) -> Result<EventingFunctionInfo> {
+        let url = format!("{}/api/v1/functions/{}", self.eventing_url, encode_path_segment(name));
+        let (function, statuses) = match tokio::try_join!(
+            self.eventing_api(self.client.get(&url)),
+            self.eventing_statuses()
+        ) {
+            Err(AppError::NotFound(_)) => {
+                return Err(AppError::NotFound(format!("Eventing function '{}' not found", name)))
+            }
+            result => result?,
+        };
+
+        eventing_function_info(&function, &statuses)
+    }
+
+    /// Create the function, or replace the definition of an undeployed one. The
+    /// function is left undeployed.
+    pub async fn import_eventing_function(&self, name: &str, definition: &EventingFunctionDefinition) -> Result<()> {
+        let mut body = serde_json::to_value(definition)?;
+        body["appname"] = name.into();
+        for setting in EVENTING_STATE_SETTINGS {
+            body["settings"][setting] = false.into();
+        }
+
+        let url = format!("{}/api/v1/functions/{}", self.eventing_url, encode_path_segment(name));
+        self.eventing_api(self.client.post(&url).json(&body)).await?;
+
+        info!(function = name, "Imported eventing function");
+        Ok(())
+    }
+
+    pub async fn change_eventing_function_state(&self, name: &str, action: EventingLifecycleAction) -> Result<()> {
+        let url = format!(
+            "{}/api/v1/functions/{}/{}",
+            self.eventing_url,
+            encode_path_segment(name),
+            action.as_str()
+        );
+        match self.eventing_api(self.client.post(&url)).await {
+            Ok(_) => {}
+            Err(AppError::NotFound(_)) => {
+                return Err(AppError::NotFound(format!("Eventing function '{}' not found", name)))
+            }
+            Err(e) => return Err(e),
+        }
+
+        info!(function = name, action = action.as_str(), "Changed eventing function state");
+        Ok(())
+    }
+
+    pub async fn delete_eventing_function(&self, name: &str) -> Result<()> {
+        let url = format!("{}/api/v1/functions/{}", self.eventing_url, encode_path_segment(name));
+        match self.eventing_api(self.client.delete(&url)).await {
+            Ok(_) => {}
+            Err(AppError::NotFound(_)) => {
+                return Err(AppError::NotFound(format!("Eventing function '{}' not found", name)))
+            }
+            Err(e) => return Err(e),
+        }
+
+        info!(function = name, "Deleted eventing function");
+        Ok(())
+    }
+
+    // Analytics
+    /// Run a statement on the analytics service and return its results
+    async fn execute_analytics_statement(&self, statement: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/analytics/service", self.analytics_url);
+        let request = self.client.post(&url).form(&[("statement", statement)]);
+        Ok(self.analytics_api(request).await?["results"].clone())
+    }
+
+    async fn analytics_api(&self, request: RequestBuilder) -> Result<serde_json::Value> {
+        let request = request.basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        let body = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+        if let Some(error) = body["errors"].as_array().and_then(|errors| errors.first()) {
+            return Err(analytics_error(error, status));
+        }
+        if !(200..300).contains(&status) {
+            let message = body.as_str().map_or_else(|| body.to_string(), str::to_string);
+            return Err(if status == 404 {
+                AppError::NotFound(message)
+            } else {
+                AppError::CouchbaseApi { message, status }
+            });
+        }
+
+        Ok(body)
+    }
+
+    fn analytics_link_url(&self, scope_name: &str) -> String {
+        format!("{}/analytics/link/{}", self.analytics_url, encode_path_segment(scope_name))
+    }
+
+    pub async fn list_analytics_scopes(&self) -> Result<Vec<AnalyticsScopeInfo>> {
+        let statement = format!(
+            "SELECT VALUE d.DataverseName FROM Metadata.`Dataverse` d WHERE d.DataverseName <> {}",
+            serde_json::Value::from(validation::ANALYTICS_METADATA_SCOPE)
+        );
+        let names = self.execute_analytics_statement(&statement).await?;
+
+        Ok(names
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|name| name.as_str())
+            .map(|name| AnalyticsScopeInfo { name: name.to_string() })
+            .collect())
+    }
+
+    pub async fn create_analytics_scope(&self, scope_name: &str) -> Result<()> {
+        let statement = format!("CREATE ANALYTICS SCOPE {}", quote_identifier(scope_name));
+        self.execute_analytics_statement(&statement).await?;
+        info!(scope_name, "Created analytics scope");
+        Ok(())
+    }
+
+    pub async fn drop_analytics_scope(&self, scope_name: &str) -> Result<()> {
+        let statement = format!("DROP ANALYTICS SCOPE {}", quote_identifier(scope_name));
+        self.execute_analytics_statement(&statement).await?;
+        info!(scope_name, "Dropped analytics scope");
+        Ok(())
+    }
+
+    /// Ingestion progress of every analytics collection, by the link feeding it
+    pub async fn analytics_ingestion(&self) -> Result<Vec<AnalyticsIngestionStatus>> {
+        let url = format!("{}/analytics/status/ingestion", self.analytics_url);
+        let body = self.analytics_api(self.client.get(&url)).await?;
+        Ok(ingestion_statuses(&body))
+    }
+
+    pub async fn list_analytics_collections(&self, scope_name: &str) -> Result<Vec<AnalyticsCollectionInfo>> {
+        let statement = format!(
+            "SELECT VALUE d FROM Metadata.`Dataset` d WHERE d.DataverseName = {}",
+            serde_json::Value::from(scope_name)
+        );
+        let (datasets, ingestion) = tokio::join!(
+            self.execute_analytics_statement(&statement),
+            self.analytics_ingestion()
+        );
+        let datasets = datasets?;
+        // Ingestion status is informative; collections are listed without it if it is unavailable
+        let ingestion = ingestion.unwrap_or_else(|e| {
+            debug!(error = %e, "No analytics ingestion status");
+            Vec::new()
+        });
+
+        let mut collections = Vec::new();
+        let datasets = datasets.as_array().map(Vec::as_slice).unwrap_or_default();
+        for mut collection in datasets.iter().filter_map(analytics_collection_info) {
+            collection.ingestion = ingestion
+                .iter()
+                .find(|status| status.scope == collection.scope && status.collection == collection.name)
+                .cloned();
+
+            // Settings of the source are only known when it is in this cluster
+            if collection.link == LOCAL_ANALYTICS_LINK {
+                if let Ok(manifest) = self.get_collection_manifest(&collection.bucket).await {
+                    if let Some(source) = manifest
+                        .scopes
+                        .iter()
+                        .find(|scope| scope.name == collection.source.scope)
+                        .and_then(|scope| scope.collections.iter().find(|c| c.name == collection.source.name))
+                    {
+                        collection.source = source.clone();
+                    }
+                }
+            }
+            collections.push(collection);
+        }
+
+        Ok(collections)
+    }
+
+    /// Returns the statement that created the collection
+    pub async fn create_analytics_collection(
+        &self,
+        scope_name: &str,
+        request: &CreateAnalyticsCollectionRequest,
+    ) -> Result<String> {
+        let mut statement = format!(
+            "CREATE ANALYTICS COLLECTION {}.{} ON {} AT {}",
+            quote_identifier(scope_name),
+            quote_identifier(&request.collection_name),
+            keyspace(&request.source.bucket, &request.source.scope, &request.source.collection),
+            quote_identifier(&request.link_name)
+        );
+        if let Some(filter) = &request.filter {
+            statement.push_str(&format!(" WHERE {}", filter));
+        }
+
+        self.execute_analytics_statement(&statement).await?;
+        info!(scope_name, collection = %request.collection_name, "Created analytics collection");
+        Ok(statement)
+    }
+
+    pub async fn drop_analytics_collection(&self, scope_name: &str, collection_name: &str) -> Result<()> {
+        let statement = format!(
+            "DROP ANALYTICS COLLECTION {}.{}",
+            quote_identifier(scope_name),
+            quote_identifier(collection_name)
+        );
+        self.execute_analytics_statement(&statement).await?;
+        info!(scope_name, collection = collection_name, "Dropped analytics collection");
+        Ok(())
+    }
+
+    /// The scope's links, starting with the built-in `Local` link. Secrets are redacted.
+    pub async fn list_analytics_links(&self, scope_name: &str) -> Result<Vec<AnalyticsLinkInfo>> {
+        let body = self
+            .analytics_api(self.client.get(self.analytics_link_url(scope_name)))
+            .await?;
+
+        let mut links = vec![AnalyticsLinkInfo {
+            name: LOCAL_ANALYTICS_LINK.to_string(),
+            scope: scope_name.to_string(),
+            settings: AnalyticsLinkSettings::Local,
+        }];
+        links.extend(
+            body.as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|link| analytics_link_info(scope_name, link)),
+        );
+        Ok(links)
+    }
+
+    pub async fn create_analytics_link(&self, scope_name: &str, request: &CreateAnalyticsLinkRequest) -> Result<()> {
+        let mut params: Vec<(&str, String)> = Vec::new();
+        match &request.settings {
+            AnalyticsLinkSettings::Local => {
+                return Err(AppError::Validation("Local links cannot be created".to_string()))
+            }
+            AnalyticsLinkSettings::Couchbase {
+                hostname,
+                username,
+                password,
+                encryption,
+                certificate,
+            } => {
+                params.push(("type", "couchbase".to_string()));
+                params.push(("hostname", hostname.clone()));
+                params.push(("username", username.clone()));
+                params.push(("password", password.clone()));
+                params.push(("encryption", encryption.as_str().to_string()));
+                if let Some(certificate) = certificate {
+                    params.push(("certificate", certificate.clone()));
+                }
+            }
+            AnalyticsLinkSettings::S3 {
+                access_key_id,
+                secret_access_key,
+                session_token,
+                region,
+                service_endpoint,
+            } => {
+                params.push(("type", "s3".to_string()));
+                params.push(("accessKeyId", access_key_id.clone()));
+                params.push(("secretAccessKey", secret_access_key.clone()));
+                params.push(("region", region.clone()));
+                if let Some(session_token) = session_token {
+                    params.push(("sessionToken", session_token.clone()));
+                }
+                if let Some(service_endpoint) = service_endpoint {
+                    params.push(("serviceEndpoint", service_endpoint.clone()));
+                }
+            }
+        }
+
+        let url = format!(
+            "{}/{}",
+            self.analytics_link_url(scope_name),
+            encode_path_segment(&request.link_name)
+        );
+        self.analytics_api(self.client.post(&url).form(&params)).await?;
+
+        info!(scope_name, link = %request.link_name, "Created analytics link");
+        Ok(())
+    }
+
+    pub async fn delete_analytics_link(&self, scope_name: &str, link_name: &str) -> Result<()> {
+        let url = format!(
+            "{}/{}",
+            self.analytics_link_url(scope_name),
+            encode_path_segment(link_name)
+        );
+        self.analytics_api(self.client.delete(&url)).await?;
+
+        info!(scope_name, link = link_name, "Deleted analytics link");
+        Ok(())
+    }
+
+    /// Connecting a link starts ingestion into the analytics collections it feeds
+    pub async fn set_analytics_link_connected(&self, scope_name: &str, link_name: &str, connected: bool) -> Result<()> {
+        let statement = format!(
+            "{} LINK {}.{}",
+            if connected { "CONNECT" } else { "DISCONNECT" },
+            quote_identifier(scope_name),
+            quote_identifier(link_name)
+        );
+        self.execute_analytics_statement(&statement).await?;
+
+        info!(scope_name, link = link_name, connected, "Changed analytics link state");
+        Ok(())
+    }
+
+    // User Management
+    pub async fn create_user(&self, request: &CouchbaseUserConfig) -> Result<()> {
+        let url = format!("{}/settings/rbac/users/{}/{}", self.base_url, request.domain, request.name);
+        
+        // Convert roles to form-encoded format
+        let mut params: Vec<(String, String)> = vec![
+            ("name".to_string(), request.name.clone()),
+        ];
+
+        if let Some(password) = &request.password {
+            params.push(("password".to_string(), password.clone()));
+        }
+
+        // Add roles - Couchbase expects roles as separate parameters
+        // We need to send all roles in a single "roles" parameter as a comma-separated string
+        let mut role_strings = Vec::new();
+        for role in &request.roles {
+            if role.bucket_name.is_none() && role.scope_name.is_none() && role.collection_name.is_none() {
+                // Global role
+                role_strings.push(role.role.clone());
+            } else {
+                // Bucket/scope/collection specific role - format as "role[bucket:scope:collection]"
+                let role_spec = if let Some(bucket) = &role.bucket_name {
+                    let mut spec = format!("{}[{}", role.role, bucket);
+                    if let Some(scope) = &role.scope_name {
+                        spec.push_str(&format!(":{}", scope));
+                        if let Some(collection) = &role.collection_name {
+                            spec.push_str(&format!(":{}", collection));
+                        }
+                    }
+                    spec.push(']');
+                    spec
+                } else {
+                    role.role.clone()
+                };
+                role_strings.push(role_spec);
+            }
+        }
+        
+        // Join all roles with comma separation
+        if !role_strings.is_empty() {
+            params.push(("roles".to_string(), role_strings.join(",")));
+        }
+
+        let request = self
+            .client
+            .put(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .form(&params);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        self.cache.invalidate_users();
+
+        Ok(())
+    }
+
+    pub async fn list_users(&self, domain: Option<UserDomain>) -> Result<Vec<UserInfo>> {
+        if let Some(users) = self.cache.users(domain) {
+            return Ok(users);
+        }
+
+        let url = match domain {
+            Some(domain) => format!("{}/settings/rbac/users/{}", self.base_url, domain),
+            None => format!("{}/settings/rbac/users", self.base_url),
+        };
+        
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        let users_data: Vec<serde_json::Value> = response.json().await?;
+        let users: Vec<UserInfo> = users_data.iter().map(user_info_from_json).collect();
+        self.cache.put_users(domain, users.clone());
+        Ok(users)
+    }
+
+    /// Fetch one page of users using Couchbase's own paging on `/settings/rbac/users`.
+    /// `start_from` is the first user of the page, as returned in `UserPage::next`.
+    pub async fn list_users_page(
+        &self,
+        page_size: usize,
+        start_from: Option<(UserDomain, String)>,
+        sort_by: UserSortField,
+        order: SortOrder,
+        search: Option<&str>,
+    ) -> Result<UserPage> {
+        let url = format!("{}/settings/rbac/users", self.base_url);
+
+        let mut params: Vec<(String, String)> = vec![
+            ("pageSize".to_string(), page_size.to_string()),
+            ("sortBy".to_string(), sort_by.couchbase_name().to_string()),
+            ("order".to_string(), match order {
+                SortOrder::Asc => "asc".to_string(),
+                SortOrder::Desc => "desc".to_string(),
+            }),
+        ];
+        if let Some((domain, name)) = start_from {
+            params.push(("startFrom".to_string(), name));
+            params.push(("startFromDomain".to_string(), domain.to_string()));
+        }
+        if let Some(search) = search {
+            params.push(("substr".to_string(), search.to_string()));
+        }
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .query(&params);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        let page: serde_json::Value = response.json().await?;
+        let users = page["users"]
+            .as_array()
+            .unwrap_or(&vec![])
+            .iter()
+            .map(user_info_from_json)
+            .collect();
+
+        // `links.next` is a relative URL whose query string holds the next page's start
+        let next = page["links"]["next"].as_str().and_then(|next| {
+            let next = reqwest::Url::parse(&format!("{}{}", self.base_url, next)).ok()?;
+            let pairs: HashMap<_, _> = next.query_pairs().into_owned().collect();
+            let name = pairs.get("startFrom")?.clone();
+            let domain = UserDomain::from_couchbase(pairs.get("startFromDomain").map(String::as_str));
+            Some((domain, name))
+        });
+
+        Ok(UserPage {
+            users,
+            total: page["total"].as_u64().unwrap_or(0) as usize,
+            next,
+        })
+    }
+
+    pub async fn get_user(&self, domain: UserDomain, username: &str) -> Result<UserInfo> {
+        if let Some(user) = self.cache.user(domain, username) {
+            return Ok(user);
+        }
+
+        let url = format!("{}/settings/rbac/users/{}/{}", self.base_url, domain, username);
+        
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("User '{}' not found", username)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        let user: serde_json::Value = response.json().await?;
+        debug!(
+            user = %username,
+            %domain,
+            roles = user["roles"].as_array().map_or(0, Vec::len),
+            "Fetched user"
+        );
+
+        let roles: Result<Vec<Role>> = user["roles"]
+            .as_array()
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|role| {
+                // Handle different role formats that Couchbase might return
+                if role.is_string() {
+                    // Simple string role (e.g., "cluster_admin")
+                    Ok(Role {
+                        role: role.as_str().unwrap_or("").to_string(),
+                        bucket: None,
+                        scope: None,
+                        collection: None,
+                    })
+                } else if role.is_object() {
+                    // Object role with bucket/scope/collection info
+                    Ok(Role {
+                        role: role["role"].as_str().unwrap_or("").to_string(),
+                        bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
+                        scope: role["scope_name"].as_str().map(|s| s.to_string()),
+                        collection: role["collection_name"].as_str().map(|s| s.to_string()),
+                    })
+                } else {
+                    // Fallback - try to extract role name from any field
+                    let role_str = role["role"].as_str()
+                        .or_else(|| role["name"].as_str())
+                        .or_else(|| role.as_str())
+                        .unwrap_or("unknown");
+                    
+                    Ok(Role {
+                        role: role_str.to_string(),
+                        bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
+                        scope: role["scope_name"].as_str().map(|s| s.to_string()),
+                        collection: role["collection_name"].as_str().map(|s| s.to_string()),
+                    })
+                }
+            })
+            .collect();
+
+        let user = UserInfo {
+            username: user["id"].as_str().unwrap_or("").to_string(),
+            domain: UserDomain::from_couchbase(user["domain"].as_str()),
+            roles: roles?,
+            groups: user["groups"]
+                .as_array()
+                .unwrap_or(&vec![])
+                .iter()
+                .map(|g| g.as_str().unwrap_or("").to_string())
+                .collect(),
+            display_name: display_name(&user),
+        };
+        self.cache.put_user(user.clone());
+        Ok(user)
+    }
+
+    pub async fn delete_user(&self, domain: UserDomain, username: &str) -> Result<()> {
+        let url = format!("{}/settings/rbac/users/{}/{}", self.base_url, domain, username);
+        
+        let request = self
+            .client
+            .delete(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("User '{}' not found", username)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        self.cache.invalidate_users();
+
+        Ok(())
+    }
+
+    pub async fn update_user(&self, request: &CouchbaseUserConfig) -> Result<()> {
+        let url = format!("{}/settings/rbac/users/{}/{}", self.base_url, request.domain, request.name);
+        
+        let mut params: Vec<(String, String)> = vec![
+            ("name".to_string(), request.name.clone()),
+        ];
+
+        if let Some(password) = &request.password {
+            params.push(("password".to_string(), password.clone()));
+        }
+
+        // Add roles - Couchbase expects simple role names for basic roles
+        for role in &request.roles {
+            if role.bucket_name.is_none() && role.scope_name.is_none() && role.collection_name.is_none() {
+                // Global role
+                params.push(("roles".to_string(), role.role.clone()));
+            } else {
+                // Bucket/scope/collection specific role - format as "role[bucket:scope:collection]"
+                let role_spec = if let Some(bucket) = &role.bucket_name {
+                    let mut spec = format!("{}[{}", role.role, bucket);
+                    if let Some(scope) = &role.scope_name {
+                        spec.push_str(&format!(":{}", scope));
+                        if let Some(collection) = &role.collection_name {
+                            spec.push_str(&format!(":{}", collection));
+                        }
+                    }
+                    spec.push(']');
+                    spec
+                } else {
+                    role.role.clone()
+                };
+                params.push(("roles".to_string(), role_spec));
+            }
+        }
+
+        let put_request = self
+            .client
+            .put(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .form(&params);
+        let response = self.send(put_request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("User '{}' not found", request.name)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        self.cache.invalidate_users();
+
+        Ok(())
+    }
+
+    async fn get_user_document(&self, domain: UserDomain, username: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/settings/rbac/users/{}/{}", self.base_url, domain, username);
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("User '{}' not found", username)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Roles assigned directly to a user, excluding those inherited from groups
+    pub async fn get_user_direct_roles(&self, domain: UserDomain, username: &str) -> Result<Vec<Role>> {
+        let user = self.get_user_document(domain, username).await?;
+        Ok(direct_roles(&user))
+    }
+
+    /// Replace a user's direct roles, leaving the password unchanged
+    pub async fn set_user_roles(&self, domain: UserDomain, username: &str, roles: &[Role]) -> Result<()> {
+        let user = self.get_user_document(domain, username).await?;
+        self.put_user_settings(domain, username, &user, roles, None).await
+    }
+
+    /// Create or update a user from an import row. Roles are replaced; an existing
+    /// user keeps its groups and display name unless the row gives new ones.
+    pub async fn import_user(&self, request: &CreateUserRequest, exists: bool) -> Result<()> {
+        let mut user = if exists {
+            self.get_user_document(request.domain, &request.username).await?
+        } else {
+            serde_json::json!({ "name": request.username })
+        };
+        if let Some(groups) = &request.groups {
+            user["groups"] = groups.clone().into();
+        }
+        if let Some(name) = &request.display_name {
+            user["name"] = name.clone().into();
+        }
+
+        self.put_user_settings(
+            request.domain,
+            &request.username,
+            &user,
+            &request.roles,
+            request.password.as_deref(),
+        )
+        .await
+    }
+
+    /// Set a new password for a local user, leaving its roles unchanged
+    pub async fn set_user_password(&self, username: &str, password: &str) -> Result<()> {
+        let user = self.get_user_document(UserDomain::Local, username).await?;
+        self.put_user_settings(UserDomain::Local, username, &user, &direct_roles(&user), Some(password))
+            .await
+    }
+
+    // Couchbase replaces the whole user definition on PUT, so the display name and
+    // groups are carried over from the current document
+    async fn put_user_settings(
+        &self,
+        domain: UserDomain,
+        username: &str,
+        user: &serde_json::Value,
+        roles: &[Role],
+        password: Option<&str>,
+    ) -> Result<()> {
+        let url = format!("{}/settings/rbac/users/{}/{}", self.base_url, domain, username);
+
+        let role_specs: Vec<String> = roles.iter().map(Role::to_spec).collect();
+        let groups: Vec<String> = user["groups"]
+            .as_array()
+            .unwrap_or(&vec![])
+            .iter()
+            .filter_map(|g| g.as_str().map(|s| s.to_string()))
+            .collect();
+
+        let mut params: Vec<(String, String)> = vec![
+            ("roles".to_string(), role_specs.join(",")),
+            ("groups".to_string(), groups.join(",")),
+        ];
+        if let Some(name) = user["name"].as_str() {
+            params.push(("name".to_string(), name.to_string()));
+        }
+        if let Some(password) = password {
+            params.push(("password".to_string(), password.to_string()));
+        }
+
+        let request = self
+            .client
+            .put(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .form(&params);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("User '{}' not found", username)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        self.cache.invalidate_users();
+
+        Ok(())
+    }
+
+    // Password Policy
+    /// Fetch the cluster-wide password policy from `/settings/passwordPolicy`
+    pub async fn get_password_policy(&self) -> Result<PasswordPolicy> {
+        let url = format!("{}/settings/passwordPolicy", self.base_url);
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        let policy: serde_json::Value = response.json().await?;
+        Ok(PasswordPolicy {
+            min_length: policy["minLength"].as_u64().unwrap_or(0) as usize,
+            require_uppercase: policy["enforceUppercase"].as_bool().unwrap_or(false),
+            require_lowercase: policy["enforceLowercase"].as_bool().unwrap_or(false),
+            require_digits: policy["enforceDigits"].as_bool().unwrap_or(false),
+            require_special_chars: policy["enforceSpecialChars"].as_bool().unwrap_or(false),
+            denylist: Default::default(),
+        })
+    }
+
+    // Role Catalog
+    /// Return the cached role catalog, fetching it from the cluster when missing or stale.
+    /// A stale catalog is still served if the cluster cannot be reached.
+    pub async fn role_catalog(&self) -> Result<Arc<RoleCatalog>> {
+        {
+            let cached = self.role_catalog.read().await;
+            if let Some(cached) = cached.as_ref() {
+                if cached.fetched_at.elapsed() < self.role_catalog_ttl {
+                    return Ok(cached.catalog.clone());
+                }
+            }
+        }
+
+        match self.refresh_role_catalog().await {
+            Ok(catalog) => Ok(catalog),
+            Err(e) => {
+                let cached = self.role_catalog.read().await;
+                match cached.as_ref() {
+                    Some(cached) => {
+                        warn!("Failed to refresh role catalog, serving stale copy: {}", e);
+                        Ok(cached.catalog.clone())
+                    }
+                    None => Err(e),
+                }
+            }
+        }
+    }
+
+    /// Fetch the role catalog from `/settings/rbac/roles` and replace the cached copy
+    pub async fn refresh_role_catalog(&self) -> Result<Arc<RoleCatalog>> {
+        let url = format!("{}/settings/rbac/roles", self.base_url);
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        let roles_data: Vec<serde_json::Value> = response.json().await?;
+        let definitions: Vec<RoleDefinition> = roles_data
+            .iter()
+            .filter_map(|role| {
+                let name = role["role"].as_str()?;
+                let parameters = [
+                    ("bucket_name", RoleParameter::Bucket),
+                    ("scope_name", RoleParameter::Scope),
+                    ("collection_name", RoleParameter::Collection),
+                ]
+                .into_iter()
+                .filter(|(key, _)| role.get(*key).is_some())
+                .map(|(_, parameter)| parameter)
+                .collect();
+
+                Some(RoleDefinition {
+                    role: name.to_string(),
+                    name: role["name"].as_str().unwrap_or(name).to_string(),
+                    description: role["desc"].as_str().unwrap_or("").to_string(),
+                    parameters,
+                })
+            })
+            .collect();
+
+        let catalog = Arc::new(RoleCatalog::new(definitions));
+        *self.role_catalog.write().await = Some(CachedRoleCatalog {
+            catalog: catalog.clone(),
+            fetched_at: Instant::now(),
+        });
+
+        Ok(catalog)
+    }
+
+    /// Keep the role catalog warm by refreshing it on the configured interval
+    pub async fn run_role_catalog_refresh(self) {
+        let mut interval = tokio::time::interval(self.role_catalog_ttl);
+        loop {
+            interval.tick().await;
+            match self.refresh_role_catalog().await {
+                Ok(catalog) => info!("Role catalog refreshed ({} roles)", catalog.definitions().len()),
+                Err(e) => warn!("Failed to refresh role catalog: {}", e),
+            }
+        }
+    }
+
+    // Group Management
+    pub async fn get_group_roles(&self, group: &str) -> Result<Vec<Role>> {
+        let url = format!("{}/settings/rbac/groups/{}", self.base_url, group);
+
+        let request = self
+            .client
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            if status == 404 {
+                return Err(AppError::NotFound(format!("Group '{}' not found", group)));
+            }
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        let group_data: serde_json::Value = response.json().await?;
+        let roles = group_data["roles"]
+            .as_array()
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|role| Role {
+                role: role["role"].as_str().unwrap_or("").to_string(),
+                bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
+                scope: role["scope_name"].as_str().map(|s| s.to_string()),
+                collection: role["collection_name"].as_str().map(|s| s.to_string()),
+            })
+            .collect();
+
+        Ok(roles)
+    }
+
+    /// Collect the roles a user receives through its groups
+    pub async fn get_inherited_roles(&self, user: &UserInfo) -> Result<Vec<InheritedRole>> {
+        let mut inherited = Vec::new();
+        for group in user.groups.iter().filter(|g| !g.is_empty()) {
+            for role in self.get_group_roles(group).await? {
+                inherited.push(InheritedRole {
+                    group: group.clone(),
+                    role,
+                });
+            }
+        }
+        Ok(inherited)
+    }
+
+    // Permission Checks
+    /// Evaluate permissions as the given user via `/pools/default/checkPermissions`.
+    /// The request is made on behalf of the user, so Couchbase resolves wildcards and
+    /// group-inherited roles exactly as it would for the user's own requests.
+    pub async fn check_permissions(
+        &self,
+        domain: UserDomain,
+        username: &str,
+        permissions: &[String],
+    ) -> Result<HashMap<String, bool>> {
+        let url = format!("{}/pools/default/checkPermissions", self.base_url);
+        let on_behalf_of = general_purpose::STANDARD.encode(format!("{}:{}", username, domain));
+
+        let request = self
+            .client
+            .post(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("cb-on-behalf-of", on_behalf_of)
+            .body(permissions.join(","));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::CouchbaseApi {
+                message: error_text,
+                status,
+            });
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+fn direct_roles(user: &serde_json::Value) -> Vec<Role> {
+    user["roles"]
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .filter(|role| {
+            // Roles without origins predate group support and are always direct
+            role["origins"].as_array().is_none_or(|origins| {
+                origins.iter().any(|o| o["type"].as_str() == Some("user"))
+            })
+        })
+        .map(|role| Role {
+            role: role["role"].as_str().unwrap_or("").to_string(),
+            bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
+            scope: role["scope_name"].as_str().map(|s| s.to_string()),
+            collection: role["collection_name"].as_str().map(|s| s.to_string()),
+        })
+        .collect()
+}
+
+fn cluster_capacity(pool: &serde_json::Value, buckets: &[BucketInfo]) -> ClusterCapacity {
+    const MB: u64 = 1024 * 1024;
+    let bucket_quota_mb: u64 = buckets.iter().map(|b| b.ram_quota_mb as u64).sum();
+
+    let services: Vec<ServiceCapacity> = SERVICE_QUOTAS
+        .iter()
+        .filter_map(|(service, key)| {
+            let quota_mb = pool[*key].as_u64()?;
+            let used_mb = (*service == "kv").then_some(bucket_quota_mb);
+            Some(ServiceCapacity {
+                service: service.to_string(),
+                quota_mb,
+                used_mb,
+                free_mb: used_mb.map(|used| quota_mb.saturating_sub(used)),
+            })
+        })
+        .collect();
+
+    let nodes = pool["nodes"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let node_memory_mb = nodes
+        .iter()
+        .filter_map(|node| node["memoryTotal"].as_u64())
+        .min()
+        .unwrap_or(0)
+        / MB;
+    let allocated_quota_mb: u64 = services.iter().map(|s| s.quota_mb).sum();
+
+    let hdd = &pool["storageTotals"]["hdd"];
+    ClusterCapacity {
+        ram: RamCapacity {
+            node_count: nodes.len(),
+            node_memory_mb,
+            allocated_quota_mb,
+            unallocated_mb: node_memory_mb.saturating_sub(allocated_quota_mb),
+        },
+        disk: DiskCapacity {
+            total_bytes: hdd["total"].as_u64().unwrap_or(0),
+            used_bytes: hdd["used"].as_u64().unwrap_or(0),
+            used_by_data_bytes: hdd["usedByData"].as_u64().unwrap_or(0),
+            free_bytes: hdd["free"].as_u64().unwrap_or(0),
+        },
+        services,
+    }
+}
+
+/// `bucket`.`scope`.`collection`, quoted for N1QL
+fn keyspace(bucket_name: &str, scope_name: &str, collection_name: &str) -> String {
+    format!(
+        "{}.{}.{}",
+        quote_identifier(bucket_name),
+        quote_identifier(scope_name),
+        quote_identifier(collection_name)
+    )
+}
+
+fn create_index_statement(keyspace: &str, request: &CreateIndexRequest) -> String {
+    let mut statement = if request.primary {
+        match &request.index_name {
+            Some(name) => format!("CREATE PRIMARY INDEX {} ON {}", quote_identifier(name), keyspace),
+            None => format!("CREATE PRIMARY INDEX ON {}", keyspace),
+        }
+    } else {
+        format!(
+            "CREATE INDEX {} ON {}({})",
+            quote_identifier(request.name()),
+            keyspace,
+            request.fields.join(", ")
+        )
+    };
+
+    if !request.partition_by.is_empty() {
+        statement.push_str(&format!(" PARTITION BY HASH({})", request.partition_by.join(", ")));
+    }
+    if let Some(condition) = &request.condition {
+        statement.push_str(&format!(" WHERE {}", condition));
+    }
+
+    let mut with = serde_json::Map::new();
+    if request.deferred {
+        with.insert("defer_build".to_string(), true.into());
+    }
+    if let Some(num_replica) = request.num_replica {
+        with.insert("num_replica".to_string(), num_replica.into());
+    }
+    if let Some(num_partition) = request.num_partition {
+        with.insert("num_partition".to_string(), num_partition.into());
+    }
+    if !with.is_empty() {
+        statement.push_str(&format!(" WITH {}", serde_json::Value::Object(with)));
+    }
+
+    statement
+}
+
+fn query_error(error: &serde_json::Value, status: u16) -> AppError {
+    let code = error["code"].as_u64().unwrap_or(0);
+    let message = error["msg"].as_str().unwrap_or("Unknown query error").to_string();
+    match code {
+        // Keyspace, scope or index not found
+        12003 | 12004 | 12016 | 12021 => AppError::NotFound(message),
+        // Index already exists
+        4300 => AppError::Conflict(message),
+        _ => AppError::CouchbaseApi {
+            message,
+            status: if status >= 400 { status } else { 500 },
+        },
+    }
+}
+
+fn search_error(body: &serde_json::Value, status: u16) -> AppError {
+    let message = body["error"]
+        .as_str()
+        .or(body.as_str())
+        .map_or_else(|| body.to_string(), str::to_string);
+    // A missing index is reported as a bad request rather than a 404
+    if status == 404 || message.contains("not found") || message.contains("no indexName") {
+        return AppError::NotFound(message);
+    }
+    AppError::CouchbaseApi {
+        message,
+        status: if status >= 400 { status } else { 500 },
+    }
+}
+
+/// The parts of a stored search index definition that can be applied to any cluster
+fn portable_search_definition(name: &str, definition: &serde_json::Value) -> SearchIndexDefinition {
+    let object = |key: &str| definition.get(key).filter(|value| value.is_object()).cloned();
+    SearchIndexDefinition {
+        name: Some(name.to_string()),
+        index_type: definition["type"].as_str().unwrap_or(SEARCH_INDEX_TYPE).to_string(),
+        params: definition["params"].clone(),
+        plan_params: object("planParams"),
+        source_params: object("sourceParams"),
+    }
+}
+
+/// The eventing service reports errors as `{"name": "ERR_...", "description": ...}`,
+/// with details such as compilation errors under `runtime_info.info`
+fn eventing_error(body: &serde_json::Value, status: u16) -> AppError {
+    let name = body["name"].as_str().unwrap_or_default();
+    let message = match &body["runtime_info"]["info"] {
+        serde_json::Value::String(info) => info.clone(),
+        serde_json::Value::Null => body["description"]
+            .as_str()
+            .or(body.as_str())
+            .map_or_else(|| body.to_string(), str::to_string),
+        // Compilation errors carry a description and the line it refers to
+        info => match (info["description"].as_str(), info["line_number"].as_i64()) {
+            (Some(description), Some(line)) => format!("{} (line {})", description, line),
+            (Some(description), None) => description.to_string(),
+            _ => info.to_string(),
+        },
+    };
+
+    if status == 404 || name.contains("NOT_FOUND") {
+        AppError::NotFound(message)
+    } else if EVENTING_STATE_ERRORS.contains(&name) {
+        AppError::Conflict(message)
+    } else {
+        AppError::CouchbaseApi {
+            message,
+            status: if status >= 400 { status } else { 500 },
+        }
+    }
+}
+
+fn eventing_function_info(
+    function: &serde_json::Value,
+    statuses: &[serde_json::Value],
+) -> Result<EventingFunctionInfo> {
+    let mut definition: EventingFunctionDefinition = serde_json::from_value(function.clone())?;
+    definition
+        .settings
+        .retain(|setting, _| !EVENTING_STATE_SETTINGS.contains(&setting.as_str()));
+
+    let name = definition.appname.clone().unwrap_or_default();
+    let status = statuses.iter().find(|status| status["name"] == name.as_str());
+
+    Ok(EventingFunctionInfo {
+        status: status
+            .and_then(|status| status["composite_status"].as_str())
+            .unwrap_or("undeployed")
+            .to_string(),
+        deployed_nodes: status
+            .and_then(|status| status["num_deployed_nodes"].as_u64())
+            .unwrap_or(0) as u32,
+        name,
+        definition,
+    })
+}
+
+fn analytics_error(error: &serde_json::Value, status: u16) -> AppError {
+    let code = error["code"].as_u64().unwrap_or(0);
+    let message = error["msg"].as_str().unwrap_or("Unknown analytics error").to_string();
+    if ANALYTICS_NOT_FOUND_CODES.contains(&code) {
+        AppError::NotFound(message)
+    } else if ANALYTICS_EXISTS_CODES.contains(&code) {
+        AppError::Conflict(message)
+    } else {
+        AppError::CouchbaseApi {
+            message,
+            status: if status >= 400 { status } else { 500 },
+        }
+    }
+}
+
+/// An analytics collection from its `Metadata.Dataset` entry
+fn analytics_collection_info(dataset: &serde_json::Value) -> Option<AnalyticsCollectionInfo> {
+    // First of the fields that is set, as field names differ between versions
+    let text = |keys: &[&str]| keys.iter().find_map(|key| dataset[*key].as_str()).map(str::to_string);
+    let default_name = || validation::DEFAULT_KEYSPACE_NAME.to_string();
+
+    Some(AnalyticsCollectionInfo {
+        name: text(&["DatasetName"])?,
+        scope: text(&["DataverseName"]).unwrap_or_default(),
+        link: text(&["LinkName"]).unwrap_or_else(|| LOCAL_ANALYTICS_LINK.to_string()),
+        bucket: text(&["BucketName"]).unwrap_or_default(),
+        source: CollectionInfo {
+            name: text(&["CollectionName", "BucketCollectionName"]).unwrap_or_else(default_name),
+            max_ttl: None,
+            history: None,
+            scope: text(&["ScopeName", "BucketScopeName"]).unwrap_or_else(default_name),
+        },
+        filter: text(&["Filter", "WhereClause"]),
+        ingestion: None,
+    })
+}
+
+/// Flatten `/analytics/status/ingestion`, which lists collections per link and scope
+fn ingestion_statuses(body: &serde_json::Value) -> Vec<AnalyticsIngestionStatus> {
+    let list = |value: &serde_json::Value| value.as_array().cloned().unwrap_or_default();
+
+    let mut statuses = Vec::new();
+    for link in list(&body["links"]) {
+        let link_name = link["name"].as_str().unwrap_or_default();
+        let status = link["status"].as_str().unwrap_or("unknown");
+        for state in list(&link["state"]) {
+            for scope in list(&state["scopes"]) {
+                for collection in list(&scope["collections"]) {
+                    statuses.push(AnalyticsIngestionStatus {
+                        scope: scope["name"].as_str().unwrap_or_default().to_string(),
+                        collection: collection["name"].as_str().unwrap_or_default().to_string(),
+                        link: link_name.to_string(),
+                        status: status.to_string(),
+                        progress: state["progress"].as_f64(),
+                    });
+                }
+            }
+        }
+    }
+    statuses
+}
+
+/// A link from `/analytics/link`, with its secrets redacted
+fn analytics_link_info(scope_name: &str, link: &serde_json::Value) -> Option<AnalyticsLinkInfo> {
+    let text = |key: &str| link[key].as_str().unwrap_or_default().to_string();
+    let optional = |key: &str| link[key].as_str().filter(|value| !value.is_empty()).map(str::to_string);
+
+    let settings = match link["type"].as_str()? {
+        "couchbase" => AnalyticsLinkSettings::Couchbase {
+            hostname: text("hostname"),
+            username: text("username"),
+            password: String::new(),
+            encryption: match link["encryption"].as_str() {
+                Some("half") => XdcrEncryption::Half,
+                Some("full") => XdcrEncryption::Full,
+                _ => XdcrEncryption::None,
+            },
+            certificate: optional("certificate"),
+        },
+        "s3" => AnalyticsLinkSettings::S3 {
+            access_key_id: text("accessKeyId"),
+            secret_access_key: String::new(),
+            session_token: optional("sessionToken"),
+            region: text("region"),
+            service_endpoint: optional("serviceEndpoint"),
+        },
+        _ => return None,
+    };
+
+    Some(AnalyticsLinkInfo {
+        name: text("name"),
+        scope: link["scope"].as_str().unwrap_or(scope_name).to_string(),
+        settings: settings.redacted(),
+    })
+}
+
+// Percent-encode a value used as a single path segment, such as a replication id
+fn encode_path_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+/// XDCR endpoints report errors as an object of field to message, sometimes under
+/// `errors`, with `_` for errors that are not about one field
+fn xdcr_error(error_text: &str, status: u16) -> AppError {
+    let body: serde_json::Value = serde_json::from_str(error_text).unwrap_or_default();
+    let fields = body.get("errors").unwrap_or(&body).as_object();
+
+    let message = match fields {
+        Some(fields) if !fields.is_empty() => fields
+            .iter()
+            .map(|(field, message)| {
+                let message = message.as_str().map_or_else(|| message.to_string(), str::to_string);
+                if field == "_" {
+                    message
+                } else {
+                    format!("{}: {}", field, message)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => error_text.to_string(),
+    };
+
+    AppError::CouchbaseApi { message, status }
+}
+
+fn remote_cluster_info(reference: &serde_json::Value) -> RemoteClusterInfo {
+    let text = |key: &str| reference[key].as_str().unwrap_or_default().to_string();
+    RemoteClusterInfo {
+        name: text("name"),
+        uuid: text("uuid"),
+        hostname: text("hostname"),
+        username: text("username"),
+        encryption: XdcrEncryption::from_couchbase(reference),
+        connectivity_status: reference["connectivityStatus"].as_str().map(str::to_string),
+    }
+}
+
+/// Explicit collection mapping rules from a replication's settings. The rules may
+/// be reported as an object or as a JSON string.
+fn collection_mappings(settings: &serde_json::Value) -> BTreeMap<String, Option<String>> {
+    if settings["collectionsExplicitMapping"].as_bool() != Some(true) {
+        return BTreeMap::new();
+    }
+
+    let rules = match &settings["colMappingRules"] {
+        serde_json::Value::String(rules) => serde_json::from_str(rules).unwrap_or_default(),
+        rules => rules.clone(),
+    };
+    rules
+        .as_object()
+        .map(|rules| {
+            rules
+                .iter()
+                .map(|(source, target)| (source.clone(), target.as_str().map(str::to_string)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// One `IndexInfo` per index from the per-replica entries of `/indexStatus`
+fn index_infos(statuses: &[serde_json::Value]) -> Vec<IndexInfo> {
+    let mut indexes: Vec<IndexInfo> = Vec::new();
+
+    for status in statuses {
+        let name = status["indexName"]
+            .as_str()
+            .or(status["index"].as_str())
+            .unwrap_or("")
+            .to_string();
+        let bucket = status["bucket"].as_str().unwrap_or("").to_string();
+        let scope = status["scope"].as_str().unwrap_or("_default").to_string();
+        let collection = status["collection"].as_str().unwrap_or("_default").to_string();
+        let state = status["status"].as_str().unwrap_or("").to_string();
+        let progress = status["progress"].as_u64().unwrap_or(0) as u32;
+        let hosts = status["hosts"]
+            .as_array()
+            .map(|hosts| hosts.iter().filter_map(|h| h.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+
+        let existing = indexes.iter_mut().find(|index| {
+            index.name == name && index.bucket == bucket && index.scope == scope && index.collection == collection
+        });
+        match existing {
+            Some(index) => {
+                if index.status == READY_INDEX_STATUS {
+                    index.status = state;
+                }
+                index.progress = index.progress.min(progress);
+                index.hosts.extend(hosts);
+            }
+            None => {
+                let definition = status["definition"].as_str().unwrap_or("").to_string();
+                indexes.push(IndexInfo {
+                    primary: definition.contains("PRIMARY INDEX"),
+                    definition,
+                    name,
+                    bucket,
+                    scope,
+                    collection,
+                    status: state,
+                    progress,
+                    num_replica: status["numReplica"].as_u64().unwrap_or(0) as u32,
+                    num_partition: status["partitioned"]
+                        .as_bool()
+                        .unwrap_or(false)
+                        .then(|| status["numPartition"].as_u64().unwrap_or(0) as u32),
+                    hosts,
+                });
+            }
+        }
+    }
+
+    indexes
+}
+
+// Collection manifest changes answer with the bucket's new `{"uid": ...}`
+async fn manifest_uid(response: Response) -> Result<String> {
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    uid_from_manifest(&body)
+}
+
+// The uid becomes the manifest's ETag, so a missing one must not turn into `""`
+fn uid_from_manifest(body: &serde_json::Value) -> Result<String> {
+    match body["uid"].as_str() {
+        Some(uid) if !uid.is_empty() => Ok(uid.to_string()),
+        _ => Err(AppError::CouchbaseApi {
+            message: "Couchbase did not return a collection manifest uid".to_string(),
+            status: 502,
+        }),
+    }
+}
+
+fn user_info_from_json(user: &serde_json::Value) -> UserInfo {
+    let roles = user["roles"]
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .map(|role| Role {
+            role: role["role"].as_str().unwrap_or("").to_string(),
+            bucket: role["bucket_name"].as_str().map(|s| s.to_string()),
+            scope: role["scope_name"].as_str().map(|s| s.to_string()),
+            collection: role["collection_name"].as_str().map(|s| s.to_string()),
+        })
+        .collect();
+
+    UserInfo {
+        username: user["id"].as_str().unwrap_or("").to_string(),
+        domain: UserDomain::from_couchbase(user["domain"].as_str()),
+        roles,
+        groups: user["groups"]
+            .as_array()
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|g| g.as_str().unwrap_or("").to_string())
+            .collect(),
+        display_name: display_name(user),
+    }
+}
+
+fn display_name(user: &serde_json::Value) -> Option<String> {
+    user["name"].as_str().filter(|name| !name.is_empty()).map(str::to_string)
+}
+
+/// A stand-in cluster for tests: a local HTTP server answering from fixtures
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use crate::config::CacheConfig;
+    use axum::http::{HeaderMap, StatusCode, Uri};
+    use std::sync::Mutex;
+
+    pub use axum::http::Method;
+
+    #[derive(Debug, Clone)]
+    pub struct Recorded {
+        pub method: Method,
+        pub path: String,
+        pub headers: HeaderMap,
+        pub body: String,
+    }
+
+    impl Recorded {
+        pub fn form(&self) -> HashMap<String, String> {
+            reqwest::Url::parse(&format!("http://form/?{}", self.body))
+                .unwrap()
+                .query_pairs()
+                .into_owned()
+                .collect()
+        }
+    }
+
+    pub type Fixture = (Method, &'static str, u16, serde_json::Value);
+
+    // Answers each request with the first fixture for its method and path, and records it.
+    // Unmatched reads get a 404 and unmatched writes succeed. Every service URL points at
+    // the same server.
+    pub async fn fake_cluster(fixtures: Vec<Fixture>) -> (CouchbaseService, Arc<Mutex<Vec<Recorded>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let app = axum::Router::new().fallback(move |method: Method, uri: Uri, headers: HeaderMap, body: String| {
+            let fixture = fixtures
+                .iter()
+                .find(|(m, path, _, _)| *m == method && *path == uri.path())
+                .map(|(_, _, status, body)| (*status, body.clone()));
+            let default_status = if method == Method::GET { 404 } else { 200 };
+            recorded.lock().unwrap().push(Recorded {
+                method,
+                path: uri.path().to_string(),
+                headers,
+                body,
+            });
+            let (status, body) = fixture.unwrap_or((default_status, serde_json::json!({})));
+            async move { (StatusCode::from_u16(status).unwrap(), axum::Json(body)) }
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let service = CouchbaseService {
+            client: Client::new(),
+            base_url: url.clone(),
+            query_url: url.clone(),
+            search_url: url.clone(),
+            eventing_url: url.clone(),
+            analytics_url: url,
+            query_account: None,
+            username: "Administrator".to_string(),
+            password: "password".to_string(),
+            role_catalog: Arc::new(RwLock::new(None)),
+            role_catalog_ttl: Duration::from_secs(300),
+            topology_concurrency: 4,
+            cache: Arc::new(ManagementCache::new(&CacheConfig {
+                enabled: false,
+                ttl_seconds: 0,
+            })),
+            outbound: Arc::new(Semaphore::new(4)),
+        };
+        (service, requests)
+    }
+
+    pub fn url(service: &CouchbaseService, path: &str) -> String {
+        format!("{}{}", service.base_url, path)
+    }
+
+    pub fn puts(requests: &Mutex<Vec<Recorded>>) -> Vec<Recorded> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.method == Method::PUT)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{fake_cluster, puts, Method};
+    use super::*;
+
+    fn import_request(json: serde_json::Value) -> CreateUserRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn imported_upserts_keep_unlisted_groups_and_name() {
+        let (service, requests) = fake_cluster(vec![(
+            Method::GET,
+            "/settings/rbac/users/local/alice",
+            200,
+            serde_json::json!({
+                "id": "alice",
+                "domain": "local",
+                "name": "Alice Liddell",
+                "groups": ["ops", "dev"],
+                "roles": [{"role": "admin", "origins": [{"type": "user"}]}]
+            }),
+        )])
+        .await;
+
+        let roles_only = import_request(serde_json::json!({
+            "username": "alice",
+            "roles": [{"role": "data_reader", "bucket": "travel"}, {"role": "ro_admin"}]
+        }));
+        service.import_user(&roles_only, true).await.unwrap();
+
+        let renamed = import_request(serde_json::json!({
+            "username": "alice",
+            "password": "Secret-123",
+            "groups": ["ops"],
+            "display_name": "Alice L."
+        }));
+        service.import_user(&renamed, true).await.unwrap();
+
+        let puts = puts(&requests);
+        assert_eq!(puts.len(), 2);
+        assert!(puts.iter().all(|put| put.path == "/settings/rbac/users/local/alice"));
+
+        let form = puts[0].form();
+        assert_eq!(form["roles"], "data_reader[travel],ro_admin");
+        assert_eq!(form["groups"], "ops,dev");
+        assert_eq!(form["name"], "Alice Liddell");
+        assert!(!form.contains_key("password"));
+
+        let form = puts[1].form();
+        assert_eq!(form["roles"], "");
+        assert_eq!(form["groups"], "ops");
+        assert_eq!(form["name"], "Alice L.");
+        assert_eq!(form["password"], "Secret-123");
+    }
+
+    #[tokio::test]
+    async fn imported_users_are_created_with_their_groups_and_name() {
+        let (service, requests) = fake_cluster(vec![]).await;
+
+        let bob = import_request(serde_json::json!({
+            "username": "bob",
+            "password": "Secret-123",
+            "roles": [{"role": "admin"}],
+            "groups": ["ops"]
+        }));
+        service.import_user(&bob, false).await.unwrap();
+
+        let puts = puts(&requests);
+        assert_eq!(puts.len(), 1);
+        assert_eq!(requests.lock().unwrap().len(), 1, "new users are not looked up");
+        let form = puts[0].form();
+        assert_eq!(form["roles"], "admin");
+        assert_eq!(form["groups"], "ops");
+        assert_eq!(form["name"], "bob");
+        assert_eq!(form["password"], "Secret-123");
+    }
+
+    fn pool() -> serde_json::Value {
+        serde_json::json!({
+            "memoryQuota": 2048,
+            "indexMemoryQuota": 512,
+            "ftsMemoryQuota": 256,
+            "nodes": [
+                {"hostname": "cb1:8091", "memoryTotal": 8u64 << 30},
+                {"hostname": "cb2:8091", "memoryTotal": 6u64 << 30}
+            ],
+            "storageTotals": {"hdd": {"total": 1000, "used": 400, "usedByData": 150, "free": 600}}
+        })
+    }
+
+    fn bucket(name: &str, ram_quota_mb: u64) -> serde_json::Value {
+        serde_json::json!({"name": name, "bucketType": "membase", "quota": {"rawRAM": ram_quota_mb << 20}})
+    }
+
+    fn capacity_fixtures() -> Vec<testing::Fixture> {
+        vec![
+            (Method::GET, "/pools/default", 200, pool()),
+            (
+                Method::GET,
+                "/pools/default/buckets",
+                200,
+                serde_json::json!([bucket("travel", 1024), bucket("beer", 512)]),
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn capacity_is_read_from_the_pool_and_bucket_quotas() {
+        let (service, _) = fake_cluster(capacity_fixtures()).await;
+        let capacity = service.get_capacity().await.unwrap();
+
+        // The smallest node bounds what every node can give
+        assert_eq!(capacity.ram.node_count, 2);
+        assert_eq!(capacity.ram.node_memory_mb, 6144);
+        assert_eq!(capacity.ram.allocated_quota_mb, 2048 + 512 + 256);
+        assert_eq!(capacity.ram.unallocated_mb, 6144 - 2816);
+        assert_eq!(capacity.disk.free_bytes, 600);
+        assert_eq!(capacity.disk.used_by_data_bytes, 150);
+
+        let services: Vec<_> = capacity.services.iter().map(|s| s.service.as_str()).collect();
+        assert_eq!(services, ["kv", "index", "fts"]);
+        let data = &capacity.services[0];
+        assert_eq!((data.quota_mb, data.used_mb, data.free_mb), (2048, Some(1536), Some(512)));
+        assert_eq!(capacity.services[1].used_mb, None);
+    }
+
+    #[tokio::test]
+    async fn overcommitted_data_quota_has_no_free_ram() {
+        let (service, _) = fake_cluster(vec![
+            (Method::GET, "/pools/default", 200, pool()),
+            (
+                Method::GET,
+                "/pools/default/buckets",
+                200,
+                serde_json::json!([bucket("travel", 1536), bucket("beer", 1024)]),
+            ),
+        ])
+        .await;
+        let capacity = service.get_capacity().await.unwrap();
+        assert_eq!(capacity.services[0].used_mb, Some(2560));
+        assert_eq!(capacity.services[0].free_mb, Some(0));
+    }
+
+    #[tokio::test]
+    async fn bucket_quotas_must_fit_in_free_ram() {
+        let (service, _) = fake_cluster(capacity_fixtures()).await;
+
+        service.ensure_ram_available("orders", 512).await.unwrap();
+        assert!(matches!(
+            service.ensure_ram_available("orders", 513).await,
+            Err(AppError::Conflict(_))
+        ));
+        // A bucket's own quota is free for it to resize into
+        service.ensure_ram_available("travel", 1536).await.unwrap();
+        assert!(matches!(
+            service.ensure_ram_available("travel", 1537).await,
+            Err(AppError::Conflict(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn topology_without_the_index_service_still_lists_buckets() {
+        let buckets = (
+            Method::GET,
+            "/pools/default/buckets",
+            200,
+            serde_json::json!([{"name": "travel", "bucketType": "membase", "collectionsManifestUid": "2"}]),
+        );
+        let manifest = (
+            Method::GET,
+            "/pools/default/buckets/travel/scopes",
+            200,
+            serde_json::json!({"uid": "2", "scopes": [{"name": "inventory", "uid": "8", "collections": []}]}),
+        );
+        let index_statuses = serde_json::json!({"indexes": [
+            {"bucket": "travel", "scope": "inventory", "collection": "airline", "index": "by_name",
+             "status": "Ready", "definition": "CREATE INDEX `by_name` ON `travel`.`inventory`.`airline`(`name`)"}
+        ]});
+
+        let (service, _) = fake_cluster(vec![
+            buckets.clone(),
+            manifest.clone(),
+            (Method::GET, "/indexStatus", 200, index_statuses),
+        ])
+        .await;
+        let topology = service.get_topology().await.unwrap();
+        assert!(topology.indexes_error.is_none());
+        assert_eq!(topology.buckets[0].indexes.len(), 1);
+
+        let (service, _) = fake_cluster(vec![
+            buckets,
+            manifest,
+            (Method::GET, "/indexStatus", 503, serde_json::json!({})),
+        ])
+        .await;
+        let topology = service.get_topology().await.unwrap();
+        assert!(topology.indexes_error.is_some());
+        assert_eq!(topology.buckets.len(), 1);
+        assert_eq!(topology.buckets[0].scopes[0].name, "inventory");
+        assert!(topology.buckets[0].indexes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn role_catalog_is_read_from_the_cluster() {
+        // Trimmed from a Couchbase Server 7.2 `/settings/rbac/roles` response
+        let roles = serde_json::json!([
+            {"role": "admin", "name": "Full Admin", "desc": "Can manage all cluster features.", "ce": true},
+            {"role": "bucket_admin", "bucket_name": "*", "name": "Bucket Admin",
+             "desc": "Can manage ALL bucket features for a given bucket.", "ce": true},
+            {"role": "data_reader", "bucket_name": "*", "scope_name": "*", "collection_name": "*",
+             "name": "Data Reader", "desc": "Can read data from a given bucket, scope or collection."},
+            {"role": "query_select", "bucket_name": "*", "scope_name": "*", "collection_name": "*",
+             "name": "Query Select", "desc": "Can execute a SELECT statement."},
+            {"name": "No role key"}
+        ]);
+        let (service, _) = fake_cluster(vec![(Method::GET, "/settings/rbac/roles", 200, roles)]).await;
+
+        let catalog = service.refresh_role_catalog().await.unwrap();
+
+        assert_eq!(catalog.definitions().len(), 4);
+        let admin = catalog.get("admin").unwrap();
+        assert_eq!(admin.name, "Full Admin");
+        assert!(admin.parameters.is_empty());
+        assert_eq!(catalog.get("bucket_admin").unwrap().parameters, [RoleParameter::Bucket]);
+        assert_eq!(
+            catalog.get("data_reader").unwrap().parameters,
+            [RoleParameter::Bucket, RoleParameter::Scope, RoleParameter::Collection]
+        );
+        assert!(catalog.validate_role(&Role::parse_spec("query_select[travel:inventory]").unwrap()).is_ok());
+        assert!(catalog.validate_role(&Role::parse_spec("bucket_admin").unwrap()).is_err());
+        // The fetched catalog is cached for later lookups
+        assert!(Arc::ptr_eq(&catalog, &service.role_catalog().await.unwrap()));
+    }
+
+    #[test]
+    fn collection_mappings_are_read_from_objects_and_strings() {
+        let rules = serde_json::json!({"inventory.airline": "archive.airline", "tenant_a": null});
+        let expected = BTreeMap::from([
+            ("inventory.airline".to_string(), Some("archive.airline".to_string())),
+            ("tenant_a".to_string(), None),
+        ]);
+
+        let settings = serde_json::json!({"collectionsExplicitMapping": true, "colMappingRules": rules});
+        assert_eq!(collection_mappings(&settings), expected);
+        let settings =
+            serde_json::json!({"collectionsExplicitMapping": true, "colMappingRules": rules.to_string()});
+        assert_eq!(collection_mappings(&settings), expected);
+
+        // Rules only apply with explicit mapping on
+        for settings in [
+            serde_json::json!({"collectionsExplicitMapping": false, "colMappingRules": rules}),
+            serde_json::json!({"colMappingRules": rules}),
+            serde_json::json!({"collectionsExplicitMapping": true, "colMappingRules": "not json"}),
+            serde_json::json!({"collectionsExplicitMapping": true}),
+        ] {
+            assert!(collection_mappings(&settings).is_empty(), "{}", settings);
+        }
+    }
+
+    #[test]
+    fn xdcr_errors_name_their_fields() {
+        let message = |error_text: &str, status: u16| match xdcr_error(error_text, status) {
+            AppError::CouchbaseApi { message, status: actual } => {
+                assert_eq!(actual, status);
+                message
+            }
+            other => panic!("unexpected error {:?}", other),
+        };
+
+        assert_eq!(
+            message(r#"{"hostname": "Remote cluster is unreachable"}"#, 400),
+            "hostname: Remote cluster is unreachable"
+        );
+        assert_eq!(
+            message(r#"{"errors": {"_": "Replication already exists"}}"#, 400),
+            "Replication already exists"
+        );
+        let combined = message(r#"{"errors": {"toBucket": "unknown bucket", "filterExpression": "bad"}}"#, 400);
+        assert!(combined.contains("toBucket: unknown bucket"), "{}", combined);
+        assert!(combined.contains("filterExpression: bad"), "{}", combined);
+        assert_eq!(message(r#"{"errors": {"count": 3}}"#, 400), "count: 3");
+
+        // Bodies that are not field maps are passed through
+        for body in ["Unknown remote cluster", "{}", "[\"oops\"]"] {
+            assert_eq!(message(body, 404), body);
+        }
+    }
+
+    fn eventing_function(name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "appname": name,
+            "appcode": "function OnUpdate(doc, meta) {}",
+            "depcfg": {"source_bucket": "travel", "metadata_bucket": "eventing"},
+            "settings": {"worker_count": 2, "deployment_status": true, "processing_status": true}
+        })
+    }
+
+    #[test]
+    fn eventing_functions_take_status_from_the_status_list() {
+        let statuses = [
+            serde_json::json!({"name": "audit", "composite_status": "paused", "num_deployed_nodes": 0}),
+            serde_json::json!({"name": "enrich", "composite_status": "deployed", "num_deployed_nodes": 3}),
+        ];
+
+        let info = eventing_function_info(&eventing_function("enrich"), &statuses).unwrap();
+        assert_eq!((info.name.as_str(), info.status.as_str(), info.deployed_nodes), ("enrich", "deployed", 3));
+        // Deployment state is not part of the definition
+        assert_eq!(info.definition.settings.keys().collect::<Vec<_>>(), ["worker_count"]);
+        assert_eq!(info.definition.depcfg.source_scope, "_default");
+
+        let info = eventing_function_info(&eventing_function("audit"), &statuses).unwrap();
+        assert_eq!((info.status.as_str(), info.deployed_nodes), ("paused", 0));
+
+        // Functions the status list doesn't know about yet are undeployed
+        let info = eventing_function_info(&eventing_function("new"), &statuses).unwrap();
+        assert_eq!((info.status.as_str(), info.deployed_nodes), ("undeployed", 0));
+
+        assert!(eventing_function_info(&serde_json::json!({"appname": "broken"}), &statuses).is_err());
+    }
+
+    #[test]
+    fn eventing_errors_map_to_status_codes() {
+        let not_found = eventing_error(
+            &serde_json::json!({"name": "ERR_APP_NOT_FOUND_TS", "description": "Application not found"}),
+            400,
+        );
+        assert!(matches!(not_found, AppError::NotFound(message) if message == "Application not found"));
+        assert!(matches!(eventing_error(&serde_json::json!("no such function"), 404), AppError::NotFound(_)));
+
+        let conflict = eventing_error(
+            &serde_json::json!({
+                "name": "ERR_APP_ALREADY_DEPLOYED",
+                "description": "Function is already deployed",
+                "runtime_info": {"info": "Function: enrich is already deployed"}
+            }),
+            422,
+        );
+        assert!(matches!(conflict, AppError::Conflict(message) if message == "Function: enrich is already deployed"));
+
+        let compile = eventing_error(
+            &serde_json::json!({
+                "name": "ERR_HANDLER_COMPILATION",
+                "description": "handler compilation failed",
+                "runtime_info": {"info": {"description": "SyntaxError: Unexpected token", "line_number": 3}}
+            }),
+            422,
+        );
+        assert!(matches!(
+            compile,
+            AppError::CouchbaseApi { message, status: 422 } if message == "SyntaxError: Unexpected token (line 3)"
+        ));
+
+        // A failure reported with a success status is still an error
+        let unknown = eventing_error(&serde_json::json!({"name": "ERR_INTERNAL"}), 200);
+        assert!(matches!(unknown, AppError::CouchbaseApi { status: 500, .. }));
+    }
+
+    #[test]
+    fn analytics_links_never_carry_secrets() {
+        let remote = serde_json::json!({
+            "name": "west", "type": "couchbase", "scope": "sales", "hostname": "cb-west:8091",
+            "username": "analytics", "password": "remote-secret", "encryption": "full",
+            "certificate": "-----BEGIN CERTIFICATE-----"
+        });
+        let s3 = serde_json::json!({
+            "name": "archive", "type": "s3", "accessKeyId": "AKIAEXAMPLE",
+            "secretAccessKey": "s3-secret", "sessionToken": "session-secret",
+            "region": "eu-west-1", "serviceEndpoint": ""
+        });
+
+        let info = analytics_link_info("ignored", &remote).unwrap();
+        assert_eq!((info.name.as_str(), info.scope.as_str()), ("west", "sales"));
+        match &info.settings {
+            AnalyticsLinkSettings::Couchbase { hostname, username, password, encryption, certificate } => {
+                assert_eq!((hostname.as_str(), username.as_str()), ("cb-west:8091", "analytics"));
+                assert_eq!(password, crate::models::REDACTED);
+                assert!(matches!(encryption, XdcrEncryption::Full));
+                assert!(certificate.is_some());
+            }
+            other => panic!("unexpected settings {:?}", other),
+        }
+
+        let info = analytics_link_info("archive_scope", &s3).unwrap();
+        assert_eq!(info.scope, "archive_scope");
+        match &info.settings {
+            AnalyticsLinkSettings::S3 { access_key_id, secret_access_key, session_token, region, service_endpoint } => {
+                assert_eq!((access_key_id.as_str(), region.as_str()), ("AKIAEXAMPLE", "eu-west-1"));
+                assert_eq!(secret_access_key, crate::models::REDACTED);
+                assert_eq!(session_token.as_deref(), Some(crate::models::REDACTED));
+                assert_eq!(*service_endpoint, None);
+            }
+            other => panic!("unexpected settings {:?}", other),
+        }
+
+        for (link, secrets) in [(remote, &["remote-secret"][..]), (s3, &["s3-secret", "session-secret"])] {
+            let serialized = serde_json::to_string(&analytics_link_info("sales", &link).unwrap()).unwrap();
+            for secret in secrets {
+                assert!(!serialized.contains(secret), "{}", serialized);
+            }
+        }
+
+        assert!(analytics_link_info("sales", &serde_json::json!({"name": "blob", "type": "azureblob"})).is_none());
+        assert!(analytics_link_info("sales", &serde_json::json!({"name": "untyped"})).is_none());
+    }
+
+    #[test]
+    fn ingestion_statuses_are_flattened_per_collection() {
+        let body = serde_json::json!({"links": [
+            {"name": "Local", "status": "healthy", "state": [
+                {"progress": 0.5, "scopes": [
+                    {"name": "sales", "collections": [{"name": "orders"}, {"name": "customers"}]}
+                ]},
+                {"progress": 1.0, "scopes": [{"name": "ops", "collections": [{"name": "events"}]}]}
+            ]},
+            {"name": "west", "status": "stopped", "state": [
+                {"scopes": [{"name": "sales", "collections": [{"name": "returns"}]}]}
+            ]},
+            {"name": "empty", "status": "healthy"}
+        ]});
+
+        let statuses: Vec<_> = ingestion_statuses(&body)
+            .into_iter()
+            .map(|s| (s.link, s.scope, s.collection, s.status, s.progress))
+            .collect();
+        let row = |link: &str, scope: &str, collection: &str, status: &str, progress| {
+            (link.to_string(), scope.to_string(), collection.to_string(), status.to_string(), progress)
+        };
+        assert_eq!(
+            statuses,
+            [
+                row("Local", "sales", "orders", "healthy", Some(0.5)),
+                row("Local", "sales", "customers", "healthy", Some(0.5)),
+                row("Local", "ops", "events", "healthy", Some(1.0)),
+                row("west", "sales", "returns", "stopped", None),
+            ]
+        );
+        assert!(ingestion_statuses(&serde_json::json!({})).is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_urls_encode_every_segment() {
+        let (service, requests) = fake_cluster(vec![]).await;
+
+        let _ = service
+            .search("travel/..", "in ventory", "../../cfg", &serde_json::json!({}))
+            .await;
+        let _ = service.delete_search_index("travel", "inventory", "hotels?x=1").await;
+
+        let paths: Vec<String> = requests.lock().unwrap().iter().map(|r| r.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/api/bucket/travel%2F../scope/in%20ventory/index/..%2F..%2Fcfg/query",
+                "/api/bucket/travel/scope/inventory/index/hotels%3Fx%3D1",
+            ]
+        );
     }
 }
@@ -0,0 +1,19 @@
+use axum::extract::FromRef;
+use std::sync::Arc;
+
+use crate::{
+    config::QueryConfig, grants::GrantStore, models::RoleTemplates,
+    passwords::PasswordPolicyEnforcer, rotation::CredentialRotator, services::CouchbaseService,
+};
+
+/// Shared application state. Handlers extract the individual parts they need,
+/// e.g. `State<CouchbaseService>`.
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub couchbase_service: CouchbaseService,
+    pub role_templates: Arc<RoleTemplates>,
+    pub grant_store: Arc<GrantStore>,
+    pub password_policy: Arc<PasswordPolicyEnforcer>,
+    pub rotator: Arc<CredentialRotator>,
+    pub query_config: Arc<QueryConfig>,
+}
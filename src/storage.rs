@@ -0,0 +1,66 @@
+//! Helpers for the small JSON files the service keeps on local disk.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::Result;
+
+/// Read a JSON file, returning the default value if it does not exist yet
+pub async fn read_json_or_default<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match tokio::fs::read(path).await {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replace a JSON file. The data is written to a temporary file first so a crash
+/// never leaves a truncated file behind.
+pub async fn write_json_atomic<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    ensure_parent_dir(path).await?;
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    tokio::fs::write(&tmp_path, serde_json::to_vec_pretty(value)?).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+/// Append one JSON document as a line to a JSON-lines file
+pub async fn append_json_line<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    ensure_parent_dir(path).await?;
+
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&line).await?;
+    Ok(())
+}
+
+/// Read every document from a JSON-lines file; a missing file has no documents
+pub async fn read_json_lines<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let data = match tokio::fs::read_to_string(path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(data
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+pub async fn ensure_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,296 @@
+//! Optional OpenTelemetry trace export over OTLP/HTTP and W3C trace context propagation.
+
+use axum::http::HeaderMap;
+use opentelemetry::{global, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Config as TraceConfig, Tracer, TracerProvider},
+    Resource,
+};
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{
+    config::TelemetryConfig,
+    error::{AppError, Result},
+};
+
+/// Owns the tracer provider; spans are flushed when it is shut down
+pub struct Telemetry {
+    provider: TracerProvider,
+    tracer: Tracer,
+}
+
+impl Telemetry {
+    /// Set up W3C trace context propagation and, when enabled, the OTLP exporter
+    pub fn init(config: &TelemetryConfig) -> Result<Option<Self>> {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let exporter = opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(&config.otlp_endpoint)
+            .build_span_exporter()
+            .map_err(|e| AppError::Internal(format!("Failed to build OTLP exporter: {}", e)))?;
+
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_config(TraceConfig::default().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                config.service_name.clone(),
+            )])))
+            .build();
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+        Ok(Some(Self { provider, tracer }))
+    }
+
+    pub fn layer<S>(&self) -> tracing_opentelemetry::OpenTelemetryLayer<S, Tracer>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer.clone())
+    }
+
+    /// Export any buffered spans. Blocks until the exporter has finished.
+    pub fn flush(&self) {
+        for result in self.provider.force_flush() {
+            if let Err(e) = result {
+                tracing::warn!("Failed to flush spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Continue the caller's trace if the request carries a `traceparent` header
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let carrier: HashMap<String, String> = ["traceparent", "tracestate"]
+        .into_iter()
+        .filter_map(|name| Some((name.to_string(), headers.get(name)?.to_str().ok()?.to_string())))
+        .collect();
+
+    if !carrier.is_empty() {
+        let parent = global::get_text_map_propagator(|propagator| propagator.extract(&carrier));
+        span.set_parent(parent);
+    }
+}
+
+/// Span for one call to the Couchbase management API. The bucket, scope and
+/// collection in the URL become attributes; names in the span name are replaced
+/// by placeholders to keep the number of distinct span names small.
+pub fn couchbase_span(method: &reqwest::Method, path: &str) -> Span {
+    let target = CouchbaseTarget::from_path(path);
+    let span = tracing::info_span!(
+        "couchbase",
+        otel.name = %format!("couchbase {} {}", method, target.route),
+        otel.kind = "client",
+        db.system = "couchbase",
+        http.method = %method,
+        couchbase.bucket = tracing::field::Empty,
+        couchbase.scope = tracing::field::Empty,
+        couchbase.collection = tracing::field::Empty,
+        http.status_code = tracing::field::Empty,
+    );
+
+    if let Some(bucket) = &target.bucket {
+        span.record("couchbase.bucket", bucket.as_str());
+    }
+    if let Some(scope) = &target.scope {
+        span.record("couchbase.scope", scope.as_str());
+    }
+    if let Some(collection) = &target.collection {
+        span.record("couchbase.collection", collection.as_str());
+    }
+    span
+}
+
+/// Add the span's trace context to outgoing request headers
+pub fn inject_context(span: &Span, headers: &mut reqwest::header::HeaderMap) {
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&span.context(), &mut carrier));
+
+    for (name, value) in carrier {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct CouchbaseTarget {
+    route: String,
+    bucket: Option<String>,
+    scope: Option<String>,
+    collection: Option<String>,
+}
+
+impl CouchbaseTarget {
+    fn from_path(path: &str) -> Self {
+        let mut target = CouchbaseTarget::default();
+        let mut route = Vec::new();
+        let mut segments = path.split('/').filter(|s| !s.is_empty());
+
+        while let Some(segment) = segments.next() {
+            route.push(segment.to_string());
+            let (slot, placeholder) = match segment {
+                "buckets" => (&mut target.bucket, "{bucket}"),
+                "scopes" => (&mut target.scope, "{scope}"),
+                "collections" => (&mut target.collection, "{collection}"),
+                "users" | "groups" => {
+                    // Principal names are not recorded, only their shape
+                    route.extend(segments.by_ref().map(|_| "{name}".to_string()));
+                    break;
+                }
+                _ => continue,
+            };
+            if let Some(name) = segments.next() {
+                *slot = Some(name.to_string());
+                route.push(placeholder.to_string());
+            }
+        }
+
+        target.route = format!("/{}", route.join("/"));
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Bytes, extract::State, routing::post, Router};
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use opentelemetry_proto::tonic::common::v1::any_value::Value;
+    use opentelemetry_proto::tonic::trace::v1::Span as ProtoSpan;
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+    type Received = Arc<Mutex<Vec<Bytes>>>;
+
+    // Minimal OTLP/HTTP collector that keeps every export request body
+    async fn start_collector() -> (String, Received) {
+        async fn receive(State(received): State<Received>, body: Bytes) {
+            received.lock().unwrap().push(body);
+        }
+
+        let received = Received::default();
+        let app = Router::new()
+            .route("/v1/traces", post(receive))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (format!("http://{}", addr), received)
+    }
+
+    fn exported_spans(received: &Received) -> Vec<ProtoSpan> {
+        received
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|body| ExportTraceServiceRequest::decode(body.as_ref()).unwrap().resource_spans)
+            .flat_map(|resource| resource.scope_spans)
+            .flat_map(|scope| scope.spans)
+            .collect()
+    }
+
+    fn attribute(span: &ProtoSpan, key: &str) -> Option<String> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key == key)
+            .and_then(|kv| match kv.value.as_ref()?.value.as_ref()? {
+                Value::StringValue(value) => Some(value.clone()),
+                _ => None,
+            })
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn couchbase_target_extracts_keyspace() {
+        let target = CouchbaseTarget::from_path("/pools/default/buckets/travel/scopes/inventory/collections/airline");
+        assert_eq!(target.route, "/pools/default/buckets/{bucket}/scopes/{scope}/collections/{collection}");
+        assert_eq!(target.bucket.as_deref(), Some("travel"));
+        assert_eq!(target.scope.as_deref(), Some("inventory"));
+        assert_eq!(target.collection.as_deref(), Some("airline"));
+
+        let target = CouchbaseTarget::from_path("/settings/rbac/users/local/alice");
+        assert_eq!(target.route, "/settings/rbac/users/{name}/{name}");
+        assert_eq!(target.bucket, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn exports_request_and_couchbase_spans_to_collector() {
+        let (endpoint, received) = start_collector().await;
+        let telemetry = Telemetry::init(&TelemetryConfig {
+            enabled: true,
+            otlp_endpoint: endpoint,
+            service_name: "couchbase-admin-service-test".to_string(),
+        })
+        .unwrap()
+        .unwrap();
+
+        let subscriber = tracing_subscriber::registry().with(telemetry.layer());
+        let injected = tracing::subscriber::with_default(subscriber, || {
+            let request = axum::http::Request::builder()
+                .uri("/buckets/travel/scopes")
+                .header("traceparent", format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID))
+                .body(())
+                .unwrap();
+            let request_span = crate::middleware::make_request_span(&request);
+            let _entered = request_span.enter();
+
+            let couchbase = couchbase_span(
+                &reqwest::Method::GET,
+                "/pools/default/buckets/travel/scopes/inventory/collections/airline",
+            );
+            let mut headers = reqwest::header::HeaderMap::new();
+            inject_context(&couchbase, &mut headers);
+            headers.get("traceparent").unwrap().to_str().unwrap().to_string()
+        });
+
+        // The outgoing call continues the caller's trace
+        assert!(injected.starts_with(&format!("00-{}-", TRACE_ID)));
+
+        tokio::task::spawn_blocking(move || telemetry.flush()).await.unwrap();
+
+        let mut spans = exported_spans(&received);
+        for _ in 0..50 {
+            if spans.len() >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            spans = exported_spans(&received);
+        }
+
+        let request = spans.iter().find(|s| s.name == "GET /buckets/travel/scopes").unwrap();
+        assert_eq!(hex(&request.trace_id), TRACE_ID);
+        assert_eq!(hex(&request.parent_span_id), PARENT_SPAN_ID);
+
+        let call = spans
+            .iter()
+            .find(|s| s.name.starts_with("couchbase GET"))
+            .unwrap();
+        assert_eq!(hex(&call.trace_id), TRACE_ID);
+        assert_eq!(call.parent_span_id, request.span_id);
+        assert_eq!(attribute(call, "couchbase.bucket").as_deref(), Some("travel"));
+        assert_eq!(attribute(call, "couchbase.scope").as_deref(), Some("inventory"));
+        assert_eq!(attribute(call, "couchbase.collection").as_deref(), Some("airline"));
+    }
+}
@@ -0,0 +1,1326 @@
+//! Couchbase naming rules and setting bounds, checked before anything is sent to the cluster.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::models::{
+    AnalyticsLinkSettings, BucketInfo, BucketType, CreateAnalyticsCollectionRequest,
+    CreateAnalyticsLinkRequest, CreateAnalyticsScopeRequest, CreateBucketRequest,
+    CreateCollectionRequest, CreateIndexRequest, CreateRemoteClusterRequest,
+    CreateReplicationRequest, CreateScopeRequest, EventingFunctionDefinition, SearchIndexDefinition,
+    StorageBackend, UpdateBucketRequest, XdcrEncryption, LOCAL_ANALYTICS_LINK, PRIMARY_INDEX_NAME,
+    SEARCH_INDEX_TYPE,
+};
+
+pub const MAX_BUCKET_NAME_LENGTH: usize = 100;
+pub const MAX_SCOPE_NAME_LENGTH: usize = 251;
+pub const MAX_COLLECTION_NAME_LENGTH: usize = 251;
+pub const MIN_RAM_QUOTA_MB: u32 = 100;
+/// Magma buckets with the default 1024 vBuckets need a larger quota
+pub const MIN_MAGMA_RAM_QUOTA_MB: u32 = 1024;
+pub const MAX_REPLICAS: u32 = 3;
+/// Expiry values are signed 32-bit seconds on the server
+pub const MAX_TTL_SECONDS: u32 = i32::MAX as u32;
+/// Non-zero history retention sizes must be at least 2 GiB
+pub const MIN_HISTORY_RETENTION_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+pub const RESERVED_NAMES: &[&str] = &["_default", "_system"];
+
+pub const COMPRESSION_MODES: &[&str] = &["off", "passive", "active"];
+pub const CONFLICT_RESOLUTION_TYPES: &[&str] = &["seqno", "lww", "custom"];
+pub const MAGMA_VBUCKET_COUNTS: &[u32] = &[128, 1024];
+pub const DEFAULT_KEYSPACE_NAME: &str = "_default";
+pub const MAX_EVENTING_FUNCTION_NAME_LENGTH: usize = 100;
+pub const EVENTING_BINDING_ACCESS: &[&str] = &["r", "rw"];
+pub const EVENTING_STREAM_BOUNDARIES: &[&str] = &["everything", "from_now"];
+pub const MAX_ANALYTICS_NAME_LENGTH: usize = 255;
+/// Analytics scope holding the system catalog
+pub const ANALYTICS_METADATA_SCOPE: &str = "Metadata";
+
+/// A problem with one field of a request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn check(&mut self, field: &str, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.add(field, message);
+        }
+    }
+
+    fn finish(self) -> Result<(), Vec<FieldError>> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self.0)
+        }
+    }
+}
+
+/// One line summary of field errors, used as the response message
+pub fn summarize(errors: &[FieldError]) -> String {
+    let details: Vec<String> = errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect();
+    format!("Validation failed: {}", details.join("; "))
+}
+
+/// Bucket names use letters, digits, `_`, `.`, `-` and `%`, and may not start with `.`
+pub fn validate_bucket_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Bucket name cannot be empty".to_string());
+    }
+    if name.len() > MAX_BUCKET_NAME_LENGTH {
+        return Err(format!(
+            "Bucket name cannot be longer than {} characters",
+            MAX_BUCKET_NAME_LENGTH
+        ));
+    }
+    if name.starts_with('.') {
+        return Err("Bucket name cannot start with '.'".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '%')))
+    {
+        return Err(format!(
+            "Bucket name contains invalid character '{}'. Allowed: A-Z, a-z, 0-9, _, ., -, %",
+            c
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_scope_name(name: &str) -> Result<(), String> {
+    validate_keyspace_name("Scope", name, MAX_SCOPE_NAME_LENGTH)
+}
+
+pub fn validate_collection_name(name: &str) -> Result<(), String> {
+    validate_keyspace_name("Collection", name, MAX_COLLECTION_NAME_LENGTH)
+}
+
+// Scope and collection names use letters, digits, `_`, `-` and `%`, and may not
+// start with `_` or `%`, which are kept for system scopes and collections
+fn validate_keyspace_name(kind: &str, name: &str, max_length: usize) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("{} name cannot be empty", kind));
+    }
+    if name.len() > max_length {
+        return Err(format!(
+            "{} name cannot be longer than {} characters",
+            kind, max_length
+        ));
+    }
+    if RESERVED_NAMES.contains(&name) {
+        return Err(format!("{} name '{}' is reserved", kind, name));
+    }
+    if name.starts_with(['_', '%']) {
+        return Err(format!("{} name cannot start with '_' or '%'", kind));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '%')))
+    {
+        return Err(format!(
+            "{} name contains invalid character '{}'. Allowed: A-Z, a-z, 0-9, _, -, %",
+            kind, c
+        ));
+    }
+    Ok(())
+}
+
+fn one_of(field: &str, value: &str, allowed: &[&str]) -> Result<(), String> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid {} '{}'. Allowed: {}",
+            field,
+            value,
+            allowed.join(", ")
+        ))
+    }
+}
+
+fn validate_max_ttl(max_ttl: u32) -> Result<(), String> {
+    if max_ttl > MAX_TTL_SECONDS {
+        return Err(format!("max_ttl cannot exceed {} seconds", MAX_TTL_SECONDS));
+    }
+    Ok(())
+}
+
+/// Smallest per-node RAM quota Couchbase accepts for a bucket
+pub fn min_ram_quota_mb(storage_backend: Option<StorageBackend>, num_vbuckets: Option<u32>) -> u32 {
+    // Magma's higher minimum only applies with the default 1024 vBuckets
+    if storage_backend == Some(StorageBackend::Magma) && num_vbuckets != Some(128) {
+        MIN_MAGMA_RAM_QUOTA_MB
+    } else {
+        MIN_RAM_QUOTA_MB
+    }
+}
+
+/// Check a bucket create request against the naming rules, numeric bounds and
+/// the settings its bucket type supports
+pub fn validate_create_bucket(request: &CreateBucketRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    let bucket_type = request.bucket_type;
+    let memcached = bucket_type == BucketType::Memcached;
+    let magma = request.storage_backend == Some(StorageBackend::Magma);
+
+    errors.check("bucket_name", validate_bucket_name(&request.bucket_name));
+
+    let supported = [
+        ("replica_number", request.replica_number.is_some(), !memcached),
+        ("eviction_policy", request.eviction_policy.is_some(), !memcached),
+        ("compression_mode", request.compression_mode.is_some(), !memcached),
+        ("conflict_resolution_type", request.conflict_resolution_type.is_some(), !memcached),
+        ("max_ttl", request.max_ttl.is_some(), !memcached),
+        ("durability_min_level", request.durability_min_level.is_some(), !memcached),
+        ("storage_backend", request.storage_backend.is_some(), bucket_type == BucketType::Couchbase),
+        ("replica_index", request.replica_index.is_some(), bucket_type == BucketType::Couchbase),
+    ];
+    for (field, given, supported) in supported {
+        if given && !supported {
+            errors.add(field, format!("Not supported for {} buckets", bucket_type));
+        }
+    }
+
+    // History retention and custom vBucket counts are Magma-only features
+    let magma_only = [
+        (
+            "history_retention_collection_default",
+            request.history_retention_collection_default.is_some(),
+        ),
+        ("history_retention_bytes", request.history_retention_bytes.is_some()),
+        ("history_retention_seconds", request.history_retention_seconds.is_some()),
+        ("num_vbuckets", request.num_vbuckets.is_some()),
+    ];
+    for (field, given) in magma_only {
+        if given && !magma {
+            errors.add(field, "Requires storage_backend 'magma'");
+        }
+    }
+
+    if let Some(ram_quota_mb) = request.ram_quota_mb {
+        let minimum = min_ram_quota_mb(request.storage_backend, request.num_vbuckets);
+        if ram_quota_mb < minimum {
+            errors.add("ram_quota_mb", format!("Must be at least {} MB", minimum));
+        }
+    }
+
+    if let Some(replicas) = request.replica_number {
+        if replicas > MAX_REPLICAS {
+            errors.add("replica_number", format!("Must be between 0 and {}", MAX_REPLICAS));
+        }
+    }
+
+    if !memcached {
+        if let Some(policy) = &request.eviction_policy {
+            errors.check(
+                "eviction_policy",
+                one_of("eviction_policy", policy, bucket_type.eviction_policies()),
+            );
+        }
+        if let Some(level) = &request.durability_min_level {
+            errors.check(
+                "durability_min_level",
+                one_of("durability_min_level", level, bucket_type.durability_levels()),
+            );
+        }
+        if let Some(mode) = &request.compression_mode {
+            errors.check("compression_mode", one_of("compression_mode", mode, COMPRESSION_MODES));
+        }
+        if let Some(resolution) = &request.conflict_resolution_type {
+            errors.check(
+                "conflict_resolution_type",
+                one_of("conflict_resolution_type", resolution, CONFLICT_RESOLUTION_TYPES),
+            );
+        }
+        if let Some(max_ttl) = request.max_ttl {
+            errors.check("max_ttl", validate_max_ttl(max_ttl));
+        }
+    }
+
+    if magma {
+        if let Some(bytes) = request.history_retention_bytes {
+            if bytes != 0 && bytes < MIN_HISTORY_RETENTION_BYTES {
+                errors.add(
+                    "history_retention_bytes",
+                    format!("Must be 0 or at least {} bytes", MIN_HISTORY_RETENTION_BYTES),
+                );
+            }
+        }
+        if let Some(num_vbuckets) = request.num_vbuckets {
+            if !MAGMA_VBUCKET_COUNTS.contains(&num_vbuckets) {
+                errors.add("num_vbuckets", "Must be 128 or 1024");
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+/// Check changed settings against the same rules as a create of the existing bucket
+pub fn validate_update_bucket(
+    request: &UpdateBucketRequest,
+    existing: &BucketInfo,
+) -> Result<(), Vec<FieldError>> {
+    let magma = existing.storage_backend == Some(StorageBackend::Magma);
+    let as_create = CreateBucketRequest {
+        bucket_name: existing.name.clone(),
+        bucket_type: existing.bucket_type,
+        ram_quota_mb: request.ram_quota_mb,
+        replica_number: request.replica_number,
+        eviction_policy: request.eviction_policy.clone(),
+        compression_mode: request.compression_mode.clone(),
+        storage_backend: existing.storage_backend,
+        max_ttl: request.max_ttl,
+        flush_enabled: request.flush_enabled,
+        durability_min_level: request.durability_min_level.clone(),
+        // Needed for the Magma RAM minimum
+        num_vbuckets: if magma { existing.num_vbuckets } else { None },
+        ..Default::default()
+    };
+    validate_create_bucket(&as_create)
+}
+
+pub fn validate_create_scope(request: &CreateScopeRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.check("scope_name", validate_scope_name(&request.scope_name));
+    errors.finish()
+}
+
+pub fn validate_create_collection(request: &CreateCollectionRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.check("collection_name", validate_collection_name(&request.collection_name));
+    if let Some(max_ttl) = request.max_ttl {
+        errors.check("max_ttl", validate_max_ttl(max_ttl));
+    }
+    for (i, index) in request.indexes.iter().enumerate() {
+        check_index(&mut errors, &format!("indexes[{}].", i), index);
+    }
+    errors.finish()
+}
+
+/// Index names start with a letter and use letters, digits, `_`, `-` and `#`
+pub fn validate_index_name(name: &str) -> Result<(), String> {
+    if name == PRIMARY_INDEX_NAME {
+        return Ok(());
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err("Index name must start with a letter".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '#')))
+    {
+        return Err(format!(
+            "Index name contains invalid character '{}'. Allowed: A-Z, a-z, 0-9, _, -, #",
+            c
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_create_index(request: &CreateIndexRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    check_index(&mut errors, "", request);
+    errors.finish()
+}
+
+// `prefix` locates the index within a larger request
+fn check_index(errors: &mut Errors, prefix: &str, request: &CreateIndexRequest) {
+    let field = |name: &str| format!("{}{}", prefix, name);
+
+    match &request.index_name {
+        Some(name) => errors.check(&field("index_name"), validate_index_name(name)),
+        None if !request.primary => errors.add(&field("index_name"), "Required for secondary indexes"),
+        None => {}
+    }
+
+    if request.primary {
+        if !request.fields.is_empty() {
+            errors.add(&field("fields"), "Primary indexes do not take fields");
+        }
+        if request.condition.is_some() {
+            errors.add(&field("condition"), "Primary indexes cannot be partial");
+        }
+    } else if request.fields.is_empty() {
+        errors.add(&field("fields"), "At least one field is required for secondary indexes");
+    }
+
+    if request.fields.iter().chain(&request.partition_by).any(|e| e.trim().is_empty()) {
+        errors.add(&field("fields"), "Index expressions cannot be empty");
+    }
+    for (name, expressions) in [("fields", &request.fields), ("partition_by", &request.partition_by)] {
+        if let Some(Err(e)) = expressions.iter().map(|e| validate_expression(e)).find(Result::is_err) {
+            errors.add(&field(name), &e);
+        }
+    }
+    if let Some(condition) = &request.condition {
+        errors.check(&field("condition"), validate_expression(condition));
+    }
+
+    if let Some(num_partition) = request.num_partition {
+        if request.partition_by.is_empty() {
+            errors.add(&field("num_partition"), "Requires partition_by");
+        } else if num_partition == 0 {
+            errors.add(&field("num_partition"), "Must be at least 1");
+        }
+    }
+}
+
+/// N1QL and analytics expressions are pasted into statements as given, so they are
+/// trusted input. This only keeps one from ending its statement, commenting out the
+/// rest of it, or closing the quoting or parentheses it is placed in.
+pub fn validate_expression(expression: &str) -> Result<(), String> {
+    if expression.contains(';') {
+        return Err("Expressions cannot contain ';'".to_string());
+    }
+    if ["--", "/*", "*/", "//"].iter().any(|token| expression.contains(token)) {
+        return Err("Expressions cannot contain comments".to_string());
+    }
+    if !expression.matches('`').count().is_multiple_of(2) {
+        return Err("Expression has an unterminated `identifier`".to_string());
+    }
+
+    let mut depth = 0i32;
+    for c in expression.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            break;
+        }
+    }
+    if depth != 0 {
+        return Err("Expression has unbalanced parentheses".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_search_index_name(name: &str) -> Result<(), String> {
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err("Search index name must start with a letter".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-')))
+    {
+        return Err(format!(
+            "Search index name contains invalid character '{}'. Allowed: A-Z, a-z, 0-9, _, -",
+            c
+        ));
+    }
+    Ok(())
+}
+
+/// Bucket, scope and index names from a search index path, which are placed in search
+/// service URLs. `index_name` is `None` for routes on the whole scope.
+pub fn validate_search_index_path(
+    bucket_name: &str,
+    scope_name: &str,
+    index_name: Option<&str>,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    errors.check("bucket", validate_bucket_name(bucket_name));
+    if scope_name != DEFAULT_KEYSPACE_NAME {
+        errors.check("scope", validate_scope_name(scope_name));
+    }
+    if let Some(index_name) = index_name {
+        errors.check("index", validate_search_index_name(index_name));
+    }
+
+    errors.finish()
+}
+
+/// `index_name` comes from the path. A name in the definition may be the full
+/// `bucket.scope.name` form the search service reports, but must end in the same name.
+pub fn validate_search_index_definition(
+    index_name: &str,
+    definition: &SearchIndexDefinition,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    errors.check("name", validate_search_index_name(index_name));
+    if let Some(name) = &definition.name {
+        if name.rsplit('.').next() != Some(index_name) {
+            errors.add("name", format!("Does not match the index name '{}' in the path", index_name));
+        }
+    }
+
+    if definition.index_type != SEARCH_INDEX_TYPE {
+        errors.add("type", format!("Only '{}' definitions are supported", SEARCH_INDEX_TYPE));
+    }
+
+    if !(definition.params.is_object() || definition.params.is_null()) {
+        errors.add("params", "Must be an object");
+    }
+    for (field, value) in [("planParams", &definition.plan_params), ("sourceParams", &definition.source_params)] {
+        if value.as_ref().is_some_and(|value| !value.is_object()) {
+            errors.add(field, "Must be an object");
+        }
+    }
+
+    errors.finish()
+}
+
+/// Remote cluster reference names use letters, digits, spaces, `_`, `.` and `-`
+pub fn validate_remote_cluster_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Remote cluster name cannot be empty".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, ' ' | '_' | '.' | '-')))
+    {
+        return Err(format!(
+            "Remote cluster name contains invalid character '{}'. Allowed: A-Z, a-z, 0-9, space, _, ., -",
+            c
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_create_remote_cluster(request: &CreateRemoteClusterRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    errors.check("name", validate_remote_cluster_name(&request.name));
+    if request.hostname.trim().is_empty() {
+        errors.add("hostname", "Cannot be empty");
+    } else if request.hostname.contains('/') {
+        errors.add("hostname", "Must be a host or host:port, without a scheme or path");
+    }
+    if request.username.is_empty() {
+        errors.add("username", "Cannot be empty");
+    }
+    if request.password.is_empty() {
+        errors.add("password", "Cannot be empty");
+    }
+    if request.certificate.as_ref().is_some_and(|c| !c.contains("-----BEGIN CERTIFICATE-----")) {
+        errors.add("certificate", "Must be a PEM encoded certificate");
+    }
+
+    errors.finish()
+}
+
+// Number of parts in a `scope` or `scope.collection` namespace of a collection mapping
+fn mapping_namespace_depth(namespace: &str) -> Result<usize, String> {
+    let (scope, collection) = match namespace.split_once('.') {
+        Some((scope, collection)) => (scope, Some(collection)),
+        None => (namespace, None),
+    };
+    if collection.is_some_and(|collection| collection.contains('.')) {
+        return Err(format!("'{}' must be 'scope' or 'scope.collection'", namespace));
+    }
+
+    // Unlike when creating them, default scopes and collections can be named here
+    if scope != DEFAULT_KEYSPACE_NAME {
+        validate_scope_name(scope)?;
+    }
+    if let Some(collection) = collection.filter(|collection| *collection != DEFAULT_KEYSPACE_NAME) {
+        validate_collection_name(collection)?;
+    }
+    Ok(1 + usize::from(collection.is_some()))
+}
+
+pub fn validate_create_replication(request: &CreateReplicationRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    errors.check("source_bucket", validate_bucket_name(&request.source_bucket));
+    errors.check("remote_cluster", validate_remote_cluster_name(&request.remote_cluster));
+    errors.check("target_bucket", validate_bucket_name(&request.target_bucket));
+
+    for (source, target) in &request.collection_mappings {
+        let field = format!("collection_mappings.{}", source);
+        let source_depth = match mapping_namespace_depth(source) {
+            Ok(depth) => depth,
+            Err(message) => {
+                errors.add(&field, message);
+                continue;
+            }
+        };
+        if let Some(target) = target {
+            match mapping_namespace_depth(target) {
+                Ok(depth) if depth != source_depth => errors.add(
+                    &field,
+                    "Scopes must map to scopes and collections to collections",
+                ),
+                Ok(_) => {}
+                Err(message) => errors.add(&field, message),
+            }
+        }
+    }
+
+    if request.filter_expression.as_ref().is_some_and(|e| e.trim().is_empty()) {
+        errors.add("filter_expression", "Cannot be empty");
+    }
+
+    errors.finish()
+}
+
+/// Eventing function names use letters, digits, `_` and `-`, and start with a letter or digit
+pub fn validate_eventing_function_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Function name cannot be empty".to_string());
+    }
+    if name.len() > MAX_EVENTING_FUNCTION_NAME_LENGTH {
+        return Err(format!(
+            "Function name cannot be longer than {} characters",
+            MAX_EVENTING_FUNCTION_NAME_LENGTH
+        ));
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err("Function name must start with a letter or digit".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-')))
+    {
+        return Err(format!(
+            "Function name contains invalid character '{}'. Allowed: A-Z, a-z, 0-9, _, -",
+            c
+        ));
+    }
+    Ok(())
+}
+
+// Handlers refer to bindings by alias, so aliases must be JavaScript identifiers
+fn validate_binding_alias(alias: &str) -> Result<(), String> {
+    let valid = alias.starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '_' | '$'))
+        && alias.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Alias '{}' is not a valid JavaScript identifier", alias))
+    }
+}
+
+/// Checks that need no cluster lookups. `name` comes from the path; an `appname`
+/// in the definition must match it.
+pub fn validate_eventing_function(
+    name: &str,
+    definition: &EventingFunctionDefinition,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    let depcfg = &definition.depcfg;
+
+    errors.check("appname", validate_eventing_function_name(name));
+    if definition.appname.as_ref().is_some_and(|appname| appname != name) {
+        errors.add("appname", format!("Does not match the function name '{}' in the path", name));
+    }
+    if definition.appcode.trim().is_empty() {
+        errors.add("appcode", "Cannot be empty");
+    }
+
+    errors.check("depcfg.source_bucket", validate_bucket_name(&depcfg.source_bucket));
+    errors.check("depcfg.metadata_bucket", validate_bucket_name(&depcfg.metadata_bucket));
+    let source = (&depcfg.source_bucket, &depcfg.source_scope, &depcfg.source_collection);
+    let metadata = (&depcfg.metadata_bucket, &depcfg.metadata_scope, &depcfg.metadata_collection);
+    if source == metadata {
+        errors.add(
+            "depcfg.metadata_collection",
+            "The metadata collection must differ from the source collection",
+        );
+    }
+
+    let mut aliases = HashSet::new();
+    for (i, binding) in depcfg.buckets.iter().enumerate() {
+        let field = |name: &str| format!("depcfg.buckets[{}].{}", i, name);
+        errors.check(&field("alias"), validate_binding_alias(&binding.alias));
+        if !aliases.insert(binding.alias.as_str()) {
+            errors.add(&field("alias"), format!("Alias '{}' is used more than once", binding.alias));
+        }
+        errors.check(&field("bucket_name"), validate_bucket_name(&binding.bucket_name));
+        errors.check(&field("access"), one_of("access", &binding.access, EVENTING_BINDING_ACCESS));
+    }
+
+    if let Some(boundary) = definition.settings.get("dcp_stream_boundary") {
+        let boundary = boundary.as_str().unwrap_or_default();
+        errors.check(
+            "settings.dcp_stream_boundary",
+            one_of("dcp_stream_boundary", boundary, EVENTING_STREAM_BOUNDARIES),
+        );
+    }
+
+    errors.finish()
+}
+
+/// Analytics scope, collection and link names use letters, digits, `_` and `-`,
+/// and start with a letter
+pub fn validate_analytics_name(kind: &str, name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("{} name cannot be empty", kind));
+    }
+    if name.len() > MAX_ANALYTICS_NAME_LENGTH {
+        return Err(format!(
+            "{} name cannot be longer than {} characters",
+            kind, MAX_ANALYTICS_NAME_LENGTH
+        ));
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(format!("{} name must start with a letter", kind));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-')))
+    {
+        return Err(format!(
+            "{} name contains invalid character '{}'. Allowed: A-Z, a-z, 0-9, _, -",
+            kind, c
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_create_analytics_scope(request: &CreateAnalyticsScopeRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.check("scope_name", validate_analytics_name("Analytics scope", &request.scope_name));
+    if request.scope_name == ANALYTICS_METADATA_SCOPE {
+        errors.add("scope_name", format!("Analytics scope name '{}' is reserved", ANALYTICS_METADATA_SCOPE));
+    }
+    errors.finish()
+}
+
+pub fn validate_create_analytics_collection(
+    request: &CreateAnalyticsCollectionRequest,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    let source = &request.source;
+
+    errors.check(
+        "collection_name",
+        validate_analytics_name("Analytics collection", &request.collection_name),
+    );
+    errors.check("link_name", validate_analytics_name("Link", &request.link_name));
+
+    errors.check("source.bucket", validate_bucket_name(&source.bucket));
+    // Default scopes and collections can be a source
+    if source.scope != DEFAULT_KEYSPACE_NAME {
+        errors.check("source.scope", validate_scope_name(&source.scope));
+    }
+    if source.collection != DEFAULT_KEYSPACE_NAME {
+        errors.check("source.collection", validate_collection_name(&source.collection));
+    }
+
+    match &request.filter {
+        Some(filter) if filter.trim().is_empty() => errors.add("filter", "Cannot be empty"),
+        Some(filter) => errors.check("filter", validate_expression(filter)),
+        None => {}
+    }
+
+    errors.finish()
+}
+
+pub fn validate_create_analytics_link(request: &CreateAnalyticsLinkRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+
+    errors.check("link_name", validate_analytics_name("Link", &request.link_name));
+    if request.link_name == LOCAL_ANALYTICS_LINK {
+        errors.add("link_name", format!("Link name '{}' is reserved", LOCAL_ANALYTICS_LINK));
+    }
+
+    match &request.settings {
+        AnalyticsLinkSettings::Local => errors.add(
+            "type",
+            "Local links exist in every analytics scope and cannot be created",
+        ),
+        AnalyticsLinkSettings::Couchbase {
+            hostname,
+            username,
+            password,
+            encryption,
+            certificate,
+        } => {
+            if hostname.trim().is_empty() {
+                errors.add("hostname", "Cannot be empty");
+            } else if hostname.contains('/') {
+                errors.add("hostname", "Must be a host or host:port, without a scheme or path");
+            }
+            if username.is_empty() {
+                errors.add("username", "Cannot be empty");
+            }
+            if password.is_empty() {
+                errors.add("password", "Cannot be empty");
+            }
+            match certificate {
+                Some(certificate) if !certificate.contains("-----BEGIN CERTIFICATE-----") => {
+                    errors.add("certificate", "Must be a PEM encoded certificate")
+                }
+                None if *encryption == XdcrEncryption::Full => {
+                    errors.add("certificate", "Required for full encryption")
+                }
+                _ => {}
+            }
+        }
+        AnalyticsLinkSettings::S3 {
+            access_key_id,
+            secret_access_key,
+            region,
+            ..
+        } => {
+            for (field, value) in [
+                ("access_key_id", access_key_id),
+                ("secret_access_key", secret_access_key),
+                ("region", region),
+            ] {
+                if value.trim().is_empty() {
+                    errors.add(field, "Cannot be empty");
+                }
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(name: &str) -> CreateBucketRequest {
+        serde_json::from_value(serde_json::json!({ "bucket_name": name })).unwrap()
+    }
+
+    fn fields(result: Result<(), Vec<FieldError>>) -> Vec<String> {
+        result.unwrap_err().into_iter().map(|e| e.field).collect()
+    }
+
+    #[test]
+    fn bucket_names() {
+        for name in ["travel-sample", "beer_sample", "a", "v1.0", "50%", &"b".repeat(100)] {
+            assert!(validate_bucket_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", ".hidden", "has space", "slash/name", "ünicode", &"b".repeat(101)] {
+            assert!(validate_bucket_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn scope_and_collection_names() {
+        for name in ["inventory", "air-line", "a%b", "A_1", &"s".repeat(251)] {
+            assert!(validate_scope_name(name).is_ok(), "{}", name);
+            assert!(validate_collection_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "_private", "%pct", "has.dot", "has space", &"s".repeat(252)] {
+            assert!(validate_scope_name(name).is_err(), "{}", name);
+            assert!(validate_collection_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn reserved_names_are_rejected() {
+        for name in RESERVED_NAMES {
+            assert!(validate_scope_name(name).unwrap_err().contains("reserved"));
+            assert!(validate_collection_name(name).unwrap_err().contains("reserved"));
+        }
+    }
+
+    #[test]
+    fn minimal_bucket_is_valid() {
+        assert!(validate_create_bucket(&bucket("travel")).is_ok());
+    }
+
+    #[test]
+    fn ram_quota_bounds() {
+        let mut request = bucket("travel");
+        request.ram_quota_mb = Some(99);
+        assert_eq!(fields(validate_create_bucket(&request)), ["ram_quota_mb"]);
+
+        request.ram_quota_mb = Some(100);
+        assert!(validate_create_bucket(&request).is_ok());
+
+        request.storage_backend = Some(StorageBackend::Magma);
+        assert_eq!(fields(validate_create_bucket(&request)), ["ram_quota_mb"]);
+
+        request.num_vbuckets = Some(128);
+        assert!(validate_create_bucket(&request).is_ok());
+    }
+
+    #[test]
+    fn replica_bounds() {
+        let mut request = bucket("travel");
+        request.replica_number = Some(3);
+        assert!(validate_create_bucket(&request).is_ok());
+
+        request.replica_number = Some(4);
+        assert_eq!(fields(validate_create_bucket(&request)), ["replica_number"]);
+    }
+
+    #[test]
+    fn eviction_policy_depends_on_bucket_type() {
+        let mut request = bucket("cache");
+        request.eviction_policy = Some("nruEviction".to_string());
+        assert_eq!(fields(validate_create_bucket(&request)), ["eviction_policy"]);
+
+        request.bucket_type = BucketType::Ephemeral;
+        assert!(validate_create_bucket(&request).is_ok());
+
+        request.eviction_policy = Some("fullEviction".to_string());
+        assert_eq!(fields(validate_create_bucket(&request)), ["eviction_policy"]);
+    }
+
+    #[test]
+    fn ephemeral_durability_is_limited() {
+        let mut request = bucket("cache");
+        request.bucket_type = BucketType::Ephemeral;
+        request.durability_min_level = Some("majority".to_string());
+        assert!(validate_create_bucket(&request).is_ok());
+
+        request.durability_min_level = Some("persistToMajority".to_string());
+        assert_eq!(fields(validate_create_bucket(&request)), ["durability_min_level"]);
+    }
+
+    #[test]
+    fn memcached_rejects_persistence_settings() {
+        let mut request = bucket("legacy");
+        request.bucket_type = BucketType::Memcached;
+        request.flush_enabled = Some(true);
+        assert!(validate_create_bucket(&request).is_ok());
+
+        request.replica_number = Some(1);
+        request.storage_backend = Some(StorageBackend::Couchstore);
+        request.max_ttl = Some(60);
+        assert_eq!(
+            fields(validate_create_bucket(&request)),
+            ["replica_number", "max_ttl", "storage_backend"]
+        );
+    }
+
+    #[test]
+    fn magma_only_settings() {
+        let mut request = bucket("history");
+        request.history_retention_bytes = Some(MIN_HISTORY_RETENTION_BYTES);
+        request.num_vbuckets = Some(128);
+        assert_eq!(
+            fields(validate_create_bucket(&request)),
+            ["history_retention_bytes", "num_vbuckets"]
+        );
+
+        request.storage_backend = Some(StorageBackend::Magma);
+        assert!(validate_create_bucket(&request).is_ok());
+
+        request.history_retention_bytes = Some(1024);
+        request.num_vbuckets = Some(512);
+        assert_eq!(
+            fields(validate_create_bucket(&request)),
+            ["history_retention_bytes", "num_vbuckets"]
+        );
+    }
+
+    #[test]
+    fn enumerated_settings() {
+        let mut request = bucket("travel");
+        request.compression_mode = Some("zstd".to_string());
+        request.conflict_resolution_type = Some("newest".to_string());
+        request.max_ttl = Some(u32::MAX);
+        assert_eq!(
+            fields(validate_create_bucket(&request)),
+            ["compression_mode", "conflict_resolution_type", "max_ttl"]
+        );
+    }
+
+    #[test]
+    fn every_bad_field_is_reported() {
+        let mut request = bucket(".bad name");
+        request.ram_quota_mb = Some(10);
+        request.replica_number = Some(9);
+        let errors = validate_create_bucket(&request).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(summarize(&errors).starts_with("Validation failed: bucket_name: "));
+    }
+
+    #[test]
+    fn update_uses_existing_bucket_type() {
+        let existing: BucketInfo = serde_json::from_value(serde_json::json!({
+            "name": "cache",
+            "bucket_type": "ephemeral",
+            "ram_quota_mb": 256,
+            "replica_number": 1,
+            "eviction_policy": "noEviction",
+            "compression_mode": "passive",
+            "conflict_resolution_type": "seqno",
+            "storage_backend": null,
+            "max_ttl": 0,
+            "flush_enabled": false,
+            "durability_min_level": "none",
+            "replica_index": null,
+            "history_retention_collection_default": null,
+            "history_retention_bytes": null,
+            "history_retention_seconds": null,
+            "num_vbuckets": null,
+            "status": "healthy"
+        }))
+        .unwrap();
+
+        let mut request = UpdateBucketRequest {
+            ram_quota_mb: Some(512),
+            eviction_policy: Some("nruEviction".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_update_bucket(&request, &existing).is_ok());
+
+        request.ram_quota_mb = Some(50);
+        request.durability_min_level = Some("persistToMajority".to_string());
+        assert_eq!(
+            fields(validate_update_bucket(&request, &existing)),
+            ["ram_quota_mb", "durability_min_level"]
+        );
+    }
+
+    #[test]
+    fn index_requests() {
+        let primary = CreateIndexRequest {
+            primary: true,
+            ..Default::default()
+        };
+        assert!(validate_create_index(&primary).is_ok());
+
+        let secondary = CreateIndexRequest {
+            index_name: Some("idx_airline_country".to_string()),
+            fields: vec!["country".to_string(), "name".to_string()],
+            partition_by: vec!["META().id".to_string()],
+            num_partition: Some(8),
+            ..Default::default()
+        };
+        assert!(validate_create_index(&secondary).is_ok());
+
+        let unnamed = CreateIndexRequest {
+            fields: vec!["country".to_string()],
+            num_partition: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(fields(validate_create_index(&unnamed)), ["index_name", "num_partition"]);
+
+        let bad = CreateIndexRequest {
+            index_name: Some("1`; DROP".to_string()),
+            primary: true,
+            fields: vec!["country".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(fields(validate_create_index(&bad)), ["index_name", "fields"]);
+
+        let injected = CreateIndexRequest {
+            index_name: Some("idx".to_string()),
+            fields: vec!["country".to_string(), "name); DROP INDEX idx".to_string()],
+            partition_by: vec!["`type".to_string()],
+            condition: Some("type = 'airline' -- and more".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            fields(validate_create_index(&injected)),
+            ["fields", "partition_by", "condition"]
+        );
+    }
+
+    #[test]
+    fn expressions() {
+        for valid in ["country", "LOWER(`name`)", "DISTINCT ARRAY s.day FOR s IN schedule END", "(a + b) * 2"] {
+            assert!(validate_expression(valid).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "a; DROP INDEX idx",
+            "a -- rest",
+            "a /* rest */",
+            "a */",
+            "a // rest",
+            "`name",
+            "a) WHERE (b",
+            "LOWER(name",
+            "a)",
+        ] {
+            assert!(validate_expression(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn collection_indexes_are_located() {
+        let request = CreateCollectionRequest {
+            collection_name: "airline".to_string(),
+            max_ttl: None,
+            history: None,
+            indexes: vec![
+                CreateIndexRequest {
+                    primary: true,
+                    ..Default::default()
+                },
+                CreateIndexRequest::default(),
+            ],
+        };
+        assert_eq!(
+            fields(validate_create_collection(&request)),
+            ["indexes[1].index_name", "indexes[1].fields"]
+        );
+    }
+
+    #[test]
+    fn collection_request() {
+        let request = CreateCollectionRequest {
+            collection_name: "_system".to_string(),
+            max_ttl: Some(u32::MAX),
+            history: None,
+            indexes: vec![],
+        };
+        assert_eq!(
+            fields(validate_create_collection(&request)),
+            ["collection_name", "max_ttl"]
+        );
+        assert!(validate_create_scope(&CreateScopeRequest {
+            scope_name: "inventory".to_string()
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn search_index_paths() {
+        assert!(validate_search_index_path("travel-sample", "inventory", Some("hotels")).is_ok());
+        assert!(validate_search_index_path("travel-sample", "_default", None).is_ok());
+
+        assert_eq!(
+            fields(validate_search_index_path("..", "..", Some(".."))),
+            ["bucket", "scope", "index"]
+        );
+        assert_eq!(
+            fields(validate_search_index_path("travel", "inventory", Some("../../api/cfg"))),
+            ["index"]
+        );
+        assert_eq!(fields(validate_search_index_path("travel/..", "_system", None)), ["bucket", "scope"]);
+    }
+
+    #[test]
+    fn search_index_definitions() {
+        let definition = |json: serde_json::Value| -> SearchIndexDefinition {
+            serde_json::from_value(json).unwrap()
+        };
+
+        let exported = definition(serde_json::json!({
+            "type": "fulltext-index",
+            "name": "travel-sample.inventory.hotels",
+            "uuid": "6c9e1c9d3f3a2b1e",
+            "sourceType": "gocbcore",
+            "sourceName": "travel-sample",
+            "params": {"mapping": {"default_mapping": {"enabled": false}}},
+            "planParams": {"indexPartitions": 1}
+        }));
+        assert!(validate_search_index_definition("hotels", &exported).is_ok());
+        assert!(validate_search_index_definition("hotels", &definition(serde_json::json!({}))).is_ok());
+
+        let bad = definition(serde_json::json!({
+            "type": "fulltext-alias",
+            "name": "inventory.landmarks",
+            "params": [],
+            "planParams": 1
+        }));
+        assert_eq!(
+            fields(validate_search_index_definition("hotels", &bad)),
+            ["name", "type", "params", "planParams"]
+        );
+        assert_eq!(
+            fields(validate_search_index_definition("1hotels", &definition(serde_json::json!({})))),
+            ["name"]
+        );
+    }
+
+    #[test]
+    fn remote_cluster_requests() {
+        let request = CreateRemoteClusterRequest {
+            name: "eu-west".to_string(),
+            hostname: "cb.eu-west.example.com:8091".to_string(),
+            username: "xdcr".to_string(),
+            password: "secret".to_string(),
+            encryption: Default::default(),
+            certificate: None,
+        };
+        assert!(validate_create_remote_cluster(&request).is_ok());
+
+        let bad = CreateRemoteClusterRequest {
+            name: "eu/west".to_string(),
+            hostname: "https://cb.eu-west.example.com".to_string(),
+            password: String::new(),
+            certificate: Some("not a certificate".to_string()),
+            ..request
+        };
+        assert_eq!(
+            fields(validate_create_remote_cluster(&bad)),
+            ["name", "hostname", "password", "certificate"]
+        );
+    }
+
+    #[test]
+    fn replication_collection_mappings() {
+        let mut request = CreateReplicationRequest {
+            source_bucket: "travel".to_string(),
+            remote_cluster: "eu-west".to_string(),
+            target_bucket: "travel".to_string(),
+            ..Default::default()
+        };
+        request.collection_mappings = [
+            ("inventory", Some("inventory")),
+            ("_default._default", Some("legacy.documents")),
+            ("tenants.internal", None),
+        ]
+        .into_iter()
+        .map(|(source, target)| (source.to_string(), target.map(str::to_string)))
+        .collect();
+        assert!(validate_create_replication(&request).is_ok());
+
+        request.collection_mappings = [
+            ("inventory", Some("inventory.airline")),
+            ("a.b.c", Some("a")),
+            ("_system", None),
+        ]
+        .into_iter()
+        .map(|(source, target)| (source.to_string(), target.map(str::to_string)))
+        .collect();
+        request.filter_expression = Some(" ".to_string());
+        assert_eq!(
+            fields(validate_create_replication(&request)),
+            [
+                "collection_mappings._system",
+                "collection_mappings.a.b.c",
+                "collection_mappings.inventory",
+                "filter_expression"
+            ]
+        );
+    }
+
+    #[test]
+    fn eventing_functions() {
+        let definition = |json: serde_json::Value| -> EventingFunctionDefinition {
+            serde_json::from_value(json).unwrap()
+        };
+
+        let exported = definition(serde_json::json!({
+            "appname": "audit_hotels",
+            "appcode": "function OnUpdate(doc, meta) { audit[meta.id] = doc; }",
+            "depcfg": {
+                "source_bucket": "travel",
+                "source_scope": "inventory",
+                "source_collection": "hotel",
+                "metadata_bucket": "travel",
+                "metadata_scope": "eventing",
+                "metadata_collection": "metadata",
+                "buckets": [{
+                    "alias": "audit",
+                    "bucket_name": "travel",
+                    "scope_name": "inventory",
+                    "collection_name": "hotel_audit",
+                    "access": "rw"
+                }]
+            },
+            "settings": {"dcp_stream_boundary": "everything", "deployment_status": true}
+        }));
+        assert!(validate_eventing_function("audit_hotels", &exported).is_ok());
+        assert_eq!(exported.depcfg.buckets[0].scope_name, "inventory");
+
+        let bad = definition(serde_json::json!({
+            "appname": "other",
+            "appcode": " ",
+            "depcfg": {
+                "source_bucket": "travel",
+                "metadata_bucket": "travel",
+                "buckets": [
+                    {"alias": "a-b", "bucket_name": "travel", "access": "w"},
+                    {"alias": "src", "bucket_name": "travel", "access": "r"},
+                    {"alias": "src", "bucket_name": ".travel", "access": "r"}
+                ]
+            },
+            "settings": {"dcp_stream_boundary": "latest"}
+        }));
+        assert_eq!(
+            fields(validate_eventing_function("audit_hotels", &bad)),
+            [
+                "appname",
+                "appcode",
+                "depcfg.metadata_collection",
+                "depcfg.buckets[0].alias",
+                "depcfg.buckets[0].access",
+                "depcfg.buckets[2].alias",
+                "depcfg.buckets[2].bucket_name",
+                "settings.dcp_stream_boundary"
+            ]
+        );
+    }
+
+    #[test]
+    fn analytics_requests() {
+        let collection: CreateAnalyticsCollectionRequest = serde_json::from_value(serde_json::json!({
+            "collection_name": "hotels",
+            "source": {"bucket": "travel", "scope": "inventory", "collection": "hotel"},
+            "filter": "country = \"France\""
+        }))
+        .unwrap();
+        assert_eq!(collection.link_name, LOCAL_ANALYTICS_LINK);
+        assert!(validate_create_analytics_collection(&collection).is_ok());
+
+        let from_default: CreateAnalyticsCollectionRequest = serde_json::from_value(serde_json::json!({
+            "collection_name": "_all",
+            "source": {"bucket": "travel", "scope": "_system"}
+        }))
+        .unwrap();
+        assert_eq!(from_default.source.collection, "_default");
+        assert_eq!(
+            fields(validate_create_analytics_collection(&from_default)),
+            ["collection_name", "source.scope"]
+        );
+
+        for filter in [
+            "country = \"France\"; DROP ANALYTICS COLLECTION hotels",
+            "country = \"France\" -- rest",
+            "country = \"France\" /* rest */",
+            "`country = \"France\"",
+        ] {
+            let injected: CreateAnalyticsCollectionRequest = serde_json::from_value(serde_json::json!({
+                "collection_name": "hotels",
+                "source": {"bucket": "travel", "scope": "inventory", "collection": "hotel"},
+                "filter": filter
+            }))
+            .unwrap();
+            assert_eq!(fields(validate_create_analytics_collection(&injected)), ["filter"], "{}", filter);
+        }
+
+        let link = |json: serde_json::Value| -> CreateAnalyticsLinkRequest { serde_json::from_value(json).unwrap() };
+        assert!(validate_create_analytics_link(&link(serde_json::json!({
+            "link_name": "reporting",
+            "type": "s3",
+            "access_key_id": "AKIA",
+            "secret_access_key": "secret",
+            "region": "eu-west-1"
+        })))
+        .is_ok());
+        assert_eq!(
+            fields(validate_create_analytics_link(&link(serde_json::json!({
+                "link_name": "Local",
+                "type": "couchbase",
+                "hostname": "cb.eu-west.example.com",
+                "username": "analytics",
+                "password": "",
+                "encryption": "full"
+            })))),
+            ["link_name", "password", "certificate"]
+        );
+        assert_eq!(
+            fields(validate_create_analytics_link(&link(serde_json::json!({
+                "link_name": "mine",
+                "type": "local"
+            })))),
+            ["type"]
+        );
+    }
+}